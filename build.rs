@@ -1,12 +1,180 @@
 use build_print::info;
-use std::{
-    collections::HashMap, collections::HashSet, env, fmt::Write, fs, path::Path,
-    path::MAIN_SEPARATOR,
-};
+use prost::Message;
+use std::{collections::HashSet, env, fmt::Write, fs, path::Path};
+use tonic_prost_build::FileDescriptorSet;
 use walkdir::WalkDir;
 
 type Res = Result<(), Box<dyn std::error::Error>>;
 
+// Service families selected by cargo features, as service proto file names. Without the
+// `all-services` feature only the services of the enabled families are generated, along
+// with the resources, enums and other messages their files import.
+const SERVICE_FAMILIES: &[(&str, &[&str])] = &[
+    (
+        "reporting",
+        &["customer_service", "google_ads_field_service"],
+    ),
+    (
+        "campaign-management",
+        &[
+            "ad_group_ad_label_service",
+            "ad_group_ad_service",
+            "ad_group_bid_modifier_service",
+            "ad_group_criterion_customizer_service",
+            "ad_group_criterion_label_service",
+            "ad_group_criterion_service",
+            "ad_group_customizer_service",
+            "ad_group_label_service",
+            "ad_group_service",
+            "ad_parameter_service",
+            "ad_service",
+            "batch_job_service",
+            "bidding_data_exclusion_service",
+            "bidding_seasonality_adjustment_service",
+            "bidding_strategy_service",
+            "campaign_bid_modifier_service",
+            "campaign_budget_service",
+            "campaign_criterion_service",
+            "campaign_customizer_service",
+            "campaign_draft_service",
+            "campaign_group_service",
+            "campaign_label_service",
+            "campaign_service",
+            "campaign_shared_set_service",
+            "customer_customizer_service",
+            "customer_label_service",
+            "customer_negative_criterion_service",
+            "customizer_attribute_service",
+            "experiment_arm_service",
+            "experiment_service",
+            "geo_target_constant_service",
+            "keyword_theme_constant_service",
+            "label_service",
+            "recommendation_service",
+            "recommendation_subscription_service",
+            "shared_criterion_service",
+            "shared_set_service",
+            "smart_campaign_setting_service",
+        ],
+    ),
+    (
+        "assets",
+        &[
+            "ad_group_asset_service",
+            "ad_group_asset_set_service",
+            "asset_generation_service",
+            "asset_group_asset_service",
+            "asset_group_listing_group_filter_service",
+            "asset_group_service",
+            "asset_group_signal_service",
+            "asset_service",
+            "asset_set_asset_service",
+            "asset_set_service",
+            "automatically_created_asset_removal_service",
+            "brand_suggestion_service",
+            "campaign_asset_service",
+            "campaign_asset_set_service",
+            "customer_asset_service",
+            "customer_asset_set_service",
+            "shareable_preview_service",
+            "travel_asset_suggestion_service",
+            "youtube_video_upload_service",
+        ],
+    ),
+    (
+        "conversions",
+        &[
+            "campaign_conversion_goal_service",
+            "campaign_goal_config_service",
+            "campaign_lifecycle_goal_service",
+            "conversion_action_service",
+            "conversion_adjustment_upload_service",
+            "conversion_custom_variable_service",
+            "conversion_goal_campaign_config_service",
+            "conversion_upload_service",
+            "conversion_value_rule_service",
+            "conversion_value_rule_set_service",
+            "custom_conversion_goal_service",
+            "customer_conversion_goal_service",
+            "customer_lifecycle_goal_service",
+            "customer_sk_ad_network_conversion_value_schema_service",
+            "goal_service",
+            "remarketing_action_service",
+        ],
+    ),
+    (
+        "audiences",
+        &[
+            "audience_insights_service",
+            "audience_service",
+            "content_creator_insights_service",
+            "custom_audience_service",
+            "custom_interest_service",
+            "offline_user_data_job_service",
+            "user_data_service",
+            "user_list_customer_type_service",
+            "user_list_service",
+        ],
+    ),
+    (
+        "planning",
+        &[
+            "benchmarks_service",
+            "keyword_plan_ad_group_keyword_service",
+            "keyword_plan_ad_group_service",
+            "keyword_plan_campaign_keyword_service",
+            "keyword_plan_campaign_service",
+            "keyword_plan_idea_service",
+            "keyword_plan_service",
+            "reach_plan_service",
+            "smart_campaign_suggest_service",
+        ],
+    ),
+    (
+        "account-management",
+        &[
+            "account_budget_proposal_service",
+            "account_link_service",
+            "billing_setup_service",
+            "customer_client_link_service",
+            "customer_manager_link_service",
+            "customer_user_access_invitation_service",
+            "customer_user_access_service",
+            "data_link_service",
+            "identity_verification_service",
+            "incentive_service",
+            "invoice_service",
+            "local_services_lead_service",
+            "payments_account_service",
+            "product_link_invitation_service",
+            "product_link_service",
+            "reservation_service",
+            "third_party_app_analytics_link_service",
+        ],
+    ),
+];
+
+// GoogleAdsService is always generated: the crate's own helpers are built on it
+const REQUIRED_SERVICES: &[&str] = &["google_ads_service"];
+
+// Files generated even when no service imports them, e.g. GoogleAdsFailure
+const REQUIRED_FILES: &[&str] = &["errors.proto"];
+
+// Returns the service proto files selected by the enabled features, or None for all
+fn selected_services() -> Option<HashSet<&'static str>> {
+    if env::var_os("CARGO_FEATURE_ALL_SERVICES").is_some() {
+        return None;
+    }
+    let mut selected: HashSet<&str> = REQUIRED_SERVICES.iter().copied().collect();
+    for (feature, services) in SERVICE_FAMILIES {
+        let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+        if env::var_os(var).is_some() {
+            selected.extend(services.iter().copied());
+        }
+    }
+    Some(selected)
+}
+
 // replace:
 //   "ROOT DIR": root dir of proto files to generate
 //   "INCLUDE DIR": where all "package" specifier based on
@@ -15,6 +183,14 @@ type Res = Result<(), Box<dyn std::error::Error>>;
 fn main() -> Res {
     let mut protos = vec![];
     let mut pkgs = HashSet::new();
+    let mut services: Vec<(String, String)> = vec![];
+    // Files given to protoc for codegen: the selected services and required files
+    let mut codegen_protos = vec![];
+    let selection = selected_services();
+    let is_selected = |stem: &str| match &selection {
+        Some(selected) => selected.contains(stem),
+        None => true,
+    };
 
     let proto_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("proto");
     info!("Proto path: {:?}", &proto_path);
@@ -51,6 +227,25 @@ fn main() -> Res {
             .trim_start_matches("package ")
             .trim_end_matches(';');
 
+        let stem = path.file_stem().unwrap().to_str().unwrap();
+        let is_service = pkg.starts_with("google.ads.googleads.") && pkg.ends_with(".services");
+        if selection.is_none()
+            || (is_service && is_selected(stem))
+            || REQUIRED_FILES.contains(&path.file_name().unwrap().to_str().unwrap())
+        {
+            codegen_protos.push(path.to_owned());
+        }
+
+        // collect the selected googleads services for the GoogleAdsClient accessors
+        if is_service && is_selected(stem) {
+            for line in content.lines() {
+                if let Some(rest) = line.strip_prefix("service ") {
+                    let name = rest.trim_end_matches('{').trim();
+                    services.push((pkg.to_string(), name.to_string()));
+                }
+            }
+        }
+
         pkgs.insert(pkg.to_string());
     }
 
@@ -59,20 +254,39 @@ fn main() -> Res {
     } else {
         info!("Number of proto files: {}", protos.len());
     }
+    if let Some(selected) = &selection {
+        for service in selected {
+            if !protos
+                .iter()
+                .any(|p| p.file_stem().is_some_and(|s| s == *service))
+            {
+                return Err(format!("Service proto not found: {}.proto", service).into());
+            }
+        }
+    }
 
-    // Generate unified file descriptor set for prost-reflect
+    // Generate unified file descriptor sets:
+    //   file_descriptor_set.bin is embedded for prost-reflect, always with every proto file
+    //   codegen_descriptor_set.bin has the selected services and their imports, and also
+    //   keeps source info, for doc comments in generated code
     // Use response file approach to avoid Windows command line length limits
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR environment variable not set");
+    let descriptor_path = Path::new(&out_dir).join("file_descriptor_set.bin");
+    let codegen_descriptor_path = Path::new(&out_dir).join("codegen_descriptor_set.bin");
     {
-        let out_dir = env::var("OUT_DIR").expect("OUT_DIR environment variable not set");
-        let descriptor_path = Path::new(&out_dir).join("file_descriptor_set.bin");
-
-        // Write proto paths to a response file (one path per line)
+        // Write proto paths to response files (one path per line)
         let response_file = Path::new(&out_dir).join("proto_files.txt");
-        let mut response_content = String::new();
-        for proto in &protos {
-            writeln!(response_content, "{}", proto.display())?;
+        let codegen_response_file = Path::new(&out_dir).join("codegen_proto_files.txt");
+        for (file, protos) in [
+            (&response_file, &protos),
+            (&codegen_response_file, &codegen_protos),
+        ] {
+            let mut response_content = String::new();
+            for proto in protos {
+                writeln!(response_content, "{}", proto.display())?;
+            }
+            fs::write(file, &response_content)?;
         }
-        fs::write(&response_file, &response_content)?;
 
         // Find protoc executable (respects PROTOC env var)
         let protoc = env::var_os("PROTOC")
@@ -82,89 +296,120 @@ fn main() -> Res {
 
         info!("Using protoc: {:?}", protoc);
 
-        // Build protoc command with response file (@file syntax)
-        let status = std::process::Command::new(&protoc)
-            .arg("--experimental_allow_proto3_optional")
-            .arg(format!("--proto_path={}", proto_path.display()))
-            .arg(format!(
-                "--descriptor_set_out={}",
-                descriptor_path.display()
-            ))
-            .arg("--include_imports")
-            .arg(format!("@{}", response_file.display()))
-            .status()
-            .map_err(|e| format!("Failed to execute protoc at {:?}: {}", protoc, e))?;
-
-        if !status.success() {
-            return Err(format!("protoc failed with status: {}", status).into());
-        }
-
-        info!("Generated file descriptor set at {:?}", descriptor_path);
-    }
+        for (path, response_file, include_source_info) in [
+            (&descriptor_path, &response_file, false),
+            (&codegen_descriptor_path, &codegen_response_file, true),
+        ] {
+            // Build protoc command with response file (@file syntax)
+            let mut command = std::process::Command::new(&protoc);
+            command
+                .arg("--experimental_allow_proto3_optional")
+                .arg(format!("--proto_path={}", proto_path.display()))
+                .arg(format!("--descriptor_set_out={}", path.display()))
+                .arg("--include_imports");
+            if include_source_info {
+                command.arg("--include_source_info");
+            }
+            let status = command
+                .arg(format!("@{}", response_file.display()))
+                .status()
+                .map_err(|e| format!("Failed to execute protoc at {:?}: {}", protoc, e))?;
 
-    let package_names = ["common", "enums", "errors", "resources", "services"];
-    let mut protos_by_package: HashMap<&str, Vec<_>> = HashMap::new();
-    let mut misc_protos: Vec<_> = Vec::new();
-
-    for proto in &protos {
-        let path_str = proto.to_str().unwrap();
-        let mut matched = false;
-
-        for &package in &package_names {
-            let pkg_str = format!("{MAIN_SEPARATOR}{package}{MAIN_SEPARATOR}");
-            if path_str.contains(&pkg_str) {
-                protos_by_package
-                    .entry(package)
-                    .or_insert_with(Vec::new)
-                    .push(proto.clone());
-                matched = true;
-                // info!("added to {token}: {path_str}");
-                break;
+            if !status.success() {
+                return Err(format!("protoc failed with status: {}", status).into());
             }
-        }
 
-        if !matched {
-            // info!("Misc proto: {}", path_str);
-            misc_protos.push(proto.clone());
+            info!("Generated file descriptor set at {:?}", path);
         }
     }
 
-    if !misc_protos.is_empty() {
-        info!("> Compiling {} misc proto files", misc_protos.len());
-        tonic_prost_build::configure()
-            .build_server(false)
-            .type_attribute(".", "#[allow(clippy::all)]")
-            .protoc_arg("--experimental_allow_proto3_optional")
-            .compile_protos(&misc_protos, std::slice::from_ref(&proto_path))?;
-    }
-
-    for &package in &package_names {
-        if let Some(protos) = protos_by_package.get(package) {
-            info!(
-                "> Compiling {} proto files from package '{}'",
-                protos.len(),
-                package
-            );
-            for chunk in protos.chunks(185) {
-                info!(
-                    "  Compiling batch of {} proto files from package '{}'",
-                    chunk.len(),
-                    package
-                );
-                tonic_prost_build::configure()
-                    .build_server(false)
-                    .type_attribute(".", "#[allow(clippy::all)]")
-                    .protoc_arg("--experimental_allow_proto3_optional")
-                    .compile_protos(chunk, std::slice::from_ref(&proto_path))?;
-            }
+    // Imported service files bring their operation messages, e.g. GoogleAdsService's
+    // MutateOperation imports every mutate service; only generate the selected services
+    let mut codegen_fds = FileDescriptorSet::decode(&*fs::read(&codegen_descriptor_path)?)?;
+    for file in &mut codegen_fds.file {
+        let stem = file
+            .name()
+            .rsplit('/')
+            .next()
+            .unwrap()
+            .trim_end_matches(".proto");
+        let is_service = file.package().starts_with("google.ads.googleads.")
+            && file.package().ends_with(".services");
+        if is_service && !is_selected(stem) {
+            file.service.clear();
         }
     }
+    let generated: HashSet<&str> = codegen_fds.file.iter().map(|f| f.package()).collect();
+    pkgs.retain(|pkg| generated.contains(pkg.as_str()));
+
+    // Generate code for all packages at once from the unified descriptor set. Compiling
+    // in batches regenerates every imported package with only the imported files, so the
+    // last batch would overwrite e.g. the errors package with a partial module.
+    // Server stubs are only needed by the fake services of the `server` feature
+    info!(
+        "> Compiling {} of {} proto files with their imports, {} services",
+        codegen_protos.len(),
+        protos.len(),
+        services.len()
+    );
+    tonic_prost_build::configure()
+        .build_server(env::var_os("CARGO_FEATURE_SERVER").is_some())
+        .type_attribute(".", "#[allow(clippy::all)]")
+        .compile_fds(codegen_fds)?;
 
     write_protos_rs(pkgs)?;
+    write_clients_rs(services)?;
+
+    Ok(())
+}
+
+// Generates a GoogleAdsClient accessor for each googleads service, e.g.
+// `GoogleAdsService` -> `client.google_ads()` returning a `GoogleAdsServiceClient`.
+fn write_clients_rs(mut services: Vec<(String, String)>) -> Res {
+    services.sort();
+    let clients_rs = &mut String::new();
+
+    writeln!(clients_rs, "impl GoogleAdsClient {{")?;
+    for (pkg, service) in &services {
+        let module = format!(
+            "crate::{}",
+            pkg.split('.')
+                .map(map_keyword)
+                .collect::<Vec<_>>()
+                .join("::")
+        );
+        let snake = to_snake_case(service);
+        let accessor = map_keyword(snake.trim_end_matches("_service"));
+        writeln!(clients_rs, "    /// Returns a client for `{}`.", service)?;
+        writeln!(
+            clients_rs,
+            "    pub fn {accessor}(&self) -> {module}::{snake}_client::{service}Client<GoogleAdsChannel> {{"
+        )?;
+        writeln!(
+            clients_rs,
+            "        configure_client!(self, {module}::{snake}_client::{service}Client::with_interceptor(self.channel.clone(), self.interceptor.clone()))"
+        )?;
+        writeln!(clients_rs, "    }}")?;
+    }
+    writeln!(clients_rs, "}}")?;
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR environment variable not set");
+    fs::write(Path::new(&out_dir).join("clients.rs"), clients_rs)?;
 
     Ok(())
 }
 
+fn to_snake_case(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 4);
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            result.push('_');
+        }
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
 fn write_protos_rs(pkgs: HashSet<String>) -> Res {
     let protos_rs = &mut String::new();
 
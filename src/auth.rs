@@ -0,0 +1,544 @@
+//! OAuth2 authentication for the Google Ads API.
+//!
+//! An [`Authenticator`] exchanges credentials for access tokens at a token endpoint,
+//! caches them, and refreshes them shortly before they expire or when the API answers
+//! `UNAUTHENTICATED`. Tokens come from a [`TokenSource`]:
+//!
+//! - [`RefreshTokenCredentials`]: the installed-app / web flow with a client id, client
+//!   secret and refresh token.
+//! - [`ServiceAccountCredentials`]: a service account JSON key signing RS256 JWT
+//!   assertions, optionally impersonating a user through domain-wide delegation.
+//!
+//! The [`GoogleAdsInterceptor`](crate::client::GoogleAdsInterceptor) is synchronous, so it
+//! only reads the cached token: fetch the first token with [`Authenticator::access_token`]
+//! (or wrap calls in [`Authenticator::call`]), after which tokens close to expiry are
+//! renewed in the background.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let credentials = RefreshTokenCredentials::new(CLIENT_ID, CLIENT_SECRET, REFRESH_TOKEN);
+//! let authenticator = Authenticator::new(credentials);
+//!
+//! // or, for a service account impersonating an admin user
+//! let mut credentials = ServiceAccountCredentials::from_file("service-account.json")?;
+//! credentials.impersonate("admin@example.com");
+//! let authenticator = Authenticator::new(credentials);
+//! authenticator.access_token().await?;
+//!
+//! let interceptor = GoogleAdsInterceptor::new(DEVELOPER_TOKEN)?
+//!     .with_authenticator(authenticator.clone());
+//! let client = GoogleAdsClient::connect(interceptor).await?;
+//!
+//! // Retried once with a fresh token if the API answers UNAUTHENTICATED
+//! let response = authenticator
+//!     .call(|| client.google_ads().search(request.clone()))
+//!     .await?;
+//! ```
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use futures::future::BoxFuture;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::Deserialize;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tonic::{Code, Status};
+
+/// Google's OAuth2 token endpoint.
+pub const DEFAULT_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+
+/// OAuth2 scope of the Google Ads API.
+pub const ADWORDS_SCOPE: &str = "https://www.googleapis.com/auth/adwords";
+
+/// Grant type for exchanging a signed JWT assertion for an access token.
+pub const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// How long before expiry a cached token is renewed, by default.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// How long background renewals wait after a failed refresh before trying again.
+pub const BACKGROUND_REFRESH_BACKOFF: Duration = Duration::from_secs(5);
+
+/// An OAuth2 access token and the instant it expires.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+impl AccessToken {
+    /// Returns true if the token expires within `margin` from now.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        Instant::now() + margin >= self.expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(Duration::ZERO)
+    }
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("token", &"<redacted>")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// A source of OAuth2 access tokens.
+pub trait TokenSource: Send + Sync + 'static {
+    /// Fetches a new access token.
+    fn fetch_token<'a>(
+        &'a self,
+        http: &'a reqwest::Client,
+    ) -> BoxFuture<'a, anyhow::Result<AccessToken>>;
+}
+
+// ----------------------------------------------------------------------------
+// Token endpoint
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Posts a form to a token endpoint and parses the access token response.
+pub(crate) async fn request_token(
+    http: &reqwest::Client,
+    token_endpoint: &str,
+    form: &[(&str, &str)],
+) -> anyhow::Result<AccessToken> {
+    let requested_at = Instant::now();
+    let response = http.post(token_endpoint).form(form).send().await?;
+    let status = response.status();
+    let body = response.bytes().await?;
+
+    if !status.is_success() {
+        return Err(match serde_json::from_slice::<TokenErrorResponse>(&body) {
+            Ok(error) => anyhow::anyhow!(
+                "Token endpoint returned {}: {}{}",
+                status,
+                error.error,
+                error
+                    .error_description
+                    .map(|d| format!(" ({})", d))
+                    .unwrap_or_default()
+            ),
+            Err(_) => anyhow::anyhow!("Token endpoint returned {}", status),
+        });
+    }
+
+    let token: TokenResponse = serde_json::from_slice(&body)
+        .map_err(|e| anyhow::anyhow!("Invalid token endpoint response: {}", e))?;
+    Ok(AccessToken {
+        token: token.access_token,
+        // Google tokens last one hour when the endpoint does not say otherwise
+        expires_at: requested_at + Duration::from_secs(token.expires_in.unwrap_or(3600)),
+    })
+}
+
+// ----------------------------------------------------------------------------
+// Refresh token credentials
+// ----------------------------------------------------------------------------
+
+/// OAuth2 client id, client secret and refresh token.
+#[derive(Clone)]
+pub struct RefreshTokenCredentials {
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+    token_endpoint: String,
+}
+
+impl RefreshTokenCredentials {
+    pub fn new(client_id: &str, client_secret: &str, refresh_token: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            refresh_token: refresh_token.to_string(),
+            token_endpoint: DEFAULT_TOKEN_ENDPOINT.to_string(),
+        }
+    }
+
+    /// Sets the token endpoint URL (defaults to [`DEFAULT_TOKEN_ENDPOINT`]).
+    pub fn token_endpoint(&mut self, url: &str) -> &mut Self {
+        self.token_endpoint = url.to_string();
+        self
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+}
+
+impl fmt::Debug for RefreshTokenCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshTokenCredentials")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("refresh_token", &"<redacted>")
+            .field("token_endpoint", &self.token_endpoint)
+            .finish()
+    }
+}
+
+impl TokenSource for RefreshTokenCredentials {
+    fn fetch_token<'a>(
+        &'a self,
+        http: &'a reqwest::Client,
+    ) -> BoxFuture<'a, anyhow::Result<AccessToken>> {
+        Box::pin(async move {
+            request_token(
+                http,
+                &self.token_endpoint,
+                &[
+                    ("grant_type", "refresh_token"),
+                    ("client_id", &self.client_id),
+                    ("client_secret", &self.client_secret),
+                    ("refresh_token", &self.refresh_token),
+                ],
+            )
+            .await
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Service account credentials
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    private_key_id: Option<String>,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+/// Service account key signing RS256 JWT assertions, optionally impersonating a user
+/// through domain-wide delegation.
+#[derive(Clone)]
+pub struct ServiceAccountCredentials {
+    client_email: String,
+    private_key_id: Option<String>,
+    key_pair: Arc<RsaKeyPair>,
+    impersonated_email: Option<String>,
+    scope: String,
+    token_endpoint: String,
+}
+
+impl ServiceAccountCredentials {
+    /// Reads a service account JSON key file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// Parses a service account JSON key.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let key: ServiceAccountKey = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("Invalid service account key: {}", e))?;
+        let der = pem_to_der(&key.private_key)?;
+        let key_pair = RsaKeyPair::from_pkcs8(&der)
+            .map_err(|e| anyhow::anyhow!("Invalid service account private key: {}", e))?;
+
+        Ok(Self {
+            client_email: key.client_email,
+            private_key_id: key.private_key_id,
+            key_pair: Arc::new(key_pair),
+            impersonated_email: None,
+            scope: ADWORDS_SCOPE.to_string(),
+            token_endpoint: key
+                .token_uri
+                .unwrap_or_else(|| DEFAULT_TOKEN_ENDPOINT.to_string()),
+        })
+    }
+
+    /// Impersonates a user of the domain (the JWT `sub` claim).
+    pub fn impersonate(&mut self, email: &str) -> &mut Self {
+        self.impersonated_email = Some(email.to_string());
+        self
+    }
+
+    /// Sets the OAuth2 scope (defaults to [`ADWORDS_SCOPE`]).
+    pub fn scope(&mut self, scope: &str) -> &mut Self {
+        self.scope = scope.to_string();
+        self
+    }
+
+    /// Sets the token endpoint URL, which is also the JWT audience (defaults to the
+    /// key's `token_uri`).
+    pub fn token_endpoint(&mut self, url: &str) -> &mut Self {
+        self.token_endpoint = url.to_string();
+        self
+    }
+
+    pub fn client_email(&self) -> &str {
+        &self.client_email
+    }
+
+    pub fn impersonated_email(&self) -> Option<&str> {
+        self.impersonated_email.as_deref()
+    }
+
+    /// Builds and signs the JWT assertion exchanged for an access token, valid for one hour.
+    pub fn jwt(&self) -> anyhow::Result<String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let mut header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        if let Some(kid) = &self.private_key_id {
+            header["kid"] = kid.clone().into();
+        }
+        let mut claims = serde_json::json!({
+            "iss": self.client_email,
+            "scope": self.scope,
+            "aud": self.token_endpoint,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        if let Some(sub) = &self.impersonated_email {
+            claims["sub"] = sub.clone().into();
+        }
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let mut signature = vec![0; self.key_pair.public().modulus_len()];
+        self.key_pair
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                signing_input.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to sign JWT"))?;
+
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+}
+
+impl fmt::Debug for ServiceAccountCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceAccountCredentials")
+            .field("client_email", &self.client_email)
+            .field("private_key", &"<redacted>")
+            .field("impersonated_email", &self.impersonated_email)
+            .field("scope", &self.scope)
+            .field("token_endpoint", &self.token_endpoint)
+            .finish()
+    }
+}
+
+impl TokenSource for ServiceAccountCredentials {
+    fn fetch_token<'a>(
+        &'a self,
+        http: &'a reqwest::Client,
+    ) -> BoxFuture<'a, anyhow::Result<AccessToken>> {
+        Box::pin(async move {
+            let assertion = self.jwt()?;
+            request_token(
+                http,
+                &self.token_endpoint,
+                &[
+                    ("grant_type", JWT_BEARER_GRANT_TYPE),
+                    ("assertion", &assertion),
+                ],
+            )
+            .await
+        })
+    }
+}
+
+/// Decodes the base64 body of a PEM block.
+fn pem_to_der(pem: &str) -> anyhow::Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    STANDARD
+        .decode(body)
+        .map_err(|e| anyhow::anyhow!("Invalid PEM private key: {}", e))
+}
+
+// ----------------------------------------------------------------------------
+// Authenticator
+// ----------------------------------------------------------------------------
+
+struct Inner {
+    source: Box<dyn TokenSource>,
+    http: reqwest::Client,
+    cached: RwLock<Option<AccessToken>>,
+    refresh_lock: tokio::sync::Mutex<()>,
+    refreshing: AtomicBool,
+    last_failure: Mutex<Option<Instant>>,
+}
+
+/// Caching access token provider shared by clients and interceptors.
+///
+/// Cloning is cheap: clones share the cached token.
+#[derive(Clone)]
+pub struct Authenticator {
+    inner: Arc<Inner>,
+    refresh_margin: Duration,
+}
+
+impl Authenticator {
+    pub fn new<S: TokenSource>(source: S) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                source: Box::new(source),
+                http: reqwest::Client::new(),
+                cached: RwLock::new(None),
+                refresh_lock: tokio::sync::Mutex::new(()),
+                refreshing: AtomicBool::new(false),
+                last_failure: Mutex::new(None),
+            }),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+        }
+    }
+
+    /// Sets how long before expiry a cached token is renewed.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Returns a valid access token, fetching a new one if the cached token is missing
+    /// or expires within the refresh margin.
+    pub async fn access_token(&self) -> anyhow::Result<String> {
+        if let Some(token) = self.fresh_token() {
+            return Ok(token);
+        }
+
+        // Only one refresh at a time; waiters reuse the token it fetched
+        let _guard = self.inner.refresh_lock.lock().await;
+        if let Some(token) = self.fresh_token() {
+            return Ok(token);
+        }
+        let token = self.inner.source.fetch_token(&self.inner.http).await?;
+        let value = token.token.clone();
+        *self.inner.cached.write().unwrap() = Some(token);
+        Ok(value)
+    }
+
+    /// Returns the cached token without waiting, or `None` if there is no unexpired token.
+    ///
+    /// If the token expires within the refresh margin, a renewal is started in the
+    /// background on the current tokio runtime, unless one is already running or the
+    /// last one failed less than [`BACKGROUND_REFRESH_BACKOFF`] ago.
+    pub fn cached_token(&self) -> Option<String> {
+        let cached = self.inner.cached.read().unwrap().clone();
+        match cached {
+            Some(token) if !token.expires_within(self.refresh_margin) => Some(token.token),
+            Some(token) if !token.is_expired() => {
+                self.refresh_in_background();
+                Some(token.token)
+            }
+            _ => {
+                self.refresh_in_background();
+                None
+            }
+        }
+    }
+
+    /// Drops the cached token so the next call fetches a new one.
+    pub fn invalidate(&self) {
+        *self.inner.cached.write().unwrap() = None;
+    }
+
+    /// Runs a call with a valid token, invalidating the token and retrying once if the
+    /// call fails with `UNAUTHENTICATED`.
+    pub async fn call<F, Fut, T>(&self, mut f: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        self.access_token()
+            .await
+            .map_err(|e| Status::unauthenticated(e.to_string()))?;
+        match f().await {
+            Err(status) if status.code() == Code::Unauthenticated => {
+                self.invalidate();
+                self.access_token()
+                    .await
+                    .map_err(|e| Status::unauthenticated(e.to_string()))?;
+                f().await
+            }
+            result => result,
+        }
+    }
+
+    fn fresh_token(&self) -> Option<String> {
+        self.inner
+            .cached
+            .read()
+            .unwrap()
+            .as_ref()
+            .filter(|token| !token.expires_within(self.refresh_margin))
+            .map(|token| token.token.clone())
+    }
+
+    fn refresh_in_background(&self) {
+        let backing_off = self
+            .inner
+            .last_failure
+            .lock()
+            .unwrap()
+            .is_some_and(|at| at.elapsed() < BACKGROUND_REFRESH_BACKOFF);
+        // Only one background refresh at a time, and none right after a failure
+        if backing_off || self.inner.refreshing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            self.inner.refreshing.store(false, Ordering::Release);
+            return;
+        };
+        let this = self.clone();
+        handle.spawn(async move {
+            let result = this.access_token().await;
+            // The cached token is still served until it expires; say why it was not renewed
+            if let Err(e) = &result {
+                log::warn!("Background access token refresh failed: {:#}", e);
+            }
+            *this.inner.last_failure.lock().unwrap() = result.is_err().then(Instant::now);
+            this.inner.refreshing.store(false, Ordering::Release);
+        });
+    }
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("cached", &self.inner.cached.read().unwrap())
+            .field("refresh_margin", &self.refresh_margin)
+            .finish()
+    }
+}
@@ -0,0 +1,555 @@
+//! Record-and-replay of gRPC traffic, for offline integration tests.
+//!
+//! [`CassetteLayer`] is a tower layer for the channel under the generated clients. In
+//! [`CassetteMode::Record`] it passes calls through and appends each finished call to a
+//! cassette file: the method, the request, every response message (all the batches of a
+//! stream) and the final status, including its `GoogleAdsFailure`. In
+//! [`CassetteMode::Replay`] it never calls the channel it wraps and answers each call with
+//! the next recorded call for the same method and normalized request, so tests run
+//! offline against real captured responses.
+//!
+//! A cassette is a JSON Lines file with one call per line. Messages are stored in protobuf
+//! text format, encoded and decoded with the descriptor pool, so cassettes can be reviewed
+//! and edited by hand. Requests are matched on their text format after normalizing the
+//! GAQL of their `query` field, so reformatting a query does not invalidate a cassette.
+//! Calls with the same method and request are replayed in the order they were recorded.
+//!
+//! Headers are not recorded apart from `request-id`: credentials never reach the file.
+//!
+//! # Example
+//!
+//! ```ignore
+//! // GOOGLE_ADS_CASSETTE_MODE=record cargo test  # talks to the API and records
+//! // cargo test                                  # replays offline
+//! let cassette = CassetteLayer::open("tests/fixtures/campaigns.jsonl", CassetteMode::from_env())?;
+//! let channel = ServiceBuilder::new()
+//!     .layer(cassette.clone())
+//!     .service(Channel::from_static("https://googleads.googleapis.com").connect_lazy());
+//! let mut service = GoogleAdsServiceClient::with_interceptor(channel, interceptor);
+//! // ... run the code under test ...
+//! assert_eq!(cassette.unplayed(), 0);
+//! ```
+
+use crate::descriptor_pool;
+use crate::error::{
+    GOOGLE_ADS_FAILURE_FQN, GOOGLE_ADS_FAILURE_METADATA_KEY, REQUEST_ID_METADATA_KEY,
+};
+use crate::gaql::normalize_gaql;
+use crate::logging::method_name;
+use crate::middleware::{
+    method_descriptor, split_messages, take_ready, BufferedRequest, CallObserver, ObservedBody,
+};
+use anyhow::{anyhow, Context as _};
+use bytes::{BufMut, BytesMut};
+use futures::future::BoxFuture;
+use http_body::Frame;
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+
+/// Environment variable selecting the mode of [`CassetteMode::from_env`].
+pub const CASSETTE_MODE_ENV: &str = "GOOGLE_ADS_CASSETTE_MODE";
+
+/// Whether a [`CassetteLayer`] records calls or replays them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+impl CassetteMode {
+    /// Returns [`CassetteMode::Record`] if `GOOGLE_ADS_CASSETTE_MODE` is `record`, and
+    /// [`CassetteMode::Replay`] otherwise.
+    pub fn from_env() -> Self {
+        match std::env::var(CASSETTE_MODE_ENV) {
+            Ok(mode) if mode.eq_ignore_ascii_case("record") => Self::Record,
+            _ => Self::Replay,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Cassette file
+// ---------------------------------------------------------------------------
+
+// One line of a cassette
+#[derive(Debug, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    request: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(default)]
+    responses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<RecordedStatus>,
+}
+
+// A non-OK final status
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedStatus {
+    code: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failure: Option<String>,
+}
+
+impl RecordedStatus {
+    fn from_status(status: &Status) -> Option<Self> {
+        if status.code() == Code::Ok {
+            return None;
+        }
+        let failure = status
+            .metadata()
+            .get_bin(GOOGLE_ADS_FAILURE_METADATA_KEY)
+            .and_then(|value| value.to_bytes().ok())
+            .and_then(|bytes| DynamicMessage::decode(failure_descriptor(), bytes).ok())
+            .map(|failure| failure.to_text_format());
+        Some(Self {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+            failure,
+        })
+    }
+
+    fn to_status(&self) -> anyhow::Result<Status> {
+        let mut metadata = MetadataMap::new();
+        if let Some(failure) = &self.failure {
+            let failure = DynamicMessage::parse_text_format(failure_descriptor(), failure)?;
+            metadata.insert_bin(
+                GOOGLE_ADS_FAILURE_METADATA_KEY,
+                MetadataValue::from_bytes(&failure.encode_to_vec()),
+            );
+        }
+        Ok(Status::with_metadata(
+            Code::from_i32(self.code),
+            self.message.clone(),
+            metadata,
+        ))
+    }
+}
+
+fn failure_descriptor() -> MessageDescriptor {
+    descriptor_pool()
+        .get_message_by_name(GOOGLE_ADS_FAILURE_FQN)
+        .expect("GoogleAdsFailure is in the descriptor pool")
+}
+
+// Requests are matched on method and text format, with the GAQL `query` normalized
+fn match_key(method: &str, mut request: DynamicMessage) -> (String, String) {
+    if let Some(Value::String(query)) = request.get_field_by_name("query").map(|v| v.into_owned()) {
+        let normalized = normalize_gaql(&query).unwrap_or_else(|_| query.trim().to_string());
+        request.set_field_by_name("query", Value::String(normalized));
+    }
+    (method.to_string(), request.to_text_format())
+}
+
+// Frames an encoded message as an uncompressed gRPC message
+fn frame_message(message: &[u8]) -> Bytes {
+    let mut framed = BytesMut::with_capacity(5 + message.len());
+    framed.put_u8(0);
+    framed.put_u32(message.len() as u32);
+    framed.put_slice(message);
+    framed.freeze()
+}
+
+// A recorded call, ready to be served
+struct Replayed {
+    request_id: Option<String>,
+    messages: Vec<Bytes>,
+    status: Option<Status>,
+}
+
+impl Replayed {
+    fn parse(interaction: Interaction) -> anyhow::Result<((String, String), Self)> {
+        let method = method_descriptor(&interaction.method)
+            .ok_or_else(|| anyhow!("Unknown method {}", interaction.method))?;
+        let request = DynamicMessage::parse_text_format(method.input(), &interaction.request)?;
+        let messages = interaction
+            .responses
+            .iter()
+            .map(|response| {
+                let message = DynamicMessage::parse_text_format(method.output(), response)?;
+                Ok(frame_message(&message.encode_to_vec()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let status = interaction
+            .status
+            .as_ref()
+            .map(RecordedStatus::to_status)
+            .transpose()?;
+        Ok((
+            match_key(&interaction.method, request),
+            Self {
+                request_id: interaction.request_id,
+                messages,
+                status,
+            },
+        ))
+    }
+
+    fn into_response<B>(self) -> http::Response<CassetteBody<B>> {
+        let mut trailers = http::HeaderMap::new();
+        // Statuses come from the descriptor pool or from valid headers
+        let _ = self
+            .status
+            .unwrap_or_else(|| Status::ok(""))
+            .add_header(&mut trailers);
+        let frames = self
+            .messages
+            .into_iter()
+            .map(Frame::data)
+            .chain(std::iter::once(Frame::trailers(trailers)))
+            .collect();
+
+        let mut response = http::Response::new(CassetteBody {
+            recorded: None,
+            replay: frames,
+        });
+        let headers = response.headers_mut();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/grpc"),
+        );
+        if let Some(value) = self
+            .request_id
+            .and_then(|id| http::HeaderValue::from_str(&id).ok())
+        {
+            headers.insert(REQUEST_ID_METADATA_KEY, value);
+        }
+        response
+    }
+}
+
+enum Tape {
+    Record(Mutex<File>),
+    Replay(Mutex<HashMap<(String, String), VecDeque<Replayed>>>),
+}
+
+impl Tape {
+    fn write(&self, interaction: &Interaction) {
+        let Tape::Record(file) = self else {
+            return;
+        };
+        let result = serde_json::to_string(interaction)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(writeln!(file.lock().unwrap(), "{}", line)?));
+        if let Err(err) = result {
+            log::warn!("{} not recorded: {}", method_name(&interaction.method), err);
+        }
+    }
+
+    fn take(&self, key: &(String, String)) -> Option<Replayed> {
+        match self {
+            Tape::Replay(calls) => calls.lock().unwrap().get_mut(key)?.pop_front(),
+            Tape::Record(_) => None,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Layer
+// ---------------------------------------------------------------------------
+
+/// Tower layer recording calls to, or replaying them from, a cassette file.
+///
+/// Clones share the cassette.
+#[derive(Clone)]
+pub struct CassetteLayer {
+    mode: CassetteMode,
+    tape: Arc<Tape>,
+}
+
+impl fmt::Debug for CassetteLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CassetteLayer")
+            .field("mode", &self.mode)
+            .field("unplayed", &self.unplayed())
+            .finish()
+    }
+}
+
+impl CassetteLayer {
+    /// Opens `path` for recording or replay.
+    pub fn open(path: impl AsRef<Path>, mode: CassetteMode) -> anyhow::Result<Self> {
+        match mode {
+            CassetteMode::Record => Self::record(path),
+            CassetteMode::Replay => Self::replay(path),
+        }
+    }
+
+    /// Records calls to `path`, replacing any previous cassette.
+    pub fn record(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create cassette {}", path.display()))?;
+        Ok(Self {
+            mode: CassetteMode::Record,
+            tape: Arc::new(Tape::Record(Mutex::new(file))),
+        })
+    }
+
+    /// Loads the cassette at `path` for replay.
+    pub fn replay(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open cassette {}", path.display()))?;
+        let mut calls: HashMap<(String, String), VecDeque<Replayed>> = HashMap::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, replayed) = serde_json::from_str(&line)
+                .map_err(anyhow::Error::from)
+                .and_then(Replayed::parse)
+                .with_context(|| format!("Invalid call at {}:{}", path.display(), index + 1))?;
+            calls.entry(key).or_default().push_back(replayed);
+        }
+        Ok(Self {
+            mode: CassetteMode::Replay,
+            tape: Arc::new(Tape::Replay(Mutex::new(calls))),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Returns the number of recorded calls not replayed yet (always 0 when recording).
+    pub fn unplayed(&self) -> usize {
+        match self.tape.as_ref() {
+            Tape::Replay(calls) => calls.lock().unwrap().values().map(VecDeque::len).sum(),
+            Tape::Record(_) => 0,
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for CassetteLayer {
+    type Service = Cassette<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Cassette {
+            inner,
+            tape: self.tape.clone(),
+        }
+    }
+}
+
+/// gRPC channel recording or replaying calls; see the [module documentation](self).
+#[derive(Clone)]
+pub struct Cassette<S> {
+    inner: S,
+    tape: Arc<Tape>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Cassette<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cassette")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, ResBody> tower::Service<http::Request<tonic::body::Body>> for Cassette<S>
+where
+    S: tower::Service<http::Request<tonic::body::Body>, Response = http::Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: fmt::Display,
+{
+    type Response = http::Response<CassetteBody<ResBody>>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.tape.as_ref() {
+            Tape::Record(_) => self.inner.poll_ready(cx).map_err(Into::into),
+            // Replay never calls the inner service
+            Tape::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let mut inner = take_ready(&mut self.inner);
+        let tape = self.tape.clone();
+
+        Box::pin(async move {
+            let request = BufferedRequest::new(request).await?;
+            let path = request.path().to_string();
+
+            if let Tape::Replay(_) = tape.as_ref() {
+                let Some(message) = request.message().cloned() else {
+                    let status = Status::invalid_argument(format!(
+                        "Cassette cannot decode the {} request",
+                        method_name(&path)
+                    ));
+                    return Ok(status.into_http());
+                };
+                let key = match_key(&path, message);
+                return Ok(match tape.take(&key) {
+                    Some(replayed) => replayed.into_response(),
+                    None => Status::not_found(format!(
+                        "Cassette has no unplayed {} call for request: {}",
+                        method_name(&path),
+                        key.1
+                    ))
+                    .into_http(),
+                });
+            }
+
+            let capture = match (request.method(), request.message()) {
+                (Some(method), Some(message)) => Some(Capture {
+                    tape,
+                    path: path.clone(),
+                    output: method.output(),
+                    request: message.to_text_format(),
+                    request_id: None,
+                    buffer: BytesMut::new(),
+                    responses: Vec::new(),
+                }),
+                _ => {
+                    log::warn!("{} not recorded: undecodable request", method_name(&path));
+                    None
+                }
+            };
+            let response = inner
+                .call(request.into_request())
+                .await
+                .map_err(Into::into)?;
+            let response = match capture {
+                Some(capture) => ObservedBody::observe(response, capture),
+                None => ObservedBody::unobserved(response),
+            };
+            Ok(response.map(|body| CassetteBody {
+                recorded: Some(body),
+                replay: VecDeque::new(),
+            }))
+        })
+    }
+}
+
+// A call being recorded
+struct Capture {
+    tape: Arc<Tape>,
+    path: String,
+    output: MessageDescriptor,
+    request: String,
+    request_id: Option<String>,
+    buffer: BytesMut,
+    responses: Vec<String>,
+}
+
+impl CallObserver for Capture {
+    fn headers(&mut self, headers: &http::HeaderMap) {
+        self.request_id = headers
+            .get(REQUEST_ID_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+    }
+
+    fn data(&mut self, data: &Bytes) {
+        self.buffer.extend_from_slice(data);
+        for (compressed, bytes) in split_messages(&mut self.buffer) {
+            let rendered = (!compressed)
+                .then(|| DynamicMessage::decode(self.output.clone(), bytes).ok())
+                .flatten();
+            match rendered {
+                Some(message) => self.responses.push(message.to_text_format()),
+                None => {
+                    log::warn!(
+                        "{} not recorded: undecodable response",
+                        method_name(&self.path)
+                    );
+                    self.responses.clear();
+                    self.request.clear();
+                }
+            }
+        }
+    }
+
+    fn finish(self: Box<Self>, status: Status) {
+        if self.request.is_empty() {
+            return;
+        }
+        self.tape.write(&Interaction {
+            method: self.path,
+            request: self.request,
+            request_id: self.request_id,
+            responses: self.responses,
+            status: RecordedStatus::from_status(&status),
+        });
+    }
+
+    // Broken and unfinished calls are not recorded: they could not be replayed faithfully
+    fn abort(self: Box<Self>, _status: Status) {}
+}
+
+/// Response body of a [`Cassette`]: the recorded body, or the replayed call.
+pub struct CassetteBody<B> {
+    recorded: Option<ObservedBody<B>>,
+    replay: VecDeque<Frame<Bytes>>,
+}
+
+// Replayed errors are answered with an empty body
+impl<B> Default for CassetteBody<B> {
+    fn default() -> Self {
+        Self {
+            recorded: None,
+            replay: VecDeque::new(),
+        }
+    }
+}
+
+impl<B> Body for CassetteBody<B>
+where
+    B: Body<Data = Bytes>,
+    B::Error: fmt::Display,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let this = self.get_mut();
+        match this.recorded.as_mut() {
+            Some(recorded) => Pin::new(recorded).poll_frame(cx),
+            None => Poll::Ready(this.replay.pop_front().map(Ok)),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.recorded {
+            Some(recorded) => recorded.is_end_stream(),
+            None => self.replay.is_empty(),
+        }
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        match &self.recorded {
+            Some(recorded) => recorded.size_hint(),
+            None => http_body::SizeHint::default(),
+        }
+    }
+}
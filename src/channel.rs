@@ -0,0 +1,158 @@
+//! Channel configuration for the Google Ads API endpoint.
+//!
+//! [`ChannelBuilder`] holds the transport settings that suit `googleads.googleapis.com`:
+//! TLS with the system's native root certificates, HTTP/2 keep-alive pings so idle
+//! connections behind NATs and load balancers are not dropped silently, connect and
+//! request timeouts, and a maximum decoded message size large enough for 10,000-row
+//! `SearchStream` batches. Gzip compression of requests and responses is optional.
+//!
+//! The endpoint can be overridden, e.g. with the `http://` URL of a local stand-in such
+//! as `server::FakeServer`; TLS is only used for `https://` endpoints.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let client = ChannelBuilder::new()
+//!     .request_timeout(Some(Duration::from_secs(600)))
+//!     .gzip(true)
+//!     .connect_client(interceptor)
+//!     .await?;
+//!
+//! // Or a bare channel, e.g. to wrap it in tower layers
+//! let channel = ChannelBuilder::new().endpoint("http://localhost:50051").connect().await?;
+//! ```
+
+use crate::client::{GoogleAdsClient, GoogleAdsInterceptor, DEFAULT_ENDPOINT};
+use std::time::Duration;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+/// Default maximum size of a decoded response message (64 MiB).
+pub const DEFAULT_MAX_DECODING_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Builder for channels to the Google Ads API; see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct ChannelBuilder {
+    endpoint: String,
+    connect_timeout: Duration,
+    request_timeout: Option<Duration>,
+    keep_alive_interval: Duration,
+    keep_alive_timeout: Duration,
+    max_decoding_message_size: usize,
+    gzip: bool,
+}
+
+impl Default for ChannelBuilder {
+    fn default() -> Self {
+        Self {
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Some(Duration::from_secs(300)),
+            keep_alive_interval: Duration::from_secs(30),
+            keep_alive_timeout: Duration::from_secs(20),
+            max_decoding_message_size: DEFAULT_MAX_DECODING_MESSAGE_SIZE,
+            gzip: false,
+        }
+    }
+}
+
+impl ChannelBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides [`DEFAULT_ENDPOINT`]. A bare host name is given the `https://` scheme.
+    pub fn endpoint(&mut self, endpoint: &str) -> &mut Self {
+        self.endpoint = if endpoint.contains("://") {
+            endpoint.to_string()
+        } else {
+            format!("https://{}", endpoint)
+        };
+        self
+    }
+
+    /// Sets the timeout for establishing a connection (default 10 seconds).
+    pub fn connect_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the time a call may wait for its response headers (default 5 minutes), or
+    /// `None` for no limit. The messages of a stream are not covered by the timeout.
+    pub fn request_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets the interval of HTTP/2 keep-alive pings (default 30 seconds) and the time to
+    /// wait for their acknowledgement before closing the connection (default 20 seconds).
+    pub fn keep_alive(&mut self, interval: Duration, timeout: Duration) -> &mut Self {
+        self.keep_alive_interval = interval;
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum size of a decoded response message (default
+    /// [`DEFAULT_MAX_DECODING_MESSAGE_SIZE`]).
+    pub fn max_decoding_message_size(&mut self, limit: usize) -> &mut Self {
+        self.max_decoding_message_size = limit;
+        self
+    }
+
+    /// Compresses requests and accepts compressed responses with gzip (default off).
+    pub fn gzip(&mut self, enabled: bool) -> &mut Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Returns the endpoint URL.
+    pub fn endpoint_url(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Returns the configured tonic endpoint, without connecting.
+    pub fn build(&self) -> anyhow::Result<Endpoint> {
+        let mut endpoint = Channel::from_shared(self.endpoint.clone())?
+            .connect_timeout(self.connect_timeout)
+            .http2_keep_alive_interval(self.keep_alive_interval)
+            .keep_alive_timeout(self.keep_alive_timeout)
+            .keep_alive_while_idle(true)
+            .http2_adaptive_window(true)
+            .tcp_nodelay(true);
+        if let Some(timeout) = self.request_timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        if endpoint.uri().scheme_str() == Some("https") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        Ok(endpoint)
+    }
+
+    /// Connects a channel to the endpoint.
+    ///
+    /// The message size limit and compression are client settings in tonic: they apply to
+    /// clients made by [`connect_client`](Self::connect_client), not to the bare channel.
+    pub async fn connect(&self) -> anyhow::Result<Channel> {
+        Ok(self.build()?.connect().await?)
+    }
+
+    /// Returns a channel that connects on its first call.
+    pub fn connect_lazy(&self) -> anyhow::Result<Channel> {
+        Ok(self.build()?.connect_lazy())
+    }
+
+    /// Connects a [`GoogleAdsClient`] whose service clients use the message size limit and
+    /// compression of this builder.
+    pub async fn connect_client(
+        &self,
+        interceptor: GoogleAdsInterceptor,
+    ) -> anyhow::Result<GoogleAdsClient> {
+        let client = GoogleAdsClient::new(self.connect().await?, interceptor)
+            .with_max_decoding_message_size(self.max_decoding_message_size);
+        Ok(if self.gzip {
+            client.with_compression(CompressionEncoding::Gzip)
+        } else {
+            client
+        })
+    }
+}
@@ -0,0 +1,315 @@
+//! Date-range chunking for large historical queries.
+//!
+//! Pulling long date ranges in a single `search_stream` call can time out or hit
+//! response limits. [`DateChunker`] splits a query whose `segments.date` condition uses
+//! `BETWEEN` or `DURING` into consecutive day, week or month windows, runs the chunks
+//! with bounded concurrency, and yields a single stream of rows in date order.
+//!
+//! `DURING` ranges are resolved locally, against the local date unless
+//! [`DateChunker::today`] is set, while the API resolves them in the account's time zone.
+//! Set `today` to the account's current date when the two can differ.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let query = Query::parse(
+//!     "SELECT segments.date, campaign.id, metrics.clicks FROM campaign \
+//!      WHERE segments.date BETWEEN '2023-01-01' AND '2024-12-31'",
+//! )?;
+//!
+//! let mut chunker = DateChunker::new(ChunkWindow::Month);
+//! chunker.concurrency(4);
+//!
+//! let mut rows = chunker.search_stream(client, "1234567890", &query)?;
+//! while let Some(row) = rows.next().await {
+//!     let row = row?;
+//!     println!("{}", row.get("segments.date"));
+//! }
+//! ```
+
+use crate::gaql::{Condition, Literal, Operand, Operator, Ordering, Query};
+use crate::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsStreamRequest,
+};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Weekday};
+use futures::stream::{self, Stream, StreamExt};
+use tonic::codegen::{Body, Bytes, StdError};
+
+const DATE_FIELD: &str = "segments.date";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Size of the date windows a query is split into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkWindow {
+    /// One chunk per day.
+    Day,
+    /// Monday-to-Sunday weeks, clipped to the query range.
+    Week,
+    /// Calendar months, clipped to the query range.
+    Month,
+}
+
+/// Splits GAQL queries on `segments.date` and runs the chunks concurrently.
+#[derive(Debug, Clone)]
+pub struct DateChunker {
+    window: ChunkWindow,
+    concurrency: usize,
+    today: Option<NaiveDate>,
+}
+
+impl DateChunker {
+    pub fn new(window: ChunkWindow) -> Self {
+        Self {
+            window,
+            concurrency: 4,
+            today: None,
+        }
+    }
+
+    /// Sets the maximum number of chunks in flight at once (minimum 1, default 4).
+    pub fn concurrency(&mut self, n: usize) -> &mut Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Sets the date `DURING` ranges are resolved against. Defaults to the local date,
+    /// which differs from the API's resolution in the account's time zone around midnight.
+    pub fn today(&mut self, date: NaiveDate) -> &mut Self {
+        self.today = Some(date);
+        self
+    }
+
+    /// Returns the inclusive date range selected by the query's `segments.date` condition.
+    pub fn date_range(&self, query: &Query) -> anyhow::Result<(NaiveDate, NaiveDate)> {
+        let (_, condition) = find_date_condition(query)?;
+        match (&condition.operator, &condition.operand) {
+            (Operator::Between, Operand::Range(from, to)) => {
+                let from = parse_date_literal(from)?;
+                let to = parse_date_literal(to)?;
+                if from > to {
+                    return Err(anyhow::anyhow!(
+                        "Date range start {} is after end {}",
+                        from,
+                        to
+                    ));
+                }
+                Ok((from, to))
+            }
+            (Operator::During, Operand::Value(Literal::Keyword(function))) => {
+                let today = self.today.unwrap_or_else(|| Local::now().date_naive());
+                resolve_during(function, today)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Condition '{}' cannot be split; use BETWEEN or DURING",
+                condition
+            )),
+        }
+    }
+
+    /// Splits the query into one query per date window, in date order.
+    ///
+    /// Each chunk is a copy of the query with its `segments.date` condition replaced by
+    /// `BETWEEN` the window bounds. Queries with a `LIMIT` are rejected, since the limit
+    /// would apply to every chunk rather than to the whole result.
+    ///
+    /// When `segments.date` is selected, chunks are ordered by it: `ORDER BY segments.date`
+    /// is added to queries without an `ORDER BY`, and queries whose first ordering is
+    /// anything else are rejected, since their rows could not be merged in date order.
+    pub fn split(&self, query: &Query) -> anyhow::Result<Vec<Query>> {
+        if query.limit.is_some() {
+            return Err(anyhow::anyhow!(
+                "Queries with LIMIT cannot be split into date chunks"
+            ));
+        }
+        let (index, _) = find_date_condition(query)?;
+        let (start, end) = self.date_range(query)?;
+        let order_by = date_order(query)?;
+
+        Ok(self
+            .windows(start, end)
+            .into_iter()
+            .map(|(from, to)| {
+                let mut chunk = query.clone();
+                chunk.order_by = order_by.clone();
+                chunk.conditions[index] = Condition {
+                    field: DATE_FIELD.to_string(),
+                    operator: Operator::Between,
+                    operand: Operand::Range(
+                        Literal::String(from.format(DATE_FORMAT).to_string()),
+                        Literal::String(to.format(DATE_FORMAT).to_string()),
+                    ),
+                };
+                chunk
+            })
+            .collect())
+    }
+
+    /// Returns the inclusive `(from, to)` windows covering `start..=end`.
+    pub fn windows(&self, start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut windows = Vec::new();
+        let mut from = start;
+        while from <= end {
+            let window_end = match self.window {
+                ChunkWindow::Day => from,
+                ChunkWindow::Week => {
+                    from + Days::new(6 - from.weekday().num_days_from_monday() as u64)
+                }
+                ChunkWindow::Month => last_day_of_month(from),
+            };
+            let to = window_end.min(end);
+            windows.push((from, to));
+            from = match to.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        windows
+    }
+
+    /// Runs the chunks of `query` through `search_stream` and merges the rows.
+    ///
+    /// Up to `concurrency` chunks are opened at once. Rows are yielded batch by batch as
+    /// they arrive, chunk after chunk, so only the batches HTTP/2 flow control lets the
+    /// chunks ahead buffer are held in memory. A failing chunk yields its `Status` as an
+    /// error item.
+    pub fn search_stream<T>(
+        &self,
+        client: GoogleAdsServiceClient<T>,
+        customer_id: &str,
+        query: &Query,
+    ) -> anyhow::Result<impl Stream<Item = Result<GoogleAdsRow, tonic::Status>>>
+    where
+        T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+        T::Future: Send,
+    {
+        let chunks = self.split(query)?;
+        let customer_id = customer_id.to_string();
+
+        let fetches = chunks.into_iter().map(move |chunk| {
+            let mut client = client.clone();
+            let request = SearchGoogleAdsStreamRequest {
+                customer_id: customer_id.clone(),
+                query: chunk.to_string(),
+                ..Default::default()
+            };
+            async move { client.search_stream(request).await.map(|r| r.into_inner()) }
+        });
+
+        Ok(stream::iter(fetches)
+            .buffered(self.concurrency)
+            .flat_map(|opened| {
+                // Batches of the chunk, ending after its first error
+                stream::unfold(Some(opened), |state| async move {
+                    let batch = match state? {
+                        Ok(mut stream) => match stream.message().await {
+                            Ok(Some(response)) => {
+                                return Some((Ok(response.results), Some(Ok(stream))))
+                            }
+                            Ok(None) => return None,
+                            Err(status) => Err(status),
+                        },
+                        Err(status) => Err(status),
+                    };
+                    Some((batch, None))
+                })
+            })
+            .flat_map(|batch| {
+                stream::iter(match batch {
+                    Ok(rows) => rows.into_iter().map(Ok).collect::<Vec<_>>(),
+                    Err(status) => vec![Err(status)],
+                })
+            }))
+    }
+}
+
+fn find_date_condition(query: &Query) -> anyhow::Result<(usize, &Condition)> {
+    let mut matches = query
+        .conditions
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.field == DATE_FIELD);
+    let found = matches
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Query has no {} condition to split on", DATE_FIELD))?;
+    if matches.next().is_some() {
+        return Err(anyhow::anyhow!(
+            "Query has more than one {} condition",
+            DATE_FIELD
+        ));
+    }
+    Ok(found)
+}
+
+// Returns the ORDER BY of the chunks, putting rows of a chunk in date order
+fn date_order(query: &Query) -> anyhow::Result<Vec<Ordering>> {
+    if !query.fields.iter().any(|f| f == DATE_FIELD) {
+        // Rows are not segmented by date: chunk order is date order
+        return Ok(query.order_by.clone());
+    }
+    match query.order_by.first() {
+        None => Ok(vec![Ordering {
+            field: DATE_FIELD.to_string(),
+            descending: false,
+        }]),
+        Some(first) if first.field == DATE_FIELD && !first.descending => Ok(query.order_by.clone()),
+        Some(first) => Err(anyhow::anyhow!(
+            "Chunked rows are merged in date order, so ORDER BY must start with {} ASC, not {}",
+            DATE_FIELD,
+            first
+        )),
+    }
+}
+
+fn parse_date_literal(literal: &Literal) -> anyhow::Result<NaiveDate> {
+    match literal {
+        Literal::String(s) => NaiveDate::parse_from_str(s, DATE_FORMAT)
+            .map_err(|e| anyhow::anyhow!("Failed to parse '{}' as date: {}", s, e)),
+        other => Err(anyhow::anyhow!(
+            "Expected a date string but found {}",
+            other
+        )),
+    }
+}
+
+fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    let first = date.with_day(1).expect("day 1 exists in every month");
+    first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(NaiveDate::MAX)
+}
+
+fn previous_weekday(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let back = (7 + date.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    date - Days::new(back as u64)
+}
+
+/// Resolves a `DURING` date function to an inclusive date range relative to `today`.
+pub fn resolve_during(function: &str, today: NaiveDate) -> anyhow::Result<(NaiveDate, NaiveDate)> {
+    let yesterday = today - Days::new(1);
+    let this_monday = previous_weekday(today, Weekday::Mon);
+    let this_sunday = previous_weekday(today, Weekday::Sun);
+
+    let range = match function.to_ascii_uppercase().as_str() {
+        "TODAY" => (today, today),
+        "YESTERDAY" => (yesterday, yesterday),
+        "LAST_7_DAYS" => (today - Days::new(7), yesterday),
+        "LAST_14_DAYS" => (today - Days::new(14), yesterday),
+        "LAST_30_DAYS" => (today - Days::new(30), yesterday),
+        "THIS_MONTH" => (today.with_day(1).expect("day 1 exists"), today),
+        "LAST_MONTH" => {
+            let end = today.with_day(1).expect("day 1 exists") - Days::new(1);
+            (end.with_day(1).expect("day 1 exists"), end)
+        }
+        "THIS_WEEK_MON_TODAY" => (this_monday, today),
+        "THIS_WEEK_SUN_TODAY" => (this_sunday, today),
+        "LAST_WEEK_MON_SUN" => (this_monday - Days::new(7), this_monday - Days::new(1)),
+        "LAST_WEEK_SUN_SAT" => (this_sunday - Days::new(7), this_sunday - Days::new(1)),
+        "LAST_BUSINESS_WEEK" => (this_monday - Days::new(7), this_monday - Days::new(3)),
+        other => return Err(anyhow::anyhow!("Unknown DURING date range '{}'", other)),
+    };
+    Ok(range)
+}
@@ -0,0 +1,291 @@
+//! High-level Google Ads client.
+//!
+//! [`GoogleAdsClient`] owns a `tonic` channel and a [`GoogleAdsInterceptor`] that injects
+//! the `developer-token`, `login-customer-id`, `linked-customer-id` and `authorization`
+//! metadata into every request, and has a typed accessor for each generated service
+//! client (`client.google_ads()`, `client.campaign()`, ...).
+//!
+//! # Example
+//!
+//! ```ignore
+//! let interceptor = GoogleAdsInterceptor::new("DEVELOPER_TOKEN")?
+//!     .with_login_customer_id("123-456-7890")?
+//!     .with_access_token(&access_token)?;
+//! let client = GoogleAdsClient::connect(interceptor).await?;
+//!
+//! let mut stream = client
+//!     .google_ads()
+//!     .search_stream(SearchGoogleAdsStreamRequest {
+//!         customer_id: "9876543210".to_string(),
+//!         query: "SELECT campaign.id FROM campaign".to_string(),
+//!         ..Default::default()
+//!     })
+//!     .await?
+//!     .into_inner();
+//!
+//! // Override the login customer for a single call
+//! let mut request = tonic::Request::new(MutateGoogleAdsRequest::default());
+//! request.extensions_mut().insert(LoginCustomerId::new("1112223333"));
+//! client.google_ads().mutate(request).await?;
+//! ```
+
+use crate::auth::Authenticator;
+use crate::channel::ChannelBuilder;
+use crate::paging::PagedSearch;
+use std::fmt;
+use tonic::codec::CompressionEncoding;
+use tonic::codegen::InterceptedService;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::service::Interceptor;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+/// Default Google Ads API endpoint.
+pub const DEFAULT_ENDPOINT: &str = "https://googleads.googleapis.com";
+
+/// Transport type of the service clients returned by [`GoogleAdsClient`].
+pub type GoogleAdsChannel = InterceptedService<Channel, GoogleAdsInterceptor>;
+
+/// Request extension that overrides the `login-customer-id` header for a single call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginCustomerId(pub String);
+
+impl LoginCustomerId {
+    pub fn new(customer_id: &str) -> Self {
+        Self(customer_id.to_string())
+    }
+}
+
+/// Removes the dashes from customer ids formatted as `123-456-7890`.
+pub fn normalize_customer_id(customer_id: &str) -> String {
+    customer_id.chars().filter(|c| *c != '-').collect()
+}
+
+fn metadata_value(name: &str, value: &str) -> anyhow::Result<AsciiMetadataValue> {
+    value
+        .parse::<AsciiMetadataValue>()
+        .map_err(|e| anyhow::anyhow!("Invalid {} value: {}", name, e))
+}
+
+/// Interceptor that adds the Google Ads API headers to every request.
+#[derive(Clone)]
+pub struct GoogleAdsInterceptor {
+    developer_token: AsciiMetadataValue,
+    login_customer_id: Option<AsciiMetadataValue>,
+    linked_customer_id: Option<AsciiMetadataValue>,
+    authorization: Option<AsciiMetadataValue>,
+    authenticator: Option<Authenticator>,
+}
+
+impl GoogleAdsInterceptor {
+    pub fn new(developer_token: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            developer_token: metadata_value("developer-token", developer_token)?,
+            login_customer_id: None,
+            linked_customer_id: None,
+            authorization: None,
+            authenticator: None,
+        })
+    }
+
+    /// Sets the manager account used to access client accounts (`login-customer-id`).
+    pub fn with_login_customer_id(mut self, customer_id: &str) -> anyhow::Result<Self> {
+        self.login_customer_id = Some(metadata_value(
+            "login-customer-id",
+            &normalize_customer_id(customer_id),
+        )?);
+        Ok(self)
+    }
+
+    /// Sets the linked account for third-party app analytics (`linked-customer-id`).
+    pub fn with_linked_customer_id(mut self, customer_id: &str) -> anyhow::Result<Self> {
+        self.linked_customer_id = Some(metadata_value(
+            "linked-customer-id",
+            &normalize_customer_id(customer_id),
+        )?);
+        Ok(self)
+    }
+
+    /// Sets a static OAuth2 access token, sent as `authorization: Bearer <token>`.
+    pub fn with_access_token(mut self, access_token: &str) -> anyhow::Result<Self> {
+        self.authenticator = None;
+        self.authorization = Some(metadata_value(
+            "authorization",
+            &format!("Bearer {}", access_token),
+        )?);
+        Ok(self)
+    }
+
+    /// Takes the `authorization` token from an [`Authenticator`], replacing any static
+    /// access token.
+    ///
+    /// Requests fail with `UNAUTHENTICATED` until the authenticator holds a token.
+    pub fn with_authenticator(mut self, authenticator: Authenticator) -> Self {
+        self.authorization = None;
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    pub fn authenticator(&self) -> Option<&Authenticator> {
+        self.authenticator.as_ref()
+    }
+
+    /// Returns the configured `login-customer-id`, if any.
+    pub fn login_customer_id(&self) -> Option<&str> {
+        self.login_customer_id
+            .as_ref()
+            .and_then(|v| v.to_str().ok())
+    }
+
+    /// Returns the configured `linked-customer-id`, if any.
+    pub fn linked_customer_id(&self) -> Option<&str> {
+        self.linked_customer_id
+            .as_ref()
+            .and_then(|v| v.to_str().ok())
+    }
+}
+
+impl fmt::Debug for GoogleAdsInterceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GoogleAdsInterceptor")
+            .field("developer_token", &"<redacted>")
+            .field("login_customer_id", &self.login_customer_id())
+            .field("linked_customer_id", &self.linked_customer_id())
+            .field(
+                "authorization",
+                &self.authorization.as_ref().map(|_| "<redacted>"),
+            )
+            .field("authenticator", &self.authenticator)
+            .finish()
+    }
+}
+
+impl Interceptor for GoogleAdsInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let login_customer_id = match request.extensions().get::<LoginCustomerId>() {
+            Some(LoginCustomerId(id)) => Some(
+                metadata_value("login-customer-id", &normalize_customer_id(id))
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            ),
+            None => self.login_customer_id.clone(),
+        };
+
+        let authorization = match &self.authenticator {
+            Some(authenticator) => {
+                let token = authenticator
+                    .cached_token()
+                    .ok_or_else(|| Status::unauthenticated("No valid OAuth2 access token"))?;
+                Some(
+                    metadata_value("authorization", &format!("Bearer {}", token))
+                        .map_err(|e| Status::unauthenticated(e.to_string()))?,
+                )
+            }
+            None => self.authorization.clone(),
+        };
+
+        let metadata = request.metadata_mut();
+        metadata.insert("developer-token", self.developer_token.clone());
+        if let Some(value) = login_customer_id {
+            metadata.insert("login-customer-id", value);
+        }
+        if let Some(value) = &self.linked_customer_id {
+            metadata.insert("linked-customer-id", value.clone());
+        }
+        if let Some(value) = authorization {
+            metadata.insert("authorization", value);
+        }
+        Ok(request)
+    }
+}
+
+/// Google Ads API client owning the channel and the header interceptor.
+///
+/// Cloning is cheap: clones share the underlying channel.
+#[derive(Debug, Clone)]
+pub struct GoogleAdsClient {
+    channel: Channel,
+    interceptor: GoogleAdsInterceptor,
+    max_decoding_message_size: Option<usize>,
+    compression: Option<CompressionEncoding>,
+}
+
+impl GoogleAdsClient {
+    pub fn new(channel: Channel, interceptor: GoogleAdsInterceptor) -> Self {
+        Self {
+            channel,
+            interceptor,
+            max_decoding_message_size: None,
+            compression: None,
+        }
+    }
+
+    /// Connects to [`DEFAULT_ENDPOINT`] with the defaults of [`ChannelBuilder`].
+    pub async fn connect(interceptor: GoogleAdsInterceptor) -> anyhow::Result<Self> {
+        Self::connect_to(DEFAULT_ENDPOINT, interceptor).await
+    }
+
+    /// Connects to `endpoint` with the defaults of [`ChannelBuilder`], using TLS with the
+    /// system's native root certificates for `https://` URLs.
+    pub async fn connect_to(
+        endpoint: &str,
+        interceptor: GoogleAdsInterceptor,
+    ) -> anyhow::Result<Self> {
+        ChannelBuilder::new()
+            .endpoint(endpoint)
+            .connect_client(interceptor)
+            .await
+    }
+
+    /// Sets the maximum size of a decoded response message of the service clients.
+    pub fn with_max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Makes the service clients compress requests and accept compressed responses.
+    pub fn with_compression(mut self, encoding: CompressionEncoding) -> Self {
+        self.compression = Some(encoding);
+        self
+    }
+
+    /// Returns a client sharing this channel, with a different `login-customer-id`.
+    pub fn with_login_customer_id(&self, customer_id: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            interceptor: self
+                .interceptor
+                .clone()
+                .with_login_customer_id(customer_id)?,
+            ..self.clone()
+        })
+    }
+
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    pub fn interceptor(&self) -> &GoogleAdsInterceptor {
+        &self.interceptor
+    }
+
+    /// Runs `query` with paginated `Search`, following page tokens. See [`crate::paging`].
+    pub fn search_paged(&self, customer_id: &str, query: &str) -> PagedSearch<GoogleAdsChannel> {
+        PagedSearch::new(self.google_ads(), customer_id, query)
+    }
+}
+
+// Applies the message size limit and compression of a GoogleAdsClient to a service client
+macro_rules! configure_client {
+    ($self:ident, $client:expr) => {{
+        let mut client = $client;
+        if let Some(limit) = $self.max_decoding_message_size {
+            client = client.max_decoding_message_size(limit);
+        }
+        if let Some(encoding) = $self.compression {
+            client = client.send_compressed(encoding).accept_compressed(encoding);
+        }
+        client
+    }};
+}
+
+// Typed accessors for every generated service client, generated by build.rs
+include!(concat!(env!("OUT_DIR"), "/clients.rs"));
@@ -0,0 +1,265 @@
+//! Configuration shared with the official client libraries.
+//!
+//! [`GoogleAdsConfig::load`] reads the `google-ads.yaml` file used by the Python, Java and
+//! .NET libraries, then applies `GOOGLE_ADS_*` environment variable overrides. The file
+//! is read from `GOOGLE_ADS_CONFIGURATION_FILE_PATH` if set, otherwise from
+//! `$HOME/google-ads.yaml`.
+//!
+//! | YAML key             | Environment variable             |
+//! |----------------------|----------------------------------|
+//! | `developer_token`    | `GOOGLE_ADS_DEVELOPER_TOKEN`     |
+//! | `client_id`          | `GOOGLE_ADS_CLIENT_ID`           |
+//! | `client_secret`      | `GOOGLE_ADS_CLIENT_SECRET`       |
+//! | `refresh_token`      | `GOOGLE_ADS_REFRESH_TOKEN`       |
+//! | `json_key_file_path` | `GOOGLE_ADS_JSON_KEY_FILE_PATH`  |
+//! | `impersonated_email` | `GOOGLE_ADS_IMPERSONATED_EMAIL`  |
+//! | `login_customer_id`  | `GOOGLE_ADS_LOGIN_CUSTOMER_ID`   |
+//! | `linked_customer_id` | `GOOGLE_ADS_LINKED_CUSTOMER_ID`  |
+//! | `endpoint`           | `GOOGLE_ADS_ENDPOINT`            |
+//!
+//! Other keys of the file (e.g. `use_proto_plus`) are ignored.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let config = GoogleAdsConfig::load()?;
+//! let client = config.connect().await?;
+//! ```
+
+use crate::auth::{Authenticator, RefreshTokenCredentials, ServiceAccountCredentials};
+use crate::client::{normalize_customer_id, GoogleAdsClient, GoogleAdsInterceptor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the path of the configuration file.
+pub const CONFIGURATION_FILE_PATH_ENV: &str = "GOOGLE_ADS_CONFIGURATION_FILE_PATH";
+
+/// Name of the configuration file looked up in the home directory.
+pub const DEFAULT_CONFIGURATION_FILE: &str = "google-ads.yaml";
+
+/// Google Ads API configuration.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct GoogleAdsConfig {
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub developer_token: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub client_id: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub client_secret: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub refresh_token: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub json_key_file_path: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub impersonated_email: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub login_customer_id: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub linked_customer_id: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub endpoint: Option<String>,
+}
+
+// Customer ids are often written unquoted, so YAML parses them as numbers
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    let value = Option::<serde_yaml::Value>::deserialize(deserializer)?;
+    match value {
+        None | Some(serde_yaml::Value::Null) => Ok(None),
+        Some(serde_yaml::Value::String(s)) => Ok(Some(s)),
+        Some(serde_yaml::Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(serde_yaml::Value::Bool(b)) => Ok(Some(b.to_string())),
+        Some(other) => Err(serde::de::Error::custom(format!(
+            "expected a string, found {:?}",
+            other
+        ))),
+    }
+}
+
+impl GoogleAdsConfig {
+    /// Loads the configuration file (if present) and applies `GOOGLE_ADS_*` environment
+    /// overrides, then validates the result.
+    ///
+    /// A missing `$HOME/google-ads.yaml` is not an error, so configuration can come from
+    /// the environment alone; a missing `GOOGLE_ADS_CONFIGURATION_FILE_PATH` file is.
+    pub fn load() -> anyhow::Result<Self> {
+        let mut config = match std::env::var_os(CONFIGURATION_FILE_PATH_ENV) {
+            Some(path) => Self::from_file(path)?,
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::from_file(path)?,
+                _ => Self::default(),
+            },
+        };
+        config.override_from_env();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Returns `$HOME/google-ads.yaml`.
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| Path::new(&home).join(DEFAULT_CONFIGURATION_FILE))
+    }
+
+    /// Reads a `google-ads.yaml` file, without environment overrides or validation.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let yaml = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_yaml(&yaml)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Parses `google-ads.yaml` content, without environment overrides or validation.
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        if yaml.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// Overrides values with the `GOOGLE_ADS_*` environment variables that are set.
+    pub fn override_from_env(&mut self) {
+        self.override_with(|name| std::env::var(name).ok());
+    }
+
+    /// Overrides values with those returned by `lookup` for each `GOOGLE_ADS_*` variable
+    /// name. Empty values are ignored.
+    pub fn override_with<F: Fn(&str) -> Option<String>>(&mut self, lookup: F) {
+        let fields = [
+            ("GOOGLE_ADS_DEVELOPER_TOKEN", &mut self.developer_token),
+            ("GOOGLE_ADS_CLIENT_ID", &mut self.client_id),
+            ("GOOGLE_ADS_CLIENT_SECRET", &mut self.client_secret),
+            ("GOOGLE_ADS_REFRESH_TOKEN", &mut self.refresh_token),
+            (
+                "GOOGLE_ADS_JSON_KEY_FILE_PATH",
+                &mut self.json_key_file_path,
+            ),
+            (
+                "GOOGLE_ADS_IMPERSONATED_EMAIL",
+                &mut self.impersonated_email,
+            ),
+            ("GOOGLE_ADS_LOGIN_CUSTOMER_ID", &mut self.login_customer_id),
+            (
+                "GOOGLE_ADS_LINKED_CUSTOMER_ID",
+                &mut self.linked_customer_id,
+            ),
+            ("GOOGLE_ADS_ENDPOINT", &mut self.endpoint),
+        ];
+        for (name, field) in fields {
+            if let Some(value) = lookup(name).filter(|v| !v.is_empty()) {
+                *field = Some(value);
+            }
+        }
+    }
+
+    /// Checks that a developer token and a complete set of credentials are configured,
+    /// and that customer ids are 10 digits (dashes allowed).
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.developer_token.is_none() {
+            anyhow::bail!("developer_token is required");
+        }
+        if self.json_key_file_path.is_none() {
+            let missing: Vec<&str> = [
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("refresh_token", &self.refresh_token),
+            ]
+            .iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+            if !missing.is_empty() {
+                anyhow::bail!(
+                    "Missing OAuth2 credentials: {} (or set json_key_file_path for a service account)",
+                    missing.join(", ")
+                );
+            }
+        }
+        for (name, value) in [
+            ("login_customer_id", &self.login_customer_id),
+            ("linked_customer_id", &self.linked_customer_id),
+        ] {
+            if let Some(id) = value {
+                let id = normalize_customer_id(id);
+                if id.len() != 10 || !id.chars().all(|c| c.is_ascii_digit()) {
+                    anyhow::bail!("{} must be a 10-digit customer id, got '{}'", name, id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the API endpoint URL, adding `https://` to a bare host name.
+    pub fn endpoint_url(&self) -> String {
+        match &self.endpoint {
+            Some(endpoint) if endpoint.contains("://") => endpoint.clone(),
+            Some(endpoint) => format!("https://{}", endpoint),
+            None => crate::client::DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+
+    /// Builds an [`Authenticator`] from the service account key if `json_key_file_path`
+    /// is set, otherwise from the refresh token credentials.
+    pub fn authenticator(&self) -> anyhow::Result<Authenticator> {
+        if let Some(path) = &self.json_key_file_path {
+            let mut credentials = ServiceAccountCredentials::from_file(path)?;
+            if let Some(email) = &self.impersonated_email {
+                credentials.impersonate(email);
+            }
+            return Ok(Authenticator::new(credentials));
+        }
+        match (&self.client_id, &self.client_secret, &self.refresh_token) {
+            (Some(client_id), Some(client_secret), Some(refresh_token)) => Ok(Authenticator::new(
+                RefreshTokenCredentials::new(client_id, client_secret, refresh_token),
+            )),
+            _ => anyhow::bail!("Missing OAuth2 credentials"),
+        }
+    }
+
+    /// Builds a [`GoogleAdsInterceptor`] with the configured headers and authenticator.
+    pub fn interceptor(&self) -> anyhow::Result<GoogleAdsInterceptor> {
+        let developer_token = self
+            .developer_token
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("developer_token is required"))?;
+        let mut interceptor =
+            GoogleAdsInterceptor::new(developer_token)?.with_authenticator(self.authenticator()?);
+        if let Some(id) = &self.login_customer_id {
+            interceptor = interceptor.with_login_customer_id(id)?;
+        }
+        if let Some(id) = &self.linked_customer_id {
+            interceptor = interceptor.with_linked_customer_id(id)?;
+        }
+        Ok(interceptor)
+    }
+
+    /// Fetches an access token and connects a [`GoogleAdsClient`] to the endpoint.
+    pub async fn connect(&self) -> anyhow::Result<GoogleAdsClient> {
+        let interceptor = self.interceptor()?;
+        if let Some(authenticator) = interceptor.authenticator() {
+            authenticator.access_token().await?;
+        }
+        GoogleAdsClient::connect_to(&self.endpoint_url(), interceptor).await
+    }
+}
+
+impl fmt::Debug for GoogleAdsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| "<redacted>");
+        f.debug_struct("GoogleAdsConfig")
+            .field("developer_token", &redacted(&self.developer_token))
+            .field("client_id", &self.client_id)
+            .field("client_secret", &redacted(&self.client_secret))
+            .field("refresh_token", &redacted(&self.refresh_token))
+            .field("json_key_file_path", &self.json_key_file_path)
+            .field("impersonated_email", &self.impersonated_email)
+            .field("login_customer_id", &self.login_customer_id)
+            .field("linked_customer_id", &self.linked_customer_id)
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
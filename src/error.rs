@@ -0,0 +1,499 @@
+//! Decoding of Google Ads API failures from `tonic::Status`.
+//!
+//! A failed call returns a `google.ads.googleads.v23.errors.GoogleAdsFailure` in the
+//! `google.ads.googleads.v23.errors.googleadsfailure-bin` trailer and in the
+//! `grpc-status-details-bin` details, and the `request-id` needed by Google support in
+//! the response metadata. [`GoogleAdsError::from_status`] extracts all of them, and
+//! decodes each error's `ErrorCode` oneof into its field and enum value names.
+//!
+//! Every error code is also classified into an [`ErrorCategory`]. The taxonomy is built
+//! from the `ErrorCode` descriptor in the pool rather than a hand-written list, so error
+//! enums added by future API versions are classified as soon as the protos are updated.
+//!
+//! # Example
+//!
+//! ```ignore
+//! match client.google_ads().mutate(request).await {
+//!     Ok(response) => { /* ... */ }
+//!     Err(status) => {
+//!         let error = GoogleAdsError::from_status(&status);
+//!         eprintln!("{}", error);
+//!         if error.is_retryable() { /* retry with backoff */ }
+//!         for detail in &error.errors {
+//!             if detail.category() == ErrorCategory::Quota { /* back off */ }
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::google::ads::googleads::v23::common::value::Value as TriggerValue;
+use crate::google::ads::googleads::v23::errors::{
+    error_location::FieldPathElement, ErrorCode, ErrorLocation,
+    GoogleAdsError as GoogleAdsErrorProto, GoogleAdsFailure,
+};
+use once_cell::sync::Lazy;
+use prost::Message;
+use prost_reflect::{DynamicMessage, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Cursor;
+use tonic::{Code, Status};
+
+/// Fully-qualified name of the `GoogleAdsFailure` message.
+pub const GOOGLE_ADS_FAILURE_FQN: &str = "google.ads.googleads.v23.errors.GoogleAdsFailure";
+
+/// Binary trailer carrying the encoded `GoogleAdsFailure`.
+pub const GOOGLE_ADS_FAILURE_METADATA_KEY: &str =
+    "google.ads.googleads.v23.errors.googleadsfailure-bin";
+
+/// Response header carrying the request id.
+pub const REQUEST_ID_METADATA_KEY: &str = "request-id";
+
+const ERROR_CODE_FQN: &str = "google.ads.googleads.v23.errors.ErrorCode";
+
+/// A failed Google Ads API call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoogleAdsError {
+    /// gRPC status code.
+    pub code: Code,
+    /// gRPC status message.
+    pub message: String,
+    /// Request id from the `request-id` header, or from the failure payload.
+    pub request_id: Option<String>,
+    /// Decoded `GoogleAdsFailure`, if the status carried one.
+    pub failure: Option<GoogleAdsFailure>,
+    /// Decoded errors of the failure, in order.
+    pub errors: Vec<ErrorDetail>,
+    /// True if the errors were returned as the `partial_failure_error` of a
+    /// successful mutate response.
+    pub partial_failure: bool,
+}
+
+impl GoogleAdsError {
+    /// Extracts the `GoogleAdsFailure` and request id from a status.
+    pub fn from_status(status: &Status) -> Self {
+        let failure = failure_from_metadata(status).or_else(|| failure_from_details(status));
+        let request_id = status
+            .metadata()
+            .get(REQUEST_ID_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| {
+                failure
+                    .as_ref()
+                    .map(|f| f.request_id.clone())
+                    .filter(|id| !id.is_empty())
+            });
+        let errors = failure
+            .as_ref()
+            .map(|f| f.errors.iter().map(ErrorDetail::from_proto).collect())
+            .unwrap_or_default();
+
+        Self {
+            code: status.code(),
+            message: status.message().to_string(),
+            request_id,
+            failure,
+            errors,
+            partial_failure: false,
+        }
+    }
+
+    /// Decodes the `partial_failure_error` of a mutate response sent with
+    /// `partial_failure: true`.
+    pub fn from_partial_failure(status: &crate::google::rpc::Status) -> Self {
+        let failure = status
+            .details
+            .iter()
+            .find(|any| any.type_url.ends_with(GOOGLE_ADS_FAILURE_FQN))
+            .and_then(|any| GoogleAdsFailure::decode(any.value.as_slice()).ok());
+        let errors = failure
+            .as_ref()
+            .map(|f| f.errors.iter().map(ErrorDetail::from_proto).collect())
+            .unwrap_or_default();
+
+        Self {
+            code: Code::from_i32(status.code),
+            message: status.message.clone(),
+            request_id: failure
+                .as_ref()
+                .map(|f| f.request_id.clone())
+                .filter(|id| !id.is_empty()),
+            failure,
+            errors,
+            partial_failure: true,
+        }
+    }
+
+    /// Returns true if the status carried a `GoogleAdsFailure`.
+    pub fn has_failure(&self) -> bool {
+        self.failure.is_some()
+    }
+
+    /// Returns the category of the error.
+    ///
+    /// Partial failures are [`ErrorCategory::PartialFailure`]. Otherwise the most severe
+    /// category of the decoded errors is returned, so a request mixing a quota error and
+    /// an invalid field is not retried; without decoded errors the gRPC code is used.
+    pub fn category(&self) -> ErrorCategory {
+        if self.partial_failure {
+            return ErrorCategory::PartialFailure;
+        }
+        self.errors
+            .iter()
+            .map(ErrorDetail::category)
+            .max_by_key(|category| category.severity())
+            .unwrap_or_else(|| ErrorCategory::from_code(self.code))
+    }
+
+    /// Returns true if the call can be retried as-is after a backoff: a bare
+    /// `UNAVAILABLE`, `DEADLINE_EXCEEDED` or `INTERNAL` status, or a failure whose errors
+    /// are all [retryable](ErrorDetail::is_retryable).
+    pub fn is_retryable(&self) -> bool {
+        if self.partial_failure {
+            return false;
+        }
+        if self.errors.is_empty() {
+            return matches!(
+                self.code,
+                Code::Unavailable | Code::DeadlineExceeded | Code::Internal
+            );
+        }
+        self.errors.iter().all(ErrorDetail::is_retryable)
+    }
+}
+
+impl From<Status> for GoogleAdsError {
+    fn from(status: Status) -> Self {
+        Self::from_status(&status)
+    }
+}
+
+impl From<&Status> for GoogleAdsError {
+    fn from(status: &Status) -> Self {
+        Self::from_status(status)
+    }
+}
+
+impl fmt::Display for GoogleAdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Google Ads API error: {:?}", self.code)?;
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request-id: {})", request_id)?;
+        }
+        if self.errors.is_empty() {
+            return write!(f, ": {}", self.message);
+        }
+        for detail in &self.errors {
+            write!(f, "\n  - {}", detail)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GoogleAdsError {}
+
+fn failure_from_metadata(status: &Status) -> Option<GoogleAdsFailure> {
+    let bytes = status
+        .metadata()
+        .get_bin(GOOGLE_ADS_FAILURE_METADATA_KEY)?
+        .to_bytes()
+        .ok()?;
+    GoogleAdsFailure::decode(bytes).ok()
+}
+
+fn failure_from_details(status: &Status) -> Option<GoogleAdsFailure> {
+    if status.details().is_empty() {
+        return None;
+    }
+    let rpc_status = crate::google::rpc::Status::decode(status.details()).ok()?;
+    rpc_status
+        .details
+        .iter()
+        .find(|any| any.type_url.ends_with(GOOGLE_ADS_FAILURE_FQN))
+        .and_then(|any| GoogleAdsFailure::decode(any.value.as_slice()).ok())
+}
+
+// ----------------------------------------------------------------------------
+// Error details
+// ----------------------------------------------------------------------------
+
+/// A single error of a `GoogleAdsFailure`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorDetail {
+    /// Name of the `ErrorCode` oneof field that is set, e.g. `quota_error`.
+    pub kind: String,
+    /// Name of the error enum value, e.g. `RESOURCE_EXHAUSTED`.
+    pub code: String,
+    /// Human-readable description of the error.
+    pub message: String,
+    /// The value that triggered the error, if any.
+    pub trigger: Option<String>,
+    /// Path of the request field that caused the error, e.g. `operations[1].create.name`.
+    pub location: Option<String>,
+    /// The decoded error message.
+    pub error: GoogleAdsErrorProto,
+}
+
+impl ErrorDetail {
+    pub fn from_proto(error: &GoogleAdsErrorProto) -> Self {
+        let (kind, code) = error
+            .error_code
+            .as_ref()
+            .and_then(error_code_names)
+            .unwrap_or_default();
+        Self {
+            kind,
+            code,
+            message: error.message.clone(),
+            trigger: error
+                .trigger
+                .as_ref()
+                .and_then(|t| t.value.as_ref())
+                .map(format_trigger),
+            location: error.location.as_ref().and_then(format_location),
+            error: error.clone(),
+        }
+    }
+
+    /// Returns the error code as `kind.CODE`, e.g. `quota_error.RESOURCE_EXHAUSTED`.
+    pub fn error_code(&self) -> String {
+        format!("{}.{}", self.kind, self.code)
+    }
+
+    /// Returns the category of the error code.
+    pub fn category(&self) -> ErrorCategory {
+        classify(&self.kind, &self.code)
+    }
+
+    /// Returns true for transient errors and `quota_error.RESOURCE_TEMPORARILY_EXHAUSTED`;
+    /// other quota errors only clear when the quota window resets.
+    pub fn is_retryable(&self) -> bool {
+        self.category().is_retryable()
+            || (self.kind == "quota_error" && self.code == "RESOURCE_TEMPORARILY_EXHAUSTED")
+    }
+}
+
+impl fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.error_code(), self.message)?;
+        if let Some(trigger) = &self.trigger {
+            write!(f, " (trigger: {})", trigger)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " (location: {})", location)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the oneof field name and enum value name of an `ErrorCode`.
+pub fn error_code_names(error_code: &ErrorCode) -> Option<(String, String)> {
+    let descriptor = crate::descriptor_pool().get_message_by_name(ERROR_CODE_FQN)?;
+    let encoded = error_code.encode_to_vec();
+    let dynamic_msg = DynamicMessage::decode(descriptor, Cursor::new(&encoded)).ok()?;
+
+    let (field, value) = dynamic_msg.fields().next()?;
+    let code = match (value, field.kind().as_enum()) {
+        (Value::EnumNumber(n), Some(enum_desc)) => enum_desc
+            .get_value(*n)
+            .map(|v| v.name().to_string())
+            .unwrap_or_else(|| n.to_string()),
+        (other, _) => format!("{:?}", other),
+    };
+    Some((field.name().to_string(), code))
+}
+
+fn format_trigger(value: &TriggerValue) -> String {
+    match value {
+        TriggerValue::BooleanValue(v) => v.to_string(),
+        TriggerValue::Int64Value(v) => v.to_string(),
+        TriggerValue::FloatValue(v) => v.to_string(),
+        TriggerValue::DoubleValue(v) => v.to_string(),
+        TriggerValue::StringValue(v) => format!("'{}'", v),
+    }
+}
+
+fn format_location(location: &ErrorLocation) -> Option<String> {
+    if location.field_path_elements.is_empty() {
+        return None;
+    }
+    Some(format_field_path(&location.field_path_elements))
+}
+
+/// Formats field path elements as `operations[1].create.name`.
+pub(crate) fn format_field_path(elements: &[FieldPathElement]) -> String {
+    elements
+        .iter()
+        .map(|element| match element.index {
+            Some(index) => format!("{}[{}]", element.field_name, index),
+            None => element.field_name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// ----------------------------------------------------------------------------
+// Taxonomy
+// ----------------------------------------------------------------------------
+
+/// Category of a Google Ads API error, deciding how a caller should react.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCategory {
+    /// Transient server-side error; the same request may succeed when retried.
+    Retryable,
+    /// Quota or rate limit exceeded; retrying only helps once the quota window resets.
+    Quota,
+    /// Missing, expired or insufficient credentials.
+    Auth,
+    /// The request is invalid and must be changed.
+    InvalidRequest,
+    /// The request violates an advertising policy.
+    PolicyViolation,
+    /// Some operations of a partial-failure mutate failed.
+    PartialFailure,
+    /// Unspecified or unrecognized error code.
+    Unknown,
+}
+
+impl ErrorCategory {
+    /// Returns the snake_case name of the category.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Retryable => "retryable",
+            ErrorCategory::Quota => "quota",
+            ErrorCategory::Auth => "auth",
+            ErrorCategory::InvalidRequest => "invalid_request",
+            ErrorCategory::PolicyViolation => "policy_violation",
+            ErrorCategory::PartialFailure => "partial_failure",
+            ErrorCategory::Unknown => "unknown",
+        }
+    }
+
+    /// Returns true for transient errors, which may succeed when retried as-is.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorCategory::Retryable)
+    }
+
+    /// Maps a gRPC status code, for statuses without a `GoogleAdsFailure`.
+    pub fn from_code(code: Code) -> Self {
+        match code {
+            Code::Unavailable | Code::DeadlineExceeded | Code::Internal | Code::Aborted => {
+                ErrorCategory::Retryable
+            }
+            Code::ResourceExhausted => ErrorCategory::Quota,
+            Code::Unauthenticated | Code::PermissionDenied => ErrorCategory::Auth,
+            Code::InvalidArgument
+            | Code::FailedPrecondition
+            | Code::NotFound
+            | Code::AlreadyExists
+            | Code::OutOfRange
+            | Code::Unimplemented => ErrorCategory::InvalidRequest,
+            _ => ErrorCategory::Unknown,
+        }
+    }
+
+    // Errors that need the request or credentials to change outrank those that only
+    // need a retry
+    fn severity(&self) -> u8 {
+        match self {
+            ErrorCategory::Unknown => 0,
+            ErrorCategory::Retryable => 1,
+            ErrorCategory::Quota => 2,
+            ErrorCategory::InvalidRequest => 3,
+            ErrorCategory::PolicyViolation => 4,
+            ErrorCategory::Auth => 5,
+            ErrorCategory::PartialFailure => 6,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+static TAXONOMY: Lazy<BTreeMap<(String, String), ErrorCategory>> = Lazy::new(|| {
+    let mut taxonomy = BTreeMap::new();
+    let Some(descriptor) = crate::descriptor_pool().get_message_by_name(ERROR_CODE_FQN) else {
+        return taxonomy;
+    };
+    for field in descriptor.fields() {
+        let Some(enum_desc) = field.kind().as_enum().cloned() else {
+            continue;
+        };
+        for value in enum_desc.values() {
+            taxonomy.insert(
+                (field.name().to_string(), value.name().to_string()),
+                classify_name(field.name(), value.name()),
+            );
+        }
+    }
+    taxonomy
+});
+
+/// Returns the category of every `(kind, CODE)` pair of the `ErrorCode` oneof, as
+/// generated from the descriptor pool.
+pub fn error_taxonomy() -> &'static BTreeMap<(String, String), ErrorCategory> {
+    &TAXONOMY
+}
+
+/// Returns the category of an error code given its oneof field and enum value names,
+/// e.g. `classify("quota_error", "RESOURCE_EXHAUSTED")`.
+pub fn classify(kind: &str, code: &str) -> ErrorCategory {
+    TAXONOMY
+        .get(&(kind.to_string(), code.to_string()))
+        .copied()
+        .unwrap_or(ErrorCategory::Unknown)
+}
+
+// Name-based rules applied to each enum value of the pool, in order
+fn classify_name(kind: &str, code: &str) -> ErrorCategory {
+    if code == "UNSPECIFIED" || code == "UNKNOWN" {
+        return ErrorCategory::Unknown;
+    }
+    let transient = match kind {
+        "internal_error" => code != "ERROR_CODE_NOT_PUBLISHED",
+        _ => {
+            code.contains("TRANSIENT")
+                || code.contains("TEMPORARILY_UNAVAILABLE")
+                || code == "CONCURRENT_MODIFICATION"
+                || code == "DEADLINE_EXCEEDED"
+        }
+    };
+    if transient {
+        return ErrorCategory::Retryable;
+    }
+    // Only exhausted quotas clear up; quota_error.ACCESS_PROHIBITED means the developer
+    // token lacks the access level and falls through to Auth
+    let quota = match kind {
+        "quota_error" => {
+            code == "RESOURCE_EXHAUSTED"
+                || code == "RESOURCE_TEMPORARILY_EXHAUSTED"
+                || (code.starts_with("EXCESSIVE_") && code.ends_with("_QUERY_RESOURCE_CONSUMPTION"))
+        }
+        _ => code.contains("RATE_EXCEEDED") || code == "RESOURCE_EXHAUSTED",
+    };
+    if quota {
+        return ErrorCategory::Quota;
+    }
+    if kind.starts_with("authentication_")
+        || kind.starts_with("authorization_")
+        || code == "ACCESS_PROHIBITED"
+        || code == "PERMISSION_DENIED"
+        || code == "NOT_AUTHORIZED"
+    {
+        return ErrorCategory::Auth;
+    }
+    let policy = matches!(kind, "policy_violation_error" | "policy_finding_error")
+        || code.ends_with("POLICY_VIOLATION")
+        || code.ends_with("POLICY_VIOLATED")
+        || (code.contains("VIOLATES") && code.contains("POLICY"))
+        || code == "POLICY_ERROR"
+        || code == "POLICY_FINDING";
+    if policy {
+        return ErrorCategory::PolicyViolation;
+    }
+    ErrorCategory::InvalidRequest
+}
@@ -0,0 +1,376 @@
+//! Running one GAQL query across many accounts.
+//!
+//! [`FanOut`] runs `search_stream` for each customer id of a list, typically the accounts
+//! linked to a manager (MCC) account, with bounded concurrency and an optional per-account
+//! timeout. Rows from all accounts are merged into a single stream as they arrive, each
+//! tagged with the customer id it came from. A failing account yields an [`AccountError`]
+//! item and does not stop the other accounts.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut fanout = FanOut::new();
+//! fanout
+//!     .concurrency(20)
+//!     .timeout(Duration::from_secs(300))
+//!     .on_progress(|p| eprintln!("{}/{} accounts done", p.completed, p.total));
+//!
+//! let client = client.with_login_customer_id("1234567890")?.google_ads();
+//! let mut rows = fanout.search_stream(client, &customer_ids, "SELECT campaign.id FROM campaign");
+//! while let Some(item) = rows.next().await {
+//!     match item {
+//!         Ok(tagged) => println!("{}: {}", tagged.customer_id, tagged.row.get("campaign.id")),
+//!         Err(e) => eprintln!("{}", e),
+//!     }
+//! }
+//! ```
+
+use crate::client::normalize_customer_id;
+use crate::error::GoogleAdsError;
+use crate::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::{Status, Streaming};
+
+type ProgressCallback = dyn Fn(&Progress) + Send + Sync;
+
+/// A row tagged with the account it was returned by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomerRow {
+    /// Customer id of the account, without dashes.
+    pub customer_id: String,
+    pub row: GoogleAdsRow,
+}
+
+/// The failure of one account of a fan-out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountError {
+    /// Customer id of the account, without dashes.
+    pub customer_id: String,
+    pub error: GoogleAdsError,
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Customer {}: {}", self.customer_id, self.error)
+    }
+}
+
+impl std::error::Error for AccountError {}
+
+/// Progress reported each time an account finishes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Customer id of the account that finished.
+    pub customer_id: String,
+    /// Rows returned by that account.
+    pub rows: usize,
+    /// Whether that account failed.
+    pub failed: bool,
+    /// Accounts finished so far, including failed ones.
+    pub completed: usize,
+    /// Accounts failed so far.
+    pub failures: usize,
+    /// Total number of accounts.
+    pub total: usize,
+}
+
+/// Rows and errors of a fan-out collected by [`FanOut::run`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FanOutResult {
+    pub rows: Vec<CustomerRow>,
+    pub errors: Vec<AccountError>,
+}
+
+/// Runs a query across many accounts concurrently.
+#[derive(Clone)]
+pub struct FanOut {
+    concurrency: usize,
+    timeout: Option<Duration>,
+    progress: Option<Arc<ProgressCallback>>,
+}
+
+impl Default for FanOut {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FanOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanOut")
+            .field("concurrency", &self.concurrency)
+            .field("timeout", &self.timeout)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl FanOut {
+    pub fn new() -> Self {
+        Self {
+            concurrency: 10,
+            timeout: None,
+            progress: None,
+        }
+    }
+
+    /// Sets the maximum number of accounts queried at once (minimum 1, default 10).
+    pub fn concurrency(&mut self, n: usize) -> &mut Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Sets the time allowed for each account, from opening its stream to the last row.
+    /// An account that takes longer fails with `DEADLINE_EXCEEDED`. No timeout by default.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a callback invoked each time an account finishes, successfully or not.
+    pub fn on_progress<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Runs `query` for each customer id and merges the rows into one stream.
+    ///
+    /// Rows of an account keep their order, but rows of different accounts are
+    /// interleaved as they arrive. Each failing account yields one `Err` item.
+    pub fn search_stream<T, S>(
+        &self,
+        client: GoogleAdsServiceClient<T>,
+        customer_ids: &[S],
+        query: &str,
+    ) -> impl Stream<Item = Result<CustomerRow, AccountError>> + Send
+    where
+        T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+        T::Future: Send,
+        S: AsRef<str>,
+    {
+        let customer_ids: Vec<String> = customer_ids
+            .iter()
+            .map(|id| normalize_customer_id(id.as_ref()))
+            .collect();
+        let tracker = Arc::new(Tracker {
+            callback: self.progress.clone(),
+            counts: Mutex::new((0, 0)),
+            total: customer_ids.len(),
+        });
+        let query = query.to_string();
+        let timeout = self.timeout;
+
+        stream::iter(customer_ids)
+            .map(move |customer_id| {
+                let request = SearchGoogleAdsStreamRequest {
+                    customer_id: customer_id.clone(),
+                    query: query.clone(),
+                    ..Default::default()
+                };
+                account_stream(
+                    client.clone(),
+                    customer_id,
+                    request,
+                    timeout,
+                    tracker.clone(),
+                )
+            })
+            .flatten_unordered(self.concurrency)
+    }
+
+    /// Runs `query` for each customer id and collects all rows and account errors.
+    pub async fn run<T, S>(
+        &self,
+        client: GoogleAdsServiceClient<T>,
+        customer_ids: &[S],
+        query: &str,
+    ) -> FanOutResult
+    where
+        T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+        T::Future: Send,
+        S: AsRef<str>,
+    {
+        let mut result = FanOutResult::default();
+        let mut stream = Box::pin(self.search_stream(client, customer_ids, query));
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(row) => result.rows.push(row),
+                Err(error) => result.errors.push(error),
+            }
+        }
+        result
+    }
+}
+
+// Counts finished accounts across the account streams and reports progress
+struct Tracker {
+    callback: Option<Arc<ProgressCallback>>,
+    counts: Mutex<(usize, usize)>,
+    total: usize,
+}
+
+impl Tracker {
+    fn finish(&self, customer_id: &str, rows: usize, failed: bool) {
+        let (completed, failures) = {
+            let mut counts = self.counts.lock().unwrap();
+            counts.0 += 1;
+            if failed {
+                counts.1 += 1;
+            }
+            *counts
+        };
+        if let Some(callback) = &self.callback {
+            callback(&Progress {
+                customer_id: customer_id.to_string(),
+                rows,
+                failed,
+                completed,
+                failures,
+                total: self.total,
+            });
+        }
+    }
+}
+
+struct AccountState<T> {
+    client: GoogleAdsServiceClient<T>,
+    customer_id: String,
+    request: SearchGoogleAdsStreamRequest,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    stream: Option<Streaming<SearchGoogleAdsStreamResponse>>,
+    rows: usize,
+    done: bool,
+    tracker: Arc<Tracker>,
+}
+
+impl<T> AccountState<T> {
+    fn fail(&mut self, status: Status) -> Vec<Result<CustomerRow, AccountError>> {
+        self.done = true;
+        self.tracker.finish(&self.customer_id, self.rows, true);
+        vec![Err(AccountError {
+            customer_id: self.customer_id.clone(),
+            error: GoogleAdsError::from_status(&status),
+        })]
+    }
+}
+
+// Fails with DEADLINE_EXCEEDED if the deadline passes first
+async fn within<F, R>(
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+    future: F,
+) -> Result<R, Status>
+where
+    F: Future<Output = Result<R, Status>>,
+{
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future)
+            .await
+            .unwrap_or_else(|_| {
+                Err(Status::deadline_exceeded(format!(
+                    "Account timed out after {:?}",
+                    timeout.unwrap_or_default()
+                )))
+            }),
+        None => future.await,
+    }
+}
+
+// The Ok variant holds a whole GoogleAdsRow, so the error is not the large one
+#[allow(clippy::result_large_err)]
+fn account_stream<T>(
+    client: GoogleAdsServiceClient<T>,
+    customer_id: String,
+    request: SearchGoogleAdsStreamRequest,
+    timeout: Option<Duration>,
+    tracker: Arc<Tracker>,
+) -> BoxStream<'static, Result<CustomerRow, AccountError>>
+where
+    T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    T::Future: Send,
+{
+    let state = AccountState {
+        client,
+        customer_id,
+        request,
+        timeout,
+        deadline: None,
+        stream: None,
+        rows: 0,
+        done: false,
+        tracker,
+    };
+
+    // Each step yields the tagged rows of one batch
+    stream::unfold(state, |mut state| async move {
+        if state.done {
+            return None;
+        }
+        if state.stream.is_none() {
+            // The deadline starts when the account gets a concurrency slot
+            state.deadline = state.timeout.map(|t| Instant::now() + t);
+            let mut client = state.client.clone();
+            let request = state.request.clone();
+            match within(state.deadline, state.timeout, client.search_stream(request)).await {
+                Ok(response) => state.stream = Some(response.into_inner()),
+                Err(status) => {
+                    let items = state.fail(status);
+                    return Some((items, state));
+                }
+            }
+        }
+
+        let mut stream = state.stream.take().expect("stream was opened");
+        let message = within(state.deadline, state.timeout, stream.message()).await;
+        state.stream = Some(stream);
+        match message {
+            Ok(Some(batch)) => {
+                state.rows += batch.results.len();
+                let customer_id = state.customer_id.clone();
+                let items = batch
+                    .results
+                    .into_iter()
+                    .map(|row| {
+                        Ok(CustomerRow {
+                            customer_id: customer_id.clone(),
+                            row,
+                        })
+                    })
+                    .collect();
+                Some((items, state))
+            }
+            Ok(None) => {
+                state.tracker.finish(&state.customer_id, state.rows, false);
+                None
+            }
+            Err(status) => {
+                let items = state.fail(status);
+                Some((items, state))
+            }
+        }
+    })
+    .flat_map(stream::iter)
+    .boxed()
+}
@@ -0,0 +1,124 @@
+//! Verification of a response field mask against the requested `SELECT` list.
+//!
+//! The `field_mask` of a `SearchGoogleAdsStreamResponse` does not always match the
+//! query: some paths can be dropped, added (e.g. implicit `resource_name` fields) or
+//! reordered. [`FieldMaskReport`] lists the differences and maps each requested column
+//! to its position in the mask, so callers can always emit columns in requested order.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let query = Query::parse("SELECT campaign.id, campaign.name FROM campaign")?;
+//! let report = query.check_field_mask(response.field_mask.as_ref().unwrap());
+//! if !report.missing.is_empty() {
+//!     eprintln!("missing columns: {:?}", report.missing);
+//! }
+//! for row in response.results {
+//!     // values in mask order, reordered to the requested column order
+//!     let values = row.get_many(&report.returned_paths());
+//!     println!("{:?}", report.reorder(&values));
+//! }
+//! ```
+
+use crate::gaql::Query;
+use prost_types::FieldMask;
+
+/// Differences between requested `SELECT` paths and a returned field mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMaskReport {
+    /// Paths requested in the `SELECT` clause, in query order.
+    pub requested: Vec<String>,
+    /// Paths returned in the field mask, in mask order.
+    pub returned: Vec<String>,
+    /// Requested paths absent from the field mask.
+    pub missing: Vec<String>,
+    /// Field mask paths that were not requested.
+    pub extra: Vec<String>,
+    /// For each requested path, its index in `returned`, or `None` if missing.
+    pub column_order: Vec<Option<usize>>,
+}
+
+impl FieldMaskReport {
+    /// Compares requested paths against a returned field mask.
+    pub fn compare<S: AsRef<str>>(requested: &[S], field_mask: &FieldMask) -> Self {
+        let requested: Vec<String> = requested.iter().map(|p| p.as_ref().to_string()).collect();
+        let returned = field_mask.paths.clone();
+
+        let column_order: Vec<Option<usize>> = requested
+            .iter()
+            .map(|path| returned.iter().position(|r| r == path))
+            .collect();
+        let missing = requested
+            .iter()
+            .zip(&column_order)
+            .filter(|(_, index)| index.is_none())
+            .map(|(path, _)| path.clone())
+            .collect();
+        let extra = returned
+            .iter()
+            .filter(|path| !requested.contains(path))
+            .cloned()
+            .collect();
+
+        Self {
+            requested,
+            returned,
+            missing,
+            extra,
+            column_order,
+        }
+    }
+
+    /// Returns true if the mask holds exactly the requested paths in the requested order.
+    pub fn is_exact(&self) -> bool {
+        self.requested == self.returned
+    }
+
+    /// Returns true if the mask holds every requested path, regardless of order or extras.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Returns true if every requested path is present but at a different position.
+    pub fn is_reordered(&self) -> bool {
+        self.is_complete()
+            && self
+                .column_order
+                .iter()
+                .enumerate()
+                .any(|(i, index)| *index != Some(i))
+    }
+
+    /// Returns the returned paths as `&str`, for passing to `GoogleAdsRow::get_many`.
+    pub fn returned_paths(&self) -> Vec<&str> {
+        self.returned.iter().map(String::as_str).collect()
+    }
+
+    /// Returns the requested paths as `&str`, for passing to `GoogleAdsRow::get_many`.
+    pub fn requested_paths(&self) -> Vec<&str> {
+        self.requested.iter().map(String::as_str).collect()
+    }
+
+    /// Reorders values given in field mask order into requested column order.
+    ///
+    /// Missing columns (and values beyond the end of `values`) are filled with
+    /// `T::default()`; extra columns are dropped.
+    pub fn reorder<T: Clone + Default>(&self, values: &[T]) -> Vec<T> {
+        self.column_order
+            .iter()
+            .map(|index| {
+                index
+                    .and_then(|i| values.get(i))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+impl Query {
+    /// Compares this query's `SELECT` list against a returned field mask.
+    pub fn check_field_mask(&self, field_mask: &FieldMask) -> FieldMaskReport {
+        FieldMaskReport::compare(&self.fields, field_mask)
+    }
+}
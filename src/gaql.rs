@@ -0,0 +1,1270 @@
+//! Google Ads Query Language (GAQL) parsing, formatting and safe parameter binding.
+//!
+//! [`Query::parse`] turns a GAQL string into a small syntax tree. Conditions may use
+//! named placeholders (`:name`) in place of literal values, which are filled in with
+//! [`Query::bind`]. Each bound value is type-checked against the descriptor of the
+//! field it is compared with, and rendered as a correctly escaped GAQL literal.
+//!
+//! [`Query::format`] pretty-prints a query in a canonical layout, and
+//! [`Query::normalize`] produces a stable cache key for semantically identical queries.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let gaql = Query::parse(
+//!     "SELECT campaign.id, metrics.clicks FROM campaign \
+//!      WHERE campaign.name = :name AND segments.date BETWEEN :from AND :to",
+//! )?
+//! .bind("name", "Joe's \"Summer\" Sale")?
+//! .bind("from", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())?
+//! .bind("to", NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())?
+//! .to_gaql()?;
+//! ```
+
+use chrono::{NaiveDate, NaiveDateTime};
+use prost_reflect::{FieldDescriptor, Kind};
+use std::fmt;
+
+const GOOGLE_ADS_ROW_FQN: &str = "google.ads.googleads.v23.services.GoogleAdsRow";
+
+/// Date functions accepted by the `DURING` operator.
+pub const DATE_RANGE_FUNCTIONS: &[&str] = &[
+    "TODAY",
+    "YESTERDAY",
+    "LAST_7_DAYS",
+    "LAST_14_DAYS",
+    "LAST_30_DAYS",
+    "LAST_BUSINESS_WEEK",
+    "LAST_MONTH",
+    "LAST_WEEK_MON_SUN",
+    "LAST_WEEK_SUN_SAT",
+    "THIS_MONTH",
+    "THIS_WEEK_MON_TODAY",
+    "THIS_WEEK_SUN_TODAY",
+];
+
+// ---------------------------------------------------------------------------
+// Syntax tree
+// ---------------------------------------------------------------------------
+
+/// A parsed GAQL query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    /// Field paths of the `SELECT` clause, in query order.
+    pub fields: Vec<String>,
+    /// Resource named in the `FROM` clause.
+    pub resource: String,
+    /// Conditions of the `WHERE` clause. GAQL only supports `AND` between conditions.
+    pub conditions: Vec<Condition>,
+    /// Orderings of the `ORDER BY` clause.
+    pub order_by: Vec<Ordering>,
+    /// Value of the `LIMIT` clause.
+    pub limit: Option<u64>,
+    /// Name/value pairs of the `PARAMETERS` clause.
+    pub parameters: Vec<(String, Literal)>,
+}
+
+/// A single `field operator value` condition of a `WHERE` clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub field: String,
+    pub operator: Operator,
+    pub operand: Operand,
+}
+
+/// Comparison operators supported by GAQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Operator {
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    NotIn,
+    Like,
+    NotLike,
+    ContainsAny,
+    ContainsAll,
+    ContainsNone,
+    IsNull,
+    IsNotNull,
+    During,
+    Between,
+    RegexpMatch,
+    NotRegexpMatch,
+}
+
+impl Operator {
+    /// Returns the GAQL spelling of the operator.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::NotEq => "!=",
+            Operator::Gt => ">",
+            Operator::Gte => ">=",
+            Operator::Lt => "<",
+            Operator::Lte => "<=",
+            Operator::In => "IN",
+            Operator::NotIn => "NOT IN",
+            Operator::Like => "LIKE",
+            Operator::NotLike => "NOT LIKE",
+            Operator::ContainsAny => "CONTAINS ANY",
+            Operator::ContainsAll => "CONTAINS ALL",
+            Operator::ContainsNone => "CONTAINS NONE",
+            Operator::IsNull => "IS NULL",
+            Operator::IsNotNull => "IS NOT NULL",
+            Operator::During => "DURING",
+            Operator::Between => "BETWEEN",
+            Operator::RegexpMatch => "REGEXP_MATCH",
+            Operator::NotRegexpMatch => "NOT REGEXP_MATCH",
+        }
+    }
+
+    /// Returns true for operators whose right-hand side is a list.
+    pub fn takes_list(&self) -> bool {
+        matches!(
+            self,
+            Operator::In
+                | Operator::NotIn
+                | Operator::ContainsAny
+                | Operator::ContainsAll
+                | Operator::ContainsNone
+        )
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Right-hand side of a condition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// No value, used by `IS NULL` and `IS NOT NULL`.
+    None,
+    /// A single value, e.g. `= 'ENABLED'` or `DURING LAST_7_DAYS`.
+    Value(Literal),
+    /// A parenthesized list, e.g. `IN (1, 2, 3)`.
+    List(Vec<Literal>),
+    /// The two bounds of a `BETWEEN` condition.
+    Range(Literal, Literal),
+}
+
+/// A literal value in a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    /// A quoted string, stored unescaped.
+    String(String),
+    /// A numeric literal, stored as written.
+    Number(String),
+    /// A bare word such as an enum value, `TRUE`/`FALSE` or a date function.
+    Keyword(String),
+    /// A named placeholder (`:name`) waiting for a value from [`Query::bind`].
+    Placeholder(String),
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::String(s) => f.write_str(&quote_string(s)),
+            Literal::Number(n) => f.write_str(n),
+            Literal::Keyword(k) => f.write_str(k),
+            Literal::Placeholder(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
+/// A single `field [ASC|DESC]` entry of an `ORDER BY` clause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ordering {
+    pub field: String,
+    pub descending: bool,
+}
+
+/// Quotes a string as a GAQL string literal, escaping backslashes and single quotes.
+pub fn quote_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('\'');
+    for c in s.chars() {
+        if c == '\\' || c == '\'' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+// ---------------------------------------------------------------------------
+// Bound values
+// ---------------------------------------------------------------------------
+
+/// A value bound to a query placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Date(NaiveDate),
+    /// An enum value name, e.g. `"ENABLED"`.
+    Enum(String),
+    List(Vec<QueryValue>),
+}
+
+impl QueryValue {
+    /// Convenience constructor for enum values, e.g. `QueryValue::enum_value("ENABLED")`.
+    pub fn enum_value(name: &str) -> Self {
+        QueryValue::Enum(name.to_string())
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            QueryValue::String(_) => "string",
+            QueryValue::Int(_) => "integer",
+            QueryValue::Float(_) => "float",
+            QueryValue::Bool(_) => "bool",
+            QueryValue::Date(_) => "date",
+            QueryValue::Enum(_) => "enum",
+            QueryValue::List(_) => "list",
+        }
+    }
+}
+
+impl From<&str> for QueryValue {
+    fn from(v: &str) -> Self {
+        QueryValue::String(v.to_string())
+    }
+}
+
+impl From<String> for QueryValue {
+    fn from(v: String) -> Self {
+        QueryValue::String(v)
+    }
+}
+
+impl From<&String> for QueryValue {
+    fn from(v: &String) -> Self {
+        QueryValue::String(v.clone())
+    }
+}
+
+impl From<i64> for QueryValue {
+    fn from(v: i64) -> Self {
+        QueryValue::Int(v)
+    }
+}
+
+impl From<i32> for QueryValue {
+    fn from(v: i32) -> Self {
+        QueryValue::Int(v as i64)
+    }
+}
+
+impl From<u32> for QueryValue {
+    fn from(v: u32) -> Self {
+        QueryValue::Int(v as i64)
+    }
+}
+
+impl From<f64> for QueryValue {
+    fn from(v: f64) -> Self {
+        QueryValue::Float(v)
+    }
+}
+
+impl From<bool> for QueryValue {
+    fn from(v: bool) -> Self {
+        QueryValue::Bool(v)
+    }
+}
+
+impl From<NaiveDate> for QueryValue {
+    fn from(v: NaiveDate) -> Self {
+        QueryValue::Date(v)
+    }
+}
+
+impl<T: Into<QueryValue>> From<Vec<T>> for QueryValue {
+    fn from(v: Vec<T>) -> Self {
+        QueryValue::List(v.into_iter().map(Into::into).collect())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Field resolution
+// ---------------------------------------------------------------------------
+
+/// Resolves a GAQL field path (e.g. `"campaign.name"`) to its descriptor by walking
+/// the `GoogleAdsRow` message.
+pub fn resolve_field(path: &str) -> anyhow::Result<FieldDescriptor> {
+    let row_desc = crate::descriptor_pool()
+        .get_message_by_name(GOOGLE_ADS_ROW_FQN)
+        .ok_or_else(|| anyhow::anyhow!("GoogleAdsRow not found in descriptor pool"))?;
+
+    let mut message = row_desc;
+    let mut segments = path.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let field = message.get_field_by_name(segment).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Field '{}' of path '{}' not found on {}",
+                segment,
+                path,
+                message.full_name()
+            )
+        })?;
+        if segments.peek().is_none() {
+            return Ok(field);
+        }
+        match field.kind() {
+            Kind::Message(nested) => message = nested,
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Cannot traverse into non-message field '{}' of type {:?} in path '{}'",
+                    segment,
+                    other,
+                    path
+                ))
+            }
+        }
+    }
+    Err(anyhow::anyhow!("Empty field path"))
+}
+
+/// Returns true if the field holds a date (or date-time) encoded as a string,
+/// such as `segments.date`, `segments.week` or `campaign.start_date_time`.
+pub fn is_date_field(field: &FieldDescriptor) -> bool {
+    if !matches!(field.kind(), Kind::String) {
+        return false;
+    }
+    let name = field.name();
+    matches!(name, "date" | "week" | "month" | "quarter")
+        || name.ends_with("_date")
+        || name.ends_with("_date_time")
+}
+
+// ---------------------------------------------------------------------------
+// Binding
+// ---------------------------------------------------------------------------
+
+impl Query {
+    /// Binds a value to every occurrence of the placeholder `:name`.
+    ///
+    /// The value is checked against the descriptor of each field the placeholder is
+    /// compared with: strings are escaped and quoted, dates are formatted as
+    /// `'YYYY-MM-DD'`, enums are rendered by name (integers are resolved through the
+    /// enum descriptor) and lists are only accepted by list operators such as `IN`.
+    pub fn bind(mut self, name: &str, value: impl Into<QueryValue>) -> anyhow::Result<Self> {
+        let value = value.into();
+        let mut found = false;
+
+        for condition in &mut self.conditions {
+            if !operand_has_placeholder(&condition.operand, name) {
+                continue;
+            }
+            found = true;
+            let field = resolve_field(&condition.field)?;
+            bind_condition(condition, &field, name, &value)?;
+        }
+
+        if !found {
+            return Err(anyhow::anyhow!(
+                "Placeholder ':{}' not found in query",
+                name
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Returns the names of placeholders that have not been bound yet, in query order.
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for condition in &self.conditions {
+            for literal in operand_literals(&condition.operand) {
+                if let Literal::Placeholder(name) = literal {
+                    if !names.contains(name) {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    /// Renders the query as GAQL, failing if any placeholder is still unbound.
+    pub fn to_gaql(&self) -> anyhow::Result<String> {
+        let unbound = self.placeholders();
+        if !unbound.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Unbound placeholders: {}",
+                unbound
+                    .iter()
+                    .map(|n| format!(":{}", n))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(self.to_string())
+    }
+}
+
+fn operand_literals(operand: &Operand) -> Vec<&Literal> {
+    match operand {
+        Operand::None => vec![],
+        Operand::Value(l) => vec![l],
+        Operand::List(items) => items.iter().collect(),
+        Operand::Range(from, to) => vec![from, to],
+    }
+}
+
+fn operand_has_placeholder(operand: &Operand, name: &str) -> bool {
+    operand_literals(operand)
+        .into_iter()
+        .any(|l| matches!(l, Literal::Placeholder(n) if n == name))
+}
+
+fn is_placeholder(literal: &Literal, name: &str) -> bool {
+    matches!(literal, Literal::Placeholder(n) if n == name)
+}
+
+fn bind_condition(
+    condition: &mut Condition,
+    field: &FieldDescriptor,
+    name: &str,
+    value: &QueryValue,
+) -> anyhow::Result<()> {
+    let operator = condition.operator;
+    let field_path = condition.field.clone();
+
+    match &mut condition.operand {
+        // `IN :ids` expands a list value into a parenthesized list
+        Operand::Value(literal) if operator.takes_list() && is_placeholder(literal, name) => {
+            let items = match value {
+                QueryValue::List(items) => items,
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "Operator {} on '{}' requires a list, got {}",
+                        operator,
+                        field_path,
+                        other.type_name()
+                    ))
+                }
+            };
+            // GAQL has no empty list literal: `IN ()` does not parse
+            if items.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Operator {} on '{}' requires a non-empty list",
+                    operator,
+                    field_path
+                ));
+            }
+            let literals = items
+                .iter()
+                .map(|item| to_literal(item, field, operator, &field_path))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            condition.operand = Operand::List(literals);
+        }
+        Operand::Value(literal) => {
+            *literal = to_literal(value, field, operator, &field_path)?;
+        }
+        Operand::List(items) => {
+            for item in items.iter_mut().filter(|l| is_placeholder(l, name)) {
+                *item = to_literal(value, field, operator, &field_path)?;
+            }
+        }
+        Operand::Range(from, to) => {
+            if is_placeholder(from, name) {
+                *from = to_literal(value, field, operator, &field_path)?;
+            }
+            if is_placeholder(to, name) {
+                *to = to_literal(value, field, operator, &field_path)?;
+            }
+        }
+        Operand::None => {}
+    }
+    Ok(())
+}
+
+/// Converts a single (non-list) bound value into a literal for the given field.
+fn to_literal(
+    value: &QueryValue,
+    field: &FieldDescriptor,
+    operator: Operator,
+    field_path: &str,
+) -> anyhow::Result<Literal> {
+    let mismatch = || {
+        anyhow::anyhow!(
+            "Cannot bind {} value {:?} to field '{}' of type {:?}",
+            value.type_name(),
+            value,
+            field_path,
+            field.kind()
+        )
+    };
+
+    if let QueryValue::List(_) = value {
+        return Err(anyhow::anyhow!(
+            "Operator {} on '{}' does not accept a list",
+            operator,
+            field_path
+        ));
+    }
+
+    if operator == Operator::During {
+        let name = match value {
+            QueryValue::String(s) | QueryValue::Enum(s) => s.to_ascii_uppercase(),
+            _ => return Err(mismatch()),
+        };
+        if !DATE_RANGE_FUNCTIONS.contains(&name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "'{}' is not a valid DURING date range for '{}'",
+                name,
+                field_path
+            ));
+        }
+        return Ok(Literal::Keyword(name));
+    }
+
+    match field.kind() {
+        Kind::String if is_date_field(field) => match value {
+            QueryValue::Date(d) => Ok(Literal::String(d.format("%Y-%m-%d").to_string())),
+            // The whole string must be a date or a date-time, nothing may follow it
+            QueryValue::String(s) => {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map(|_| ())
+                    .or_else(|_| {
+                        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").map(|_| ())
+                    })
+                    .map_err(|e| {
+                        anyhow::anyhow!(
+                            "Failed to parse '{}' as date for '{}': {}",
+                            s,
+                            field_path,
+                            e
+                        )
+                    })?;
+                Ok(Literal::String(s.clone()))
+            }
+            _ => Err(mismatch()),
+        },
+        Kind::String => match value {
+            QueryValue::String(s) => Ok(Literal::String(s.clone())),
+            _ => Err(mismatch()),
+        },
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => match value {
+            QueryValue::Int(i) if i32::try_from(*i).is_ok() => Ok(Literal::Number(i.to_string())),
+            QueryValue::Int(i) => Err(anyhow::anyhow!(
+                "Value {} is out of range for int32 field '{}'",
+                i,
+                field_path
+            )),
+            _ => Err(mismatch()),
+        },
+        Kind::Uint32 | Kind::Fixed32 => match value {
+            QueryValue::Int(i) if u32::try_from(*i).is_ok() => Ok(Literal::Number(i.to_string())),
+            QueryValue::Int(i) => Err(anyhow::anyhow!(
+                "Value {} is out of range for uint32 field '{}'",
+                i,
+                field_path
+            )),
+            _ => Err(mismatch()),
+        },
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => match value {
+            QueryValue::Int(i) => Ok(Literal::Number(i.to_string())),
+            _ => Err(mismatch()),
+        },
+        Kind::Uint64 | Kind::Fixed64 => match value {
+            QueryValue::Int(i) if *i >= 0 => Ok(Literal::Number(i.to_string())),
+            QueryValue::Int(i) => Err(anyhow::anyhow!(
+                "Value {} is out of range for uint64 field '{}'",
+                i,
+                field_path
+            )),
+            _ => Err(mismatch()),
+        },
+        Kind::Double | Kind::Float => match value {
+            QueryValue::Int(i) => Ok(Literal::Number(i.to_string())),
+            QueryValue::Float(f) if f.is_finite() => Ok(Literal::Number(f.to_string())),
+            _ => Err(mismatch()),
+        },
+        Kind::Bool => match value {
+            QueryValue::Bool(b) => Ok(Literal::Keyword(
+                if *b { "TRUE" } else { "FALSE" }.to_string(),
+            )),
+            _ => Err(mismatch()),
+        },
+        Kind::Enum(enum_desc) => {
+            let resolved = match value {
+                QueryValue::Enum(name) | QueryValue::String(name) => {
+                    enum_desc.get_value_by_name(name)
+                }
+                QueryValue::Int(i) => i32::try_from(*i).ok().and_then(|n| enum_desc.get_value(n)),
+                _ => return Err(mismatch()),
+            };
+            resolved
+                .map(|v| Literal::Keyword(v.name().to_string()))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Value {:?} is not a member of enum {} for '{}'",
+                        value,
+                        enum_desc.full_name(),
+                        field_path
+                    )
+                })
+        }
+        _ => Err(mismatch()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Rendering
+// ---------------------------------------------------------------------------
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.operand {
+            Operand::None => write!(f, "{} {}", self.field, self.operator),
+            Operand::Value(v) => write!(f, "{} {} {}", self.field, self.operator, v),
+            Operand::List(items) => write!(
+                f,
+                "{} {} ({})",
+                self.field,
+                self.operator,
+                items
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Operand::Range(from, to) => {
+                write!(f, "{} {} {} AND {}", self.field, self.operator, from, to)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Ordering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "{} DESC", self.field)
+        } else {
+            write!(f, "{} ASC", self.field)
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SELECT {} FROM {}",
+            self.fields.join(", "),
+            self.resource
+        )?;
+        if !self.conditions.is_empty() {
+            let conditions: Vec<String> = self.conditions.iter().map(|c| c.to_string()).collect();
+            write!(f, " WHERE {}", conditions.join(" AND "))?;
+        }
+        if !self.order_by.is_empty() {
+            let orderings: Vec<String> = self.order_by.iter().map(|o| o.to_string()).collect();
+            write!(f, " ORDER BY {}", orderings.join(", "))?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if !self.parameters.is_empty() {
+            let parameters: Vec<String> = self
+                .parameters
+                .iter()
+                .map(|(k, v)| format!("{} = {}", k, v))
+                .collect();
+            write!(f, " PARAMETERS {}", parameters.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Formatting and normalization
+// ---------------------------------------------------------------------------
+
+/// Options for [`Query::format_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Sort `SELECT` fields alphabetically instead of preserving query order.
+    pub sort_fields: bool,
+}
+
+impl Query {
+    /// Pretty-prints the query with uppercase keywords, one field per line and one
+    /// condition per line, preserving field order.
+    ///
+    /// ```text
+    /// SELECT
+    ///   campaign.id,
+    ///   campaign.name
+    /// FROM campaign
+    /// WHERE campaign.status = 'ENABLED'
+    ///   AND segments.date DURING LAST_7_DAYS
+    /// ORDER BY campaign.id ASC
+    /// LIMIT 10
+    /// ```
+    pub fn format(&self) -> String {
+        self.format_with(&FormatOptions::default())
+    }
+
+    /// Pretty-prints the query like [`Query::format`], with the given options.
+    pub fn format_with(&self, options: &FormatOptions) -> String {
+        let mut fields = self.fields.clone();
+        if options.sort_fields {
+            fields.sort();
+        }
+
+        let mut lines = vec!["SELECT".to_string()];
+        let last = fields.len().saturating_sub(1);
+        for (i, field) in fields.iter().enumerate() {
+            let separator = if i < last { "," } else { "" };
+            lines.push(format!("  {}{}", field, separator));
+        }
+        lines.push(format!("FROM {}", self.resource));
+
+        for (i, condition) in self.conditions.iter().enumerate() {
+            let keyword = if i == 0 { "WHERE" } else { "  AND" };
+            lines.push(format!("{} {}", keyword, condition));
+        }
+        if !self.order_by.is_empty() {
+            let orderings: Vec<String> = self.order_by.iter().map(|o| o.to_string()).collect();
+            lines.push(format!("ORDER BY {}", orderings.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            lines.push(format!("LIMIT {}", limit));
+        }
+        if !self.parameters.is_empty() {
+            let parameters: Vec<String> = self
+                .parameters
+                .iter()
+                .map(|(k, v)| format!("{} = {}", k, v))
+                .collect();
+            lines.push(format!("PARAMETERS {}", parameters.join(", ")));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Returns a canonical copy of the query for comparing semantically identical queries.
+    ///
+    /// `SELECT` fields, `WHERE` conditions, list values and `PARAMETERS` are sorted, since
+    /// their order does not change the result set. Literals are normalized: enum values
+    /// and keywords are uppercased and unquoted, and numbers lose redundant digits.
+    /// `ORDER BY` is kept as written because its order is significant.
+    pub fn normalized(&self) -> Query {
+        let mut query = self.clone();
+
+        query.fields.sort();
+        query.fields.dedup();
+
+        for condition in &mut query.conditions {
+            let is_enum = resolve_field(&condition.field)
+                .map(|f| matches!(f.kind(), Kind::Enum(_)))
+                .unwrap_or(false);
+            let normalize = |literal: &mut Literal| *literal = normalize_literal(literal, is_enum);
+            match &mut condition.operand {
+                Operand::None => {}
+                Operand::Value(literal) => normalize(literal),
+                Operand::List(items) => {
+                    items.iter_mut().for_each(normalize);
+                    items.sort_by_key(|l| l.to_string());
+                    items.dedup();
+                }
+                Operand::Range(from, to) => {
+                    normalize(from);
+                    normalize(to);
+                }
+            }
+        }
+        query.conditions.sort_by_key(|c| c.to_string());
+        query.conditions.dedup();
+
+        for (name, value) in &mut query.parameters {
+            *name = name.to_ascii_lowercase();
+            *value = normalize_literal(value, false);
+        }
+        query.parameters.sort_by(|a, b| a.0.cmp(&b.0));
+
+        query
+    }
+
+    /// Returns a stable single-line cache key; semantically identical queries that differ
+    /// only in formatting, keyword casing, quoting or clause order produce the same key.
+    pub fn normalize(&self) -> String {
+        self.normalized().to_string()
+    }
+}
+
+fn normalize_literal(literal: &Literal, is_enum: bool) -> Literal {
+    match literal {
+        Literal::String(s) if is_enum => Literal::Keyword(s.to_ascii_uppercase()),
+        Literal::Keyword(k) => Literal::Keyword(k.to_ascii_uppercase()),
+        Literal::Number(n) => {
+            if let Ok(i) = n.parse::<i64>() {
+                Literal::Number(i.to_string())
+            } else if let Ok(f) = n.parse::<f64>() {
+                Literal::Number(f.to_string())
+            } else {
+                literal.clone()
+            }
+        }
+        other => other.clone(),
+    }
+}
+
+/// Parses and pretty-prints a GAQL query. See [`Query::format`].
+pub fn format_gaql(gaql: &str) -> anyhow::Result<String> {
+    Ok(Query::parse(gaql)?.format())
+}
+
+/// Parses a GAQL query and returns its normalized cache key. See [`Query::normalize`].
+pub fn normalize_gaql(gaql: &str) -> anyhow::Result<String> {
+    Ok(Query::parse(gaql)?.normalize())
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Number(String),
+    Placeholder(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(input: &str) -> anyhow::Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            ',' => {
+                tokens.push(Token::Symbol(","));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::Symbol("("));
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Symbol(")"));
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Symbol("="));
+                i += 1;
+            }
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Symbol("!="));
+                i += 2;
+            }
+            '<' | '>' => {
+                let with_eq = chars.get(i + 1) == Some(&'=');
+                tokens.push(Token::Symbol(match (c, with_eq) {
+                    ('<', true) => "<=",
+                    ('<', false) => "<",
+                    ('>', true) => ">=",
+                    _ => ">",
+                }));
+                i += if with_eq { 2 } else { 1 };
+            }
+            '\'' | '"' => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(anyhow::anyhow!("Unterminated string literal")),
+                        Some('\\') => {
+                            let escaped = chars
+                                .get(i + 1)
+                                .ok_or_else(|| anyhow::anyhow!("Unterminated string literal"))?;
+                            value.push(*escaped);
+                            i += 2;
+                        }
+                        Some(ch) if *ch == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(ch) => {
+                            value.push(*ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::String(value));
+            }
+            ':' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                if i == start {
+                    return Err(anyhow::anyhow!("Expected placeholder name after ':'"));
+                }
+                tokens.push(Token::Placeholder(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                tokens.push(Token::Number(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unexpected character '{}' at position {}",
+                    other,
+                    i
+                ))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> anyhow::Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Expected {} but found {}",
+                keyword,
+                describe(self.peek())
+            ))
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> anyhow::Result<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Expected '{}' but found {}",
+                symbol,
+                describe(self.peek())
+            ))
+        }
+    }
+
+    fn identifier(&mut self, what: &str) -> anyhow::Result<String> {
+        match self.next() {
+            Some(Token::Word(w)) if !is_reserved(&w) => Ok(w),
+            other => Err(anyhow::anyhow!(
+                "Expected {} but found {}",
+                what,
+                describe(other.as_ref())
+            )),
+        }
+    }
+
+    fn literal(&mut self) -> anyhow::Result<Literal> {
+        match self.next() {
+            Some(Token::String(s)) => Ok(Literal::String(s)),
+            Some(Token::Number(n)) => Ok(Literal::Number(n)),
+            Some(Token::Placeholder(p)) => Ok(Literal::Placeholder(p)),
+            Some(Token::Word(w)) if !is_reserved(&w) => Ok(Literal::Keyword(w)),
+            other => Err(anyhow::anyhow!(
+                "Expected a value but found {}",
+                describe(other.as_ref())
+            )),
+        }
+    }
+
+    fn literal_list(&mut self) -> anyhow::Result<Vec<Literal>> {
+        self.expect_symbol("(")?;
+        let mut items = vec![self.literal()?];
+        while self.eat_symbol(",") {
+            items.push(self.literal()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(items)
+    }
+
+    fn operator(&mut self) -> anyhow::Result<Operator> {
+        let token = self.next();
+        let op = match &token {
+            Some(Token::Symbol("=")) => Operator::Eq,
+            Some(Token::Symbol("!=")) => Operator::NotEq,
+            Some(Token::Symbol(">")) => Operator::Gt,
+            Some(Token::Symbol(">=")) => Operator::Gte,
+            Some(Token::Symbol("<")) => Operator::Lt,
+            Some(Token::Symbol("<=")) => Operator::Lte,
+            Some(Token::Word(w)) => match w.to_ascii_uppercase().as_str() {
+                "IN" => Operator::In,
+                "LIKE" => Operator::Like,
+                "DURING" => Operator::During,
+                "BETWEEN" => Operator::Between,
+                "REGEXP_MATCH" => Operator::RegexpMatch,
+                "NOT" => {
+                    if self.eat_keyword("IN") {
+                        Operator::NotIn
+                    } else if self.eat_keyword("LIKE") {
+                        Operator::NotLike
+                    } else if self.eat_keyword("REGEXP_MATCH") {
+                        Operator::NotRegexpMatch
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Expected IN, LIKE or REGEXP_MATCH after NOT but found {}",
+                            describe(self.peek())
+                        ));
+                    }
+                }
+                "CONTAINS" => {
+                    if self.eat_keyword("ANY") {
+                        Operator::ContainsAny
+                    } else if self.eat_keyword("ALL") {
+                        Operator::ContainsAll
+                    } else if self.eat_keyword("NONE") {
+                        Operator::ContainsNone
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Expected ANY, ALL or NONE after CONTAINS but found {}",
+                            describe(self.peek())
+                        ));
+                    }
+                }
+                "IS" => {
+                    let negated = self.eat_keyword("NOT");
+                    self.expect_keyword("NULL")?;
+                    if negated {
+                        Operator::IsNotNull
+                    } else {
+                        Operator::IsNull
+                    }
+                }
+                _ => return Err(anyhow::anyhow!("Unknown operator '{}'", w)),
+            },
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Expected an operator but found {}",
+                    describe(other.as_ref())
+                ))
+            }
+        };
+        Ok(op)
+    }
+
+    fn condition(&mut self) -> anyhow::Result<Condition> {
+        let field = self.identifier("a field name")?;
+        let operator = self.operator()?;
+        let operand = match operator {
+            Operator::IsNull | Operator::IsNotNull => Operand::None,
+            Operator::Between => {
+                let from = self.literal()?;
+                self.expect_keyword("AND")?;
+                let to = self.literal()?;
+                Operand::Range(from, to)
+            }
+            op if op.takes_list() => {
+                if matches!(self.peek(), Some(Token::Placeholder(_))) {
+                    Operand::Value(self.literal()?)
+                } else {
+                    Operand::List(self.literal_list()?)
+                }
+            }
+            _ => Operand::Value(self.literal()?),
+        };
+        Ok(Condition {
+            field,
+            operator,
+            operand,
+        })
+    }
+
+    fn query(&mut self) -> anyhow::Result<Query> {
+        self.expect_keyword("SELECT")?;
+        let mut fields = vec![self.identifier("a field name")?];
+        while self.eat_symbol(",") {
+            fields.push(self.identifier("a field name")?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let resource = self.identifier("a resource name")?;
+
+        let mut conditions = Vec::new();
+        if self.eat_keyword("WHERE") {
+            conditions.push(self.condition()?);
+            while self.eat_keyword("AND") {
+                conditions.push(self.condition()?);
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let field = self.identifier("a field name")?;
+                let descending = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push(Ordering { field, descending });
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        if self.eat_keyword("LIMIT") {
+            limit = match self.next() {
+                Some(Token::Number(n)) => Some(
+                    n.parse::<u64>()
+                        .map_err(|e| anyhow::anyhow!("Invalid LIMIT '{}': {}", n, e))?,
+                ),
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "Expected a number after LIMIT but found {}",
+                        describe(other.as_ref())
+                    ))
+                }
+            };
+        }
+
+        let mut parameters = Vec::new();
+        if self.eat_keyword("PARAMETERS") {
+            loop {
+                let name = self.identifier("a parameter name")?;
+                self.expect_symbol("=")?;
+                parameters.push((name, self.literal()?));
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        if let Some(token) = self.peek() {
+            return Err(anyhow::anyhow!(
+                "Unexpected {} after end of query",
+                describe(Some(token))
+            ));
+        }
+
+        Ok(Query {
+            fields,
+            resource,
+            conditions,
+            order_by,
+            limit,
+            parameters,
+        })
+    }
+}
+
+const RESERVED: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "AND",
+    "ORDER",
+    "BY",
+    "LIMIT",
+    "PARAMETERS",
+    "ASC",
+    "DESC",
+];
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED.iter().any(|r| r.eq_ignore_ascii_case(word))
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => "end of query".to_string(),
+        Some(Token::Word(w)) => format!("'{}'", w),
+        Some(Token::String(s)) => quote_string(s),
+        Some(Token::Number(n)) => n.clone(),
+        Some(Token::Placeholder(p)) => format!(":{}", p),
+        Some(Token::Symbol(s)) => format!("'{}'", s),
+    }
+}
+
+impl Query {
+    /// Parses a GAQL query string.
+    ///
+    /// Keywords are case-insensitive. Values may be written as literals or as named
+    /// placeholders (`:name`) to be filled in later with [`Query::bind`].
+    pub fn parse(gaql: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(gaql)?;
+        Parser { tokens, pos: 0 }.query()
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
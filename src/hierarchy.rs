@@ -0,0 +1,308 @@
+//! Discovery of the account hierarchy under a manager account.
+//!
+//! [`discover_hierarchy`] queries `customer_client` from the root manager, then from each
+//! sub-manager it finds, and builds an [`AccountHierarchy`] tree with parent/child links.
+//! Every query is sent with the root as `login-customer-id`, since a manager can access all
+//! accounts below it. Each node records the path of manager ids leading to it, so calls
+//! for an account can also be made through a closer manager.
+//!
+//! The hierarchy serializes to JSON, so fan-out jobs can start from a cached copy instead
+//! of walking the hierarchy on every run.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut discovery = HierarchyDiscovery::new();
+//! discovery.exclude_cancelled(true).exclude_hidden(true);
+//! let hierarchy = discovery.discover(client.google_ads(), "1234567890").await?;
+//! std::fs::write("hierarchy.json", hierarchy.to_json()?)?;
+//!
+//! let customer_ids = hierarchy.client_customer_ids();
+//! let rows = FanOut::new().search_stream(client.google_ads(), &customer_ids, query);
+//! ```
+
+use crate::client::{normalize_customer_id, LoginCustomerId};
+use crate::error::GoogleAdsError;
+use crate::google::ads::googleads::v23::enums::customer_status_enum::CustomerStatus;
+use crate::google::ads::googleads::v23::resources::CustomerClient;
+use crate::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, SearchGoogleAdsStreamRequest,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tonic::codegen::{Body, Bytes, StdError};
+
+/// Query listing a manager (level 0) and its direct client accounts (level 1).
+pub const CUSTOMER_CLIENT_QUERY: &str = "SELECT customer_client.client_customer, \
+     customer_client.id, customer_client.level, customer_client.manager, \
+     customer_client.hidden, customer_client.test_account, customer_client.status, \
+     customer_client.descriptive_name, customer_client.currency_code, \
+     customer_client.time_zone FROM customer_client WHERE customer_client.level <= 1";
+
+/// An account of the hierarchy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountNode {
+    /// Customer id, without dashes.
+    pub customer_id: String,
+    pub descriptive_name: String,
+    /// True for manager (MCC) accounts.
+    pub manager: bool,
+    pub hidden: bool,
+    pub test_account: bool,
+    /// `CustomerStatus` name, e.g. `ENABLED` or `CANCELED`.
+    pub status: String,
+    pub currency_code: String,
+    pub time_zone: String,
+    /// Depth below the root, which is at depth 0.
+    pub depth: usize,
+    /// Ids of the managers from the root down to the parent; empty for the root.
+    pub path: Vec<String>,
+    pub children: Vec<AccountNode>,
+}
+
+impl AccountNode {
+    /// Returns the id of the parent manager, or `None` for the root.
+    pub fn parent_id(&self) -> Option<&str> {
+        self.path.last().map(String::as_str)
+    }
+
+    /// Returns the `login-customer-id` to use for this account: the root manager.
+    pub fn login_customer_id(&self) -> &str {
+        self.path.first().unwrap_or(&self.customer_id)
+    }
+
+    /// Returns this node and all nodes below it, depth first.
+    pub fn iter(&self) -> impl Iterator<Item = &AccountNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+}
+
+/// The accounts under a root manager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountHierarchy {
+    pub root: AccountNode,
+}
+
+impl AccountHierarchy {
+    /// Returns all accounts, depth first from the root.
+    pub fn accounts(&self) -> impl Iterator<Item = &AccountNode> {
+        self.root.iter()
+    }
+
+    /// Returns the account with the given customer id (dashes allowed).
+    pub fn find(&self, customer_id: &str) -> Option<&AccountNode> {
+        let customer_id = normalize_customer_id(customer_id);
+        self.accounts().find(|node| node.customer_id == customer_id)
+    }
+
+    /// Returns the ids of the non-manager accounts, which are the ones holding campaigns.
+    pub fn client_customer_ids(&self) -> Vec<String> {
+        self.accounts()
+            .filter(|node| !node.manager)
+            .map(|node| node.customer_id.clone())
+            .collect()
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Walks the hierarchy under a manager, optionally skipping some accounts.
+#[derive(Debug, Clone, Default)]
+pub struct HierarchyDiscovery {
+    exclude_cancelled: bool,
+    exclude_hidden: bool,
+}
+
+impl HierarchyDiscovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out cancelled accounts and everything below them (default false).
+    pub fn exclude_cancelled(&mut self, exclude: bool) -> &mut Self {
+        self.exclude_cancelled = exclude;
+        self
+    }
+
+    /// Leaves out hidden accounts and everything below them (default false).
+    pub fn exclude_hidden(&mut self, exclude: bool) -> &mut Self {
+        self.exclude_hidden = exclude;
+        self
+    }
+
+    /// Queries `customer_client` through every manager under `root_manager_id` and
+    /// builds the tree. Fails if any manager cannot be queried.
+    pub async fn discover<T>(
+        &self,
+        mut client: GoogleAdsServiceClient<T>,
+        root_manager_id: &str,
+    ) -> anyhow::Result<AccountHierarchy>
+    where
+        T: tonic::client::GrpcService<tonic::body::Body>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        let root_id = normalize_customer_id(root_manager_id);
+        let mut nodes: HashMap<String, AccountNode> = HashMap::new();
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut managers: VecDeque<(String, Vec<String>)> = VecDeque::new();
+        managers.push_back((root_id.clone(), Vec::new()));
+
+        while let Some((manager_id, path)) = managers.pop_front() {
+            if !visited.insert(manager_id.clone()) {
+                continue;
+            }
+            let clients = query_clients(&mut client, &root_id, &manager_id).await?;
+
+            let mut child_path = path.clone();
+            child_path.push(manager_id.clone());
+            for customer_client in clients {
+                let level = customer_client.level.unwrap_or_default();
+                if level == 0 {
+                    let node = account_node(&customer_client, path.clone());
+                    // The manager itself; only the root is not already known
+                    nodes.entry(node.customer_id.clone()).or_insert(node);
+                    continue;
+                }
+                let node = account_node(&customer_client, child_path.clone());
+                if !self.includes(&node) || nodes.contains_key(&node.customer_id) {
+                    continue;
+                }
+                children
+                    .entry(manager_id.clone())
+                    .or_default()
+                    .push(node.customer_id.clone());
+                if node.manager {
+                    managers.push_back((node.customer_id.clone(), child_path.clone()));
+                }
+                nodes.insert(node.customer_id.clone(), node);
+            }
+        }
+
+        let root = assemble(&root_id, &mut nodes, &children).ok_or_else(|| {
+            anyhow::anyhow!("Customer {} was not returned by customer_client", root_id)
+        })?;
+        Ok(AccountHierarchy { root })
+    }
+
+    fn includes(&self, node: &AccountNode) -> bool {
+        let cancelled = node.status == CustomerStatus::Canceled.as_str_name();
+        !(self.exclude_cancelled && cancelled || self.exclude_hidden && node.hidden)
+    }
+}
+
+/// Discovers the whole hierarchy under `root_manager_id`, including cancelled and hidden
+/// accounts. Use [`HierarchyDiscovery`] to filter them.
+pub async fn discover_hierarchy<T>(
+    client: GoogleAdsServiceClient<T>,
+    root_manager_id: &str,
+) -> anyhow::Result<AccountHierarchy>
+where
+    T: tonic::client::GrpcService<tonic::body::Body>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    HierarchyDiscovery::new()
+        .discover(client, root_manager_id)
+        .await
+}
+
+async fn query_clients<T>(
+    client: &mut GoogleAdsServiceClient<T>,
+    login_customer_id: &str,
+    manager_id: &str,
+) -> anyhow::Result<Vec<CustomerClient>>
+where
+    T: tonic::client::GrpcService<tonic::body::Body>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    let mut request = tonic::Request::new(SearchGoogleAdsStreamRequest {
+        customer_id: manager_id.to_string(),
+        query: CUSTOMER_CLIENT_QUERY.to_string(),
+        ..Default::default()
+    });
+    request
+        .extensions_mut()
+        .insert(LoginCustomerId::new(login_customer_id));
+
+    let to_error = |status: tonic::Status| {
+        anyhow::anyhow!(
+            "Failed to list clients of {}: {}",
+            manager_id,
+            GoogleAdsError::from(status)
+        )
+    };
+    let mut stream = client
+        .search_stream(request)
+        .await
+        .map_err(to_error)?
+        .into_inner();
+    let mut clients = Vec::new();
+    while let Some(batch) = stream.message().await.map_err(to_error)? {
+        clients.extend(
+            batch
+                .results
+                .into_iter()
+                .filter_map(|row| row.customer_client),
+        );
+    }
+    Ok(clients)
+}
+
+fn account_node(customer_client: &CustomerClient, path: Vec<String>) -> AccountNode {
+    let customer_id = match customer_client.id {
+        Some(id) => id.to_string(),
+        None => customer_client
+            .client_customer
+            .as_deref()
+            .and_then(|name| name.rsplit('/').next())
+            .unwrap_or_default()
+            .to_string(),
+    };
+    AccountNode {
+        customer_id,
+        descriptive_name: customer_client.descriptive_name.clone().unwrap_or_default(),
+        manager: customer_client.manager.unwrap_or_default(),
+        hidden: customer_client.hidden.unwrap_or_default(),
+        test_account: customer_client.test_account.unwrap_or_default(),
+        status: CustomerStatus::try_from(customer_client.status)
+            .map(|status| status.as_str_name().to_string())
+            .unwrap_or_else(|_| customer_client.status.to_string()),
+        currency_code: customer_client.currency_code.clone().unwrap_or_default(),
+        time_zone: customer_client.time_zone.clone().unwrap_or_default(),
+        depth: path.len(),
+        path,
+        children: Vec::new(),
+    }
+}
+
+fn assemble(
+    customer_id: &str,
+    nodes: &mut HashMap<String, AccountNode>,
+    children: &HashMap<String, Vec<String>>,
+) -> Option<AccountNode> {
+    let mut node = nodes.remove(customer_id)?;
+    node.children = children
+        .get(customer_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| assemble(child, nodes, children))
+        .collect();
+    Some(node)
+}
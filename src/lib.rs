@@ -3,7 +3,8 @@
 //! A gRPC client library for Google Ads API, generated automatically from the API definition files.
 //!
 //! Provides `GoogleAdsRow.get(path: &str)` accessor method to easily retrieve fields selected in GAQL.
-//! Also provides `DynamicMutationBuilder` for constructing mutation requests dynamically via reflection.
+//! Also provides `DynamicMutationBuilder` for constructing mutation requests dynamically via reflection,
+//! and [`Query`] for parsing GAQL with type-checked parameter binding.
 //!
 //! # Example — Reading
 //!
@@ -36,6 +37,54 @@ mod protos {
 }
 pub use protos::*;
 
+pub mod auth;
+pub mod cassette;
+pub mod channel;
+pub mod chunking;
+pub mod client;
+pub mod config;
+pub mod error;
+pub mod fanout;
+pub mod field_mask;
+pub mod gaql;
+pub mod hierarchy;
+pub mod logging;
+pub mod middleware;
+pub mod paging;
+pub mod partial_failure;
+pub mod quota;
+pub mod rate_limit;
+pub mod retry;
+pub mod row_stream;
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "tracing")]
+pub mod telemetry;
+pub use auth::{Authenticator, RefreshTokenCredentials, ServiceAccountCredentials, TokenSource};
+pub use cassette::{CassetteLayer, CassetteMode};
+pub use channel::ChannelBuilder;
+pub use chunking::{ChunkWindow, DateChunker};
+pub use client::{GoogleAdsChannel, GoogleAdsClient, GoogleAdsInterceptor, LoginCustomerId};
+pub use config::GoogleAdsConfig;
+pub use error::{ErrorCategory, ErrorDetail, GoogleAdsError};
+pub use fanout::{CustomerRow, FanOut};
+pub use field_mask::FieldMaskReport;
+pub use gaql::{Query, QueryValue};
+pub use hierarchy::{discover_hierarchy, AccountHierarchy, AccountNode, HierarchyDiscovery};
+pub use logging::LoggingLayer;
+pub use paging::{search_paged, PagedSearch};
+pub use partial_failure::PartialFailureReport;
+pub use quota::{QuotaBudget, QuotaLayer, UsageTracker};
+pub use rate_limit::{CallKind, RateLimit, RateLimitLayer};
+pub use retry::{RetryLayer, RetryPolicy};
+pub use row_stream::{RowStream, StreamRow};
+pub use schema::{infer_schema, ColumnSchema};
+#[cfg(feature = "server")]
+pub use server::{failure_status, FakeGoogleAdsService, FakeServer};
+#[cfg(feature = "tracing")]
+pub use telemetry::TracingLayer;
+
 use once_cell::sync::Lazy;
 use prost::Message;
 use prost_reflect::{DescriptorPool, DynamicMessage, FieldDescriptor, Kind, ReflectMessage, Value};
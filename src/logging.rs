@@ -0,0 +1,387 @@
+//! Request/response logging of Google Ads API calls, with redaction of secrets and PII.
+//!
+//! [`LoggingLayer`] is a tower layer for the channel under the generated clients. When a
+//! call finishes it logs, through the [`log`] crate, one line with the method, customer
+//! id, `request-id`, status and latency: at `info` level for successful calls and `warn`
+//! for failures, which also list the Google Ads error codes.
+//!
+//! At `debug` level the request metadata and every request and response message are
+//! logged as well. Messages are decoded with the descriptor pool and rendered in protobuf
+//! text format, so GAQL queries and mutate operations appear in full. Before rendering:
+//!
+//! - the values of redacted metadata headers (by default `authorization` and
+//!   `developer-token`) are replaced by `[REDACTED]`;
+//! - redacted fields (by default `user_identifiers`, `hashed_email`, `hashed_phone_number`
+//!   and `address_info`, wherever they appear) are blanked: strings and bytes read
+//!   `[REDACTED]`, messages are emptied and other values are reset, repeated fields keep
+//!   their length.
+//!
+//! Fields are named either by their name alone, matching any message, or by their fully
+//! qualified name, e.g. `google.ads.googleads.v23.common.UserData.user_identifiers`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut logging = LoggingLayer::new();
+//! logging.redact_header("login-customer-id").redact_field("gclid");
+//!
+//! let channel = ServiceBuilder::new()
+//!     .layer(logging)
+//!     .service(client.channel().clone());
+//! let service = GoogleAdsServiceClient::with_interceptor(channel, client.interceptor().clone());
+//! // INFO GoogleAdsService/Search customer_id=1234567890 request_id=Xy12 status=Ok latency_ms=181
+//! ```
+
+use crate::error::GoogleAdsError;
+use crate::middleware::{split_messages, take_ready, BufferedRequest, CallObserver, ObservedBody};
+use bytes::BytesMut;
+use futures::future::BoxFuture;
+use log::Level;
+use prost_reflect::{
+    DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, Value,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::{Code, Status};
+
+/// Replacement for redacted header values and string fields.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Metadata headers redacted by default.
+pub const DEFAULT_REDACTED_HEADERS: &[&str] = &["authorization", "developer-token"];
+
+/// Fields redacted by default: the user data of customer match and enhanced conversions.
+pub const DEFAULT_REDACTED_FIELDS: &[&str] = &[
+    "user_identifiers",
+    "hashed_email",
+    "hashed_phone_number",
+    "address_info",
+];
+
+/// Returns the short name of a gRPC method path, e.g. `GoogleAdsService/SearchStream` for
+/// `/google.ads.googleads.v23.services.GoogleAdsService/SearchStream`.
+pub fn method_name(path: &str) -> String {
+    let path = path.trim_start_matches('/');
+    match path.split_once('/') {
+        Some((service, method)) => {
+            let service = service.rsplit('.').next().unwrap_or(service);
+            format!("{}/{}", service, method)
+        }
+        None => path.to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Redaction
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+struct Redaction {
+    headers: BTreeSet<String>,
+    fields: BTreeSet<String>,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_REDACTED_HEADERS
+                .iter()
+                .map(|h| h.to_string())
+                .collect(),
+            fields: DEFAULT_REDACTED_FIELDS
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Redaction {
+    fn redacts_field(&self, field: &FieldDescriptor) -> bool {
+        self.fields.contains(field.name()) || self.fields.contains(field.full_name())
+    }
+
+    fn redact(&self, message: &mut DynamicMessage) {
+        let fields: Vec<FieldDescriptor> = message.fields().map(|(field, _)| field).collect();
+        for field in fields {
+            if self.redacts_field(&field) {
+                let value = blank(message.get_field(&field).as_ref(), &field.kind());
+                message.set_field(&field, value);
+                continue;
+            }
+            match message.get_field_mut(&field) {
+                Value::Message(nested) => self.redact(nested),
+                Value::List(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+                Value::Map(entries) => entries
+                    .values_mut()
+                    .for_each(|item| self.redact_value(item)),
+                _ => {}
+            }
+        }
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        if let Value::Message(nested) = value {
+            self.redact(nested);
+        }
+    }
+
+    fn render(&self, descriptor: &MessageDescriptor, bytes: Bytes) -> String {
+        let len = bytes.len();
+        match DynamicMessage::decode(descriptor.clone(), bytes) {
+            Ok(message) => self.render_decoded(message),
+            Err(_) => format!("<undecodable {} bytes>", len),
+        }
+    }
+
+    fn render_decoded(&self, mut message: DynamicMessage) -> String {
+        self.redact(&mut message);
+        message.to_text_format()
+    }
+
+    fn render_headers(&self, headers: &http::HeaderMap) -> String {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.headers.contains(name.as_str()) {
+                    REDACTED
+                } else {
+                    value.to_str().unwrap_or("<binary>")
+                };
+                format!("{}: {}", name, value)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// Blanks a redacted value while keeping its type (and length, for repeated fields)
+fn blank(value: &Value, kind: &Kind) -> Value {
+    match value {
+        Value::String(_) => Value::String(REDACTED.to_string()),
+        Value::Bytes(_) => Value::Bytes(Bytes::from_static(REDACTED.as_bytes())),
+        Value::Message(message) => Value::Message(DynamicMessage::new(message.descriptor())),
+        Value::List(items) => Value::List(items.iter().map(|item| blank(item, kind)).collect()),
+        Value::Map(_) => Value::Map(HashMap::new()),
+        _ => Value::default_value(kind),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Layer
+// ---------------------------------------------------------------------------
+
+/// Tower layer logging every call made through the channel it wraps.
+#[derive(Debug, Clone, Default)]
+pub struct LoggingLayer {
+    redaction: Arc<Redaction>,
+}
+
+impl LoggingLayer {
+    /// Creates a layer redacting [`DEFAULT_REDACTED_HEADERS`] and [`DEFAULT_REDACTED_FIELDS`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redacts the value of a metadata header (case-insensitive).
+    pub fn redact_header(&mut self, name: &str) -> &mut Self {
+        Arc::make_mut(&mut self.redaction)
+            .headers
+            .insert(name.to_ascii_lowercase());
+        self
+    }
+
+    /// Redacts a field, by name in any message or by fully qualified name.
+    pub fn redact_field(&mut self, name: &str) -> &mut Self {
+        Arc::make_mut(&mut self.redaction)
+            .fields
+            .insert(name.to_string());
+        self
+    }
+
+    /// Removes all redactions, including the defaults.
+    pub fn clear_redactions(&mut self) -> &mut Self {
+        let redaction = Arc::make_mut(&mut self.redaction);
+        redaction.headers.clear();
+        redaction.fields.clear();
+        self
+    }
+
+    /// Decodes `bytes` as a `descriptor` message and renders it in text format, with the
+    /// redacted fields blanked, as it would be logged.
+    pub fn render_message(&self, descriptor: &MessageDescriptor, bytes: Bytes) -> String {
+        self.redaction.render(descriptor, bytes)
+    }
+}
+
+impl<S> tower::Layer<S> for LoggingLayer {
+    type Service = Logging<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Logging {
+            inner,
+            redaction: self.redaction.clone(),
+        }
+    }
+}
+
+/// gRPC channel logging each call; see the [module documentation](self).
+///
+/// The request body is buffered to read the customer id. The response body is passed
+/// through as it streams, and the call is logged when its trailers arrive or when the
+/// response is dropped.
+#[derive(Debug, Clone)]
+pub struct Logging<S> {
+    inner: S,
+    redaction: Arc<Redaction>,
+}
+
+impl<S, ResBody> tower::Service<http::Request<tonic::body::Body>> for Logging<S>
+where
+    S: tower::Service<http::Request<tonic::body::Body>, Response = http::Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: fmt::Display,
+{
+    type Response = http::Response<ObservedBody<ResBody>>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let mut inner = take_ready(&mut self.inner);
+        let redaction = self.redaction.clone();
+
+        Box::pin(async move {
+            if !log::log_enabled!(Level::Info) {
+                let response = inner.call(request).await.map_err(Into::into)?;
+                return Ok(ObservedBody::unobserved(response));
+            }
+
+            let request = BufferedRequest::new(request).await?;
+            let call = CallLog::new(&request, redaction);
+            match inner.call(request.into_request()).await {
+                Ok(response) => Ok(ObservedBody::observe(response, call)),
+                Err(err) => {
+                    let err = err.into();
+                    call.log(Status::unavailable(format!("transport error: {}", err)));
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+// What is known about a call until it is logged
+struct CallLog {
+    method: String,
+    output: Option<MessageDescriptor>,
+    redaction: Arc<Redaction>,
+    debug: bool,
+    customer_id: Option<String>,
+    request_id: Option<String>,
+    buffer: BytesMut,
+    messages: usize,
+    start: Instant,
+}
+
+impl CallLog {
+    fn new(request: &BufferedRequest, redaction: Arc<Redaction>) -> Self {
+        let method = method_name(request.path());
+        let debug = log::log_enabled!(Level::Debug);
+        if debug {
+            log::debug!(
+                "{} metadata: {}",
+                method,
+                redaction.render_headers(request.headers())
+            );
+            if let Some(message) = request.message() {
+                log::debug!(
+                    "{} request: {}",
+                    method,
+                    redaction.render_decoded(message.clone())
+                );
+            }
+        }
+        Self {
+            method,
+            output: request.method().map(|m| m.output()),
+            redaction,
+            debug,
+            customer_id: request.customer_id(),
+            request_id: None,
+            buffer: BytesMut::new(),
+            messages: 0,
+            start: Instant::now(),
+        }
+    }
+
+    fn log(self, status: Status) {
+        let latency = self.start.elapsed().as_millis();
+        let customer_id = self.customer_id.as_deref().unwrap_or("-");
+        let request_id = self.request_id.as_deref().unwrap_or("-");
+        if status.code() == Code::Ok {
+            log::info!(
+                "{} customer_id={} request_id={} status=Ok latency_ms={} messages={}",
+                self.method,
+                customer_id,
+                request_id,
+                latency,
+                self.messages
+            );
+            return;
+        }
+        let error = GoogleAdsError::from_status(&status);
+        let codes: Vec<String> = error.errors.iter().map(|e| e.error_code()).collect();
+        log::warn!(
+            "{} customer_id={} request_id={} status={:?} latency_ms={} errors=[{}] {}",
+            self.method,
+            customer_id,
+            error.request_id.as_deref().unwrap_or(request_id),
+            status.code(),
+            latency,
+            codes.join(", "),
+            status.message()
+        );
+    }
+}
+
+impl CallObserver for CallLog {
+    fn headers(&mut self, headers: &http::HeaderMap) {
+        self.request_id = headers
+            .get(crate::error::REQUEST_ID_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+    }
+
+    fn data(&mut self, data: &Bytes) {
+        self.buffer.extend_from_slice(data);
+        for (compressed, bytes) in split_messages(&mut self.buffer) {
+            self.messages += 1;
+            if !self.debug {
+                continue;
+            }
+            let rendered = match (&self.output, compressed) {
+                (_, true) => format!("<compressed {} bytes>", bytes.len()),
+                (Some(output), false) => self.redaction.render(output, bytes),
+                (None, false) => format!("<{} bytes>", bytes.len()),
+            };
+            log::debug!("{} response: {}", self.method, rendered);
+        }
+    }
+
+    fn finish(self: Box<Self>, status: Status) {
+        self.log(status)
+    }
+}
@@ -0,0 +1,323 @@
+//! Building blocks of the tower layers that wrap the channel under the generated clients.
+//!
+//! The layers of this crate ([`LoggingLayer`](crate::LoggingLayer), `TracingLayer`,
+//! [`CassetteLayer`](crate::CassetteLayer), [`QuotaLayer`](crate::QuotaLayer) and
+//! [`RateLimitLayer`](crate::RateLimitLayer)) work on the same pieces: the request body is
+//! buffered and decoded with the descriptor pool to read its customer id, and the response
+//! body is passed through as it streams while an observer follows its messages and is
+//! told the final status of the call.
+//!
+//! The status of a call comes from the trailers, or from the headers of a trailers-only
+//! response. Clients read the latter from the headers and drop the body without polling
+//! it, so a body dropped before its end still reports that status.
+
+use crate::descriptor_pool;
+use bytes::{Buf, BytesMut};
+use http_body_util::{BodyExt, Full};
+use prost::encoding::{decode_key, decode_varint, WireType};
+use prost_reflect::{DynamicMessage, MessageDescriptor, MethodDescriptor, Value};
+use std::fmt::{self, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::Status;
+
+// Returns the first 16 hex digits of the SHA-256 of `data`
+pub(crate) fn short_digest(data: &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, data);
+    digest.as_ref()[..8]
+        .iter()
+        .fold(String::with_capacity(16), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+// Takes the service that was driven to ready, leaving a clone for the next call
+pub(crate) fn take_ready<S: Clone>(service: &mut S) -> S {
+    let clone = service.clone();
+    std::mem::replace(service, clone)
+}
+
+// ---------------------------------------------------------------------------
+// Requests
+// ---------------------------------------------------------------------------
+
+// Looks up the descriptor of a gRPC method path in the descriptor pool
+pub(crate) fn method_descriptor(path: &str) -> Option<MethodDescriptor> {
+    let (service, method) = path.trim_start_matches('/').split_once('/')?;
+    descriptor_pool()
+        .get_service_by_name(service)?
+        .methods()
+        .find(|m| m.name() == method)
+}
+
+// Reads `customer_id`, or the id in a `customers/{id}/...` `resource_name`
+pub(crate) fn request_customer_id(message: &DynamicMessage) -> Option<String> {
+    let string_field = |name: &str| match message.get_field_by_name(name)?.as_ref() {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    };
+    string_field("customer_id").or_else(|| {
+        let resource_name = string_field("resource_name")?;
+        let id = resource_name
+            .strip_prefix("customers/")?
+            .split('/')
+            .next()?;
+        Some(id.to_string())
+    })
+}
+
+// Splits complete gRPC messages off the front of `buffer`, leaving any partial message
+pub(crate) fn split_messages(buffer: &mut BytesMut) -> Vec<(bool, Bytes)> {
+    let mut messages = Vec::new();
+    while buffer.len() >= 5 {
+        let len = u32::from_be_bytes([buffer[1], buffer[2], buffer[3], buffer[4]]) as usize;
+        if buffer.len() < 5 + len {
+            break;
+        }
+        let compressed = buffer[0] != 0;
+        buffer.advance(5);
+        messages.push((compressed, buffer.split_to(len).freeze()));
+    }
+    messages
+}
+
+// A request with its body buffered and its message decoded
+pub(crate) struct BufferedRequest {
+    parts: http::request::Parts,
+    body: Bytes,
+    method: Option<MethodDescriptor>,
+    message: Option<DynamicMessage>,
+}
+
+impl BufferedRequest {
+    // Buffers the body; the message is decoded if the method is known and it is not
+    // compressed (Google Ads requests are unary, so it is the only one)
+    pub(crate) async fn new(request: http::Request<tonic::body::Body>) -> Result<Self, StdError> {
+        let (parts, body) = request.into_parts();
+        let body = body.collect().await?.to_bytes();
+        let method = method_descriptor(parts.uri.path());
+        let message = method.as_ref().and_then(|method| {
+            let (compressed, bytes) = split_messages(&mut BytesMut::from(&body[..]))
+                .into_iter()
+                .next()?;
+            if compressed {
+                return None;
+            }
+            DynamicMessage::decode(method.input(), bytes).ok()
+        });
+        Ok(Self {
+            parts,
+            body,
+            method,
+            message,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        self.parts.uri.path()
+    }
+
+    pub(crate) fn headers(&self) -> &http::HeaderMap {
+        &self.parts.headers
+    }
+
+    pub(crate) fn method(&self) -> Option<&MethodDescriptor> {
+        self.method.as_ref()
+    }
+
+    pub(crate) fn message(&self) -> Option<&DynamicMessage> {
+        self.message.as_ref()
+    }
+
+    pub(crate) fn customer_id(&self) -> Option<String> {
+        self.message.as_ref().and_then(request_customer_id)
+    }
+
+    // Rebuilds the request to send it on
+    pub(crate) fn into_request(self) -> http::Request<tonic::body::Body> {
+        http::Request::from_parts(self.parts, tonic::body::Body::new(Full::new(self.body)))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Responses
+// ---------------------------------------------------------------------------
+
+// Field numbers of `results` and `query_resource_consumption` in the response message
+#[derive(Clone, Copy)]
+pub(crate) struct ResponseFields {
+    pub(crate) results: Option<u32>,
+    pub(crate) consumption: Option<u32>,
+}
+
+impl ResponseFields {
+    pub(crate) fn new(output: &MessageDescriptor) -> Self {
+        let number = |name: &str| output.get_field_by_name(name).map(|f| f.number());
+        Self {
+            results: number("results"),
+            consumption: number("query_resource_consumption"),
+        }
+    }
+
+    // Reads the counters off the wire, without decoding the rows
+    pub(crate) fn scan(&self, mut message: Bytes) -> Option<(u64, i64)> {
+        let (mut rows, mut consumption) = (0, 0);
+        while message.has_remaining() {
+            let (number, wire_type) = decode_key(&mut message).ok()?;
+            match wire_type {
+                WireType::Varint => {
+                    let value = decode_varint(&mut message).ok()?;
+                    if Some(number) == self.consumption {
+                        consumption += value as i64;
+                    }
+                }
+                WireType::LengthDelimited => {
+                    let len = decode_varint(&mut message).ok()? as usize;
+                    if len > message.remaining() {
+                        return None;
+                    }
+                    message.advance(len);
+                    if Some(number) == self.results {
+                        rows += 1;
+                    }
+                }
+                WireType::SixtyFourBit if message.remaining() >= 8 => message.advance(8),
+                WireType::ThirtyTwoBit if message.remaining() >= 4 => message.advance(4),
+                _ => return None,
+            }
+        }
+        Some((rows, consumption))
+    }
+}
+
+// Follows a response for an ObservedBody
+pub(crate) trait CallObserver: Send + 'static {
+    // Called with the response headers, before the body is read
+    fn headers(&mut self, _headers: &http::HeaderMap) {}
+
+    // Called with each data frame of the body
+    fn data(&mut self, _data: &Bytes) {}
+
+    // Called once with the status the call ended with
+    fn finish(self: Box<Self>, status: Status);
+
+    // Called instead of `finish` when the body fails or is dropped before its end
+    fn abort(self: Box<Self>, status: Status) {
+        self.finish(status)
+    }
+}
+
+/// Response body of the layers of this crate, passing the body through as it streams and
+/// reporting it to the layer; see the [module documentation](self).
+pub struct ObservedBody<B> {
+    inner: Pin<Box<B>>,
+    observer: Option<Box<dyn CallObserver>>,
+    header_status: Option<Status>,
+}
+
+impl<B> ObservedBody<B> {
+    // Wraps the body of `response`, reporting it to `observer`
+    pub(crate) fn observe(
+        response: http::Response<B>,
+        mut observer: impl CallObserver,
+    ) -> http::Response<Self> {
+        observer.headers(response.headers());
+        let header_status = Status::from_header_map(response.headers());
+        response.map(|body| Self {
+            inner: Box::pin(body),
+            observer: Some(Box::new(observer)),
+            header_status,
+        })
+    }
+
+    // Wraps the body of `response` without observing it
+    pub(crate) fn unobserved(response: http::Response<B>) -> http::Response<Self> {
+        response.map(|body| Self::new(body))
+    }
+
+    fn new(inner: B) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            observer: None,
+            header_status: None,
+        }
+    }
+}
+
+// InterceptedService answers calls rejected by the interceptor with a default body
+impl<B: Default> Default for ObservedBody<B> {
+    fn default() -> Self {
+        Self::new(B::default())
+    }
+}
+
+impl<B> fmt::Debug for ObservedBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObservedBody").finish_non_exhaustive()
+    }
+}
+
+impl<B> Body for ObservedBody<B>
+where
+    B: Body<Data = Bytes>,
+    B::Error: fmt::Display,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Bytes>, Self::Error>>> {
+        let this = self.get_mut();
+        let result = ready!(this.inner.as_mut().poll_frame(cx));
+        match &result {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    if let Some(observer) = this.observer.as_mut() {
+                        observer.data(data);
+                    }
+                } else if let Some(trailers) = frame.trailers_ref() {
+                    if let Some(observer) = this.observer.take() {
+                        observer.finish(
+                            Status::from_header_map(trailers).unwrap_or_else(|| Status::ok("")),
+                        );
+                    }
+                }
+            }
+            Some(Err(err)) => {
+                if let Some(observer) = this.observer.take() {
+                    observer.abort(Status::unknown(err.to_string()));
+                }
+            }
+            None => {
+                if let Some(observer) = this.observer.take() {
+                    let status = this.header_status.take();
+                    observer.finish(status.unwrap_or_else(|| Status::ok("")));
+                }
+            }
+        }
+        Poll::Ready(result)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> Drop for ObservedBody<B> {
+    fn drop(&mut self) {
+        if let Some(observer) = self.observer.take() {
+            match self.header_status.take() {
+                Some(status) => observer.finish(status),
+                None => observer.abort(Status::cancelled("response dropped before the end")),
+            }
+        }
+    }
+}
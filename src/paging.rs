@@ -0,0 +1,232 @@
+//! Paginated `GoogleAdsService.Search` as a stream of rows.
+//!
+//! `Search` returns results in pages linked by `next_page_token`. [`PagedSearch`] follows
+//! the tokens and yields the rows of every page as one stream. The `total_results_count`
+//! and field mask of the first page are kept for the caller, and the token of the page
+//! being read can be saved to resume the search later, e.g. after a crash.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut rows = client.search_paged("1234567890", "SELECT campaign.id FROM campaign");
+//! while let Some(row) = rows.next().await {
+//!     let row = row?;
+//!     println!("{} of {:?}", row.get("campaign.id"), rows.total_results_count());
+//!     checkpoint(rows.page_token());
+//! }
+//!
+//! // Later: start again from the saved page
+//! let mut rows = client.search_paged("1234567890", query);
+//! rows.resume_from(saved_token);
+//! ```
+
+use crate::client::normalize_customer_id;
+use crate::error::GoogleAdsError;
+use crate::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsRequest,
+    SearchGoogleAdsResponse, SearchSettings,
+};
+use futures::future::BoxFuture;
+use futures::{FutureExt, Stream};
+use prost_types::FieldMask;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::Status;
+
+type PageFuture<T> = BoxFuture<
+    'static,
+    (
+        GoogleAdsServiceClient<T>,
+        Result<SearchGoogleAdsResponse, Status>,
+    ),
+>;
+
+/// Stream of the rows of every page of a `Search` request.
+pub struct PagedSearch<T> {
+    client: Option<GoogleAdsServiceClient<T>>,
+    request: SearchGoogleAdsRequest,
+    pending: Option<PageFuture<T>>,
+    rows: std::vec::IntoIter<GoogleAdsRow>,
+    page_token: Option<String>,
+    next_page_token: Option<String>,
+    total_results_count: Option<i64>,
+    field_mask: Option<FieldMask>,
+    pages: usize,
+    done: bool,
+}
+
+// The client is only moved in and out of the page future, never pinned
+impl<T> Unpin for PagedSearch<T> {}
+
+impl<T> PagedSearch<T>
+where
+    T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    T::Future: Send,
+{
+    /// Prepares the search; no request is sent until the stream is polled.
+    pub fn new(client: GoogleAdsServiceClient<T>, customer_id: &str, query: &str) -> Self {
+        Self {
+            client: Some(client),
+            request: SearchGoogleAdsRequest {
+                customer_id: normalize_customer_id(customer_id),
+                query: query.to_string(),
+                search_settings: Some(SearchSettings {
+                    return_total_results_count: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            pending: None,
+            rows: Vec::new().into_iter(),
+            page_token: None,
+            next_page_token: None,
+            total_results_count: None,
+            field_mask: None,
+            pages: 0,
+            done: false,
+        }
+    }
+
+    /// Starts from a page token saved from [`page_token`](Self::page_token) or
+    /// [`next_page_token`](Self::next_page_token) of an earlier search with the same query.
+    pub fn resume_from(&mut self, page_token: &str) -> &mut Self {
+        self.next_page_token = Some(page_token.to_string()).filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Sets whether the first page should include `total_results_count` (default true).
+    pub fn return_total_results_count(&mut self, enabled: bool) -> &mut Self {
+        if let Some(settings) = self.request.search_settings.as_mut() {
+            settings.return_total_results_count = enabled;
+        }
+        self
+    }
+
+    fn fetch(&mut self) {
+        let mut client = self.client.take().expect("no page request in flight");
+        let mut request = self.request.clone();
+        request.page_token = self.next_page_token.clone().unwrap_or_default();
+        // Only the first page carries the total, so later pages skip computing it
+        if self.pages > 0 {
+            request.search_settings = None;
+        }
+        self.page_token = self.next_page_token.take();
+        self.pending = Some(
+            async move {
+                let response = client.search(request).await.map(|r| r.into_inner());
+                (client, response)
+            }
+            .boxed(),
+        );
+    }
+}
+
+impl<T> PagedSearch<T> {
+    /// Returns `total_results_count` of the first page, once it has been received.
+    ///
+    /// The count ignores any `LIMIT` clause of the query.
+    pub fn total_results_count(&self) -> Option<i64> {
+        self.total_results_count
+    }
+
+    /// Returns the field mask of the first page, once it has been received.
+    pub fn field_mask(&self) -> Option<&FieldMask> {
+        self.field_mask.as_ref()
+    }
+
+    /// Returns the token of the page whose rows are being yielded, or `None` for the
+    /// first page. Resuming from it yields that page again from its first row.
+    pub fn page_token(&self) -> Option<&str> {
+        self.page_token.as_deref()
+    }
+
+    /// Returns the token of the page after the one being yielded, or `None` if it is the
+    /// last page. Resume from it once every row of the current page has been handled.
+    pub fn next_page_token(&self) -> Option<&str> {
+        self.next_page_token.as_deref()
+    }
+
+    /// Returns the number of pages received so far.
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+}
+
+impl<T> Stream for PagedSearch<T>
+where
+    T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    T::Future: Send,
+{
+    type Item = Result<GoogleAdsRow, GoogleAdsError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(row) = this.rows.next() {
+                return Poll::Ready(Some(Ok(row)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if this.pending.is_none() {
+                if this.pages > 0 && this.next_page_token.is_none() {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                this.fetch();
+            }
+
+            let pending = this.pending.as_mut().expect("page request in flight");
+            let (client, response) = match pending.poll_unpin(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending = None;
+            this.client = Some(client);
+
+            match response {
+                Ok(page) => {
+                    if this.pages == 0 {
+                        this.total_results_count = this
+                            .request
+                            .search_settings
+                            .as_ref()
+                            .filter(|s| s.return_total_results_count)
+                            .map(|_| page.total_results_count);
+                        this.field_mask = page.field_mask;
+                    }
+                    this.pages += 1;
+                    this.next_page_token = Some(page.next_page_token).filter(|t| !t.is_empty());
+                    this.rows = page.results.into_iter();
+                }
+                Err(status) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(GoogleAdsError::from_status(&status))));
+                }
+            }
+        }
+    }
+}
+
+/// Returns a stream of the rows of every page of `query`.
+pub fn search_paged<T>(
+    client: GoogleAdsServiceClient<T>,
+    customer_id: &str,
+    query: &str,
+) -> PagedSearch<T>
+where
+    T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    T::Future: Send,
+{
+    PagedSearch::new(client, customer_id, query)
+}
@@ -0,0 +1,169 @@
+//! Interpretation of partial-failure mutate responses.
+//!
+//! With `partial_failure: true` (the [`DynamicMutationBuilder`](crate::DynamicMutationBuilder)
+//! default), a mutate succeeds even if some operations fail. The failures come back as a
+//! `google.rpc.Status` in `partial_failure_error`, whose details hold a `GoogleAdsFailure`,
+//! and each error's location starts with the index of its operation, e.g.
+//! `mutate_operations[2].campaign_operation.update.name`. Failed operations get an empty
+//! entry in the results, so results stay aligned with operations.
+//!
+//! [`PartialFailureReport`] attaches each error to its operation and field path, and pairs
+//! the results of the successful operations with their inputs.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let response = client.google_ads().mutate(request.clone()).await?.into_inner();
+//! let report = PartialFailureReport::from_mutate(&request, &response);
+//! for outcome in report.failed() {
+//!     for error in &outcome.errors {
+//!         eprintln!("operation {}: {} at {}", outcome.index, error.detail, error.field_path);
+//!     }
+//! }
+//! for outcome in report.succeeded() {
+//!     println!("operation {} -> {:?}", outcome.index, outcome.result);
+//! }
+//! ```
+
+use crate::error::{format_field_path, ErrorDetail, GoogleAdsError};
+use crate::google::ads::googleads::v23::services::{
+    MutateGoogleAdsRequest, MutateGoogleAdsResponse, MutateOperation, MutateOperationResponse,
+};
+use crate::google::rpc::Status;
+
+/// Name of the operations field of `MutateGoogleAdsRequest`.
+pub const MUTATE_OPERATIONS_FIELD: &str = "mutate_operations";
+
+/// Outcome of each operation of a partial-failure mutate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialFailureReport<O = MutateOperation, R = MutateOperationResponse> {
+    /// Outcomes in operation order.
+    pub operations: Vec<OperationOutcome<O, R>>,
+    /// Errors whose location does not point at an operation of the request.
+    pub unattributed: Vec<ErrorDetail>,
+    /// The decoded `partial_failure_error`, if any operation failed.
+    pub error: Option<GoogleAdsError>,
+}
+
+/// An operation of the request, with its result or its errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationOutcome<O, R> {
+    /// Index of the operation in the request.
+    pub index: usize,
+    /// The operation as sent.
+    pub operation: O,
+    /// The result of the operation, if it succeeded.
+    pub result: Option<R>,
+    /// The errors of the operation, empty if it succeeded.
+    pub errors: Vec<OperationError>,
+}
+
+impl<O, R> OperationOutcome<O, R> {
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// An error attached to an operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationError {
+    /// Path of the failing field within the operation, e.g. `campaign_operation.update.name`.
+    /// Empty if the error applies to the whole operation.
+    pub field_path: String,
+    /// The decoded error.
+    pub detail: ErrorDetail,
+}
+
+impl PartialFailureReport {
+    /// Builds the report of a `GoogleAdsService.Mutate` call.
+    pub fn from_mutate(
+        request: &MutateGoogleAdsRequest,
+        response: &MutateGoogleAdsResponse,
+    ) -> Self {
+        Self::from_parts(
+            MUTATE_OPERATIONS_FIELD,
+            &request.mutate_operations,
+            &response.mutate_operation_responses,
+            response.partial_failure_error.as_ref(),
+        )
+    }
+}
+
+impl<O: Clone, R: Clone> PartialFailureReport<O, R> {
+    /// Builds the report of any mutate call from its operations, results and
+    /// `partial_failure_error`. `operations_field` is the name of the request's operations
+    /// field that error locations start with, e.g. `operations` for `MutateCampaigns`.
+    pub fn from_parts(
+        operations_field: &str,
+        operations: &[O],
+        results: &[R],
+        partial_failure_error: Option<&Status>,
+    ) -> Self {
+        let mut outcomes: Vec<OperationOutcome<O, R>> = operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| OperationOutcome {
+                index,
+                operation: operation.clone(),
+                result: None,
+                errors: Vec::new(),
+            })
+            .collect();
+
+        // An OK status without details means every operation succeeded
+        let error = partial_failure_error
+            .filter(|status| status.code != 0 || !status.details.is_empty())
+            .map(GoogleAdsError::from_partial_failure);
+
+        let mut unattributed = Vec::new();
+        for detail in error.iter().flat_map(|e| e.errors.iter()) {
+            let elements = detail
+                .error
+                .location
+                .as_ref()
+                .map(|l| l.field_path_elements.as_slice())
+                .unwrap_or_default();
+            let outcome = match elements.split_first() {
+                Some((first, _)) if first.field_name == operations_field => first
+                    .index
+                    .and_then(|index| usize::try_from(index).ok())
+                    .and_then(|index| outcomes.get_mut(index)),
+                _ => None,
+            };
+            match outcome {
+                Some(outcome) => outcome.errors.push(OperationError {
+                    field_path: format_field_path(&elements[1..]),
+                    detail: detail.clone(),
+                }),
+                None => unattributed.push(detail.clone()),
+            }
+        }
+
+        for outcome in &mut outcomes {
+            if outcome.is_success() {
+                outcome.result = results.get(outcome.index).cloned();
+            }
+        }
+
+        Self {
+            operations: outcomes,
+            unattributed,
+            error,
+        }
+    }
+
+    /// Returns the operations that succeeded, with their results.
+    pub fn succeeded(&self) -> impl Iterator<Item = &OperationOutcome<O, R>> {
+        self.operations.iter().filter(|o| o.is_success())
+    }
+
+    /// Returns the operations that failed, with their errors.
+    pub fn failed(&self) -> impl Iterator<Item = &OperationOutcome<O, R>> {
+        self.operations.iter().filter(|o| !o.is_success())
+    }
+
+    /// Returns true if any error was reported.
+    pub fn has_failures(&self) -> bool {
+        self.operations.iter().any(|o| !o.is_success()) || !self.unattributed.is_empty()
+    }
+}
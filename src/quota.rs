@@ -0,0 +1,487 @@
+//! Daily usage tracking and quota budgets.
+//!
+//! [`QuotaLayer`] is a tower layer for the channel under the generated clients. It counts,
+//! per day, developer token and customer id, into a shared [`UsageTracker`]:
+//!
+//! - `requests`: calls made;
+//! - `operations`: API operations as Google Ads counts them against the daily limits of
+//!   the developer token, one per mutate operation and one per other request (each
+//!   `Search` page, each `SearchStream` call);
+//! - `query_resource_consumption`: summed over the response messages that carry it.
+//!
+//! A [`QuotaBudget`] caps these per developer token and per customer. A call that would
+//! exceed a budget is either rejected locally with `RESOURCE_EXHAUSTED`, without reaching
+//! the API, or delayed until the budget resets. Budgets reset at midnight UTC; resource
+//! consumption is only known once responses arrive, so its budget stops calls once it has
+//! been reached rather than before.
+//!
+//! Developer tokens are never stored: usage is keyed by their [`token_fingerprint`].
+//! [`UsageTracker::snapshot`] returns the numbers as serializable records for dashboards.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut budget = QuotaBudget::basic_access();
+//! budget
+//!     .per_customer(DailyLimits { requests: Some(2_000), ..Default::default() })
+//!     .on_exceeded(OverBudget::Delay);
+//! let tracker = UsageTracker::with_budget(budget);
+//!
+//! let channel = ServiceBuilder::new()
+//!     .layer(QuotaLayer::new(tracker.clone()))
+//!     .service(client.channel().clone());
+//! let service = GoogleAdsServiceClient::with_interceptor(channel, client.interceptor().clone());
+//! // ...
+//! println!("{}", serde_json::to_string(&tracker.snapshot())?);
+//! ```
+
+use crate::middleware::{
+    short_digest, split_messages, take_ready, BufferedRequest, CallObserver, ObservedBody,
+    ResponseFields,
+};
+use bytes::BytesMut;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use futures::future::BoxFuture;
+use prost_reflect::{DynamicMessage, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::Status;
+
+/// Daily operations allowed to a developer token with Basic Access.
+pub const BASIC_ACCESS_DAILY_OPERATIONS: u64 = 15_000;
+
+type Clock = dyn Fn() -> DateTime<Utc> + Send + Sync;
+
+/// Returns the key identifying a developer token in usage records: the first 16 hex
+/// digits of its SHA-256.
+pub fn token_fingerprint(developer_token: &str) -> String {
+    short_digest(developer_token.as_bytes())
+}
+
+// ---------------------------------------------------------------------------
+// Budget
+// ---------------------------------------------------------------------------
+
+/// Daily caps of one developer token or one customer; `None` is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DailyLimits {
+    pub requests: Option<u64>,
+    pub operations: Option<u64>,
+    pub query_resource_consumption: Option<u64>,
+}
+
+/// What happens to a call that would exceed a budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverBudget {
+    /// Fail the call with `RESOURCE_EXHAUSTED`.
+    #[default]
+    Reject,
+    /// Wait until the budget resets, then make the call.
+    Delay,
+}
+
+/// Daily budgets enforced by a [`UsageTracker`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuotaBudget {
+    per_developer_token: DailyLimits,
+    per_customer: DailyLimits,
+    over_budget: OverBudget,
+}
+
+impl QuotaBudget {
+    /// Creates a budget without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a budget of [`BASIC_ACCESS_DAILY_OPERATIONS`] per developer token.
+    pub fn basic_access() -> Self {
+        let mut budget = Self::new();
+        budget.per_developer_token(DailyLimits {
+            operations: Some(BASIC_ACCESS_DAILY_OPERATIONS),
+            ..Default::default()
+        });
+        budget
+    }
+
+    /// Sets the daily limits of each developer token, across all its customers.
+    pub fn per_developer_token(&mut self, limits: DailyLimits) -> &mut Self {
+        self.per_developer_token = limits;
+        self
+    }
+
+    /// Sets the daily limits of each customer id.
+    pub fn per_customer(&mut self, limits: DailyLimits) -> &mut Self {
+        self.per_customer = limits;
+        self
+    }
+
+    /// Sets what happens to calls over budget (default: [`OverBudget::Reject`]).
+    pub fn on_exceeded(&mut self, over_budget: OverBudget) -> &mut Self {
+        self.over_budget = over_budget;
+        self
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Usage
+// ---------------------------------------------------------------------------
+
+/// Usage counters over one day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub requests: u64,
+    pub operations: u64,
+    pub query_resource_consumption: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.requests += other.requests;
+        self.operations += other.operations;
+        self.query_resource_consumption += other.query_resource_consumption;
+    }
+
+    // Names the first limit one more request of `operations` operations would exceed
+    fn exceeds(&self, limits: &DailyLimits, operations: u64) -> Option<String> {
+        let over = |used: u64, limit: Option<u64>| limit.filter(|&limit| used > limit);
+        if let Some(limit) = over(self.requests + 1, limits.requests) {
+            return Some(format!("requests budget of {}", limit));
+        }
+        if let Some(limit) = over(self.operations + operations, limits.operations) {
+            return Some(format!("operations budget of {}", limit));
+        }
+        if let Some(limit) = limits
+            .query_resource_consumption
+            .filter(|&limit| self.query_resource_consumption >= limit)
+        {
+            return Some(format!("query_resource_consumption budget of {}", limit));
+        }
+        None
+    }
+}
+
+/// Usage of one developer token for one customer on one day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsageRecord {
+    pub date: NaiveDate,
+    /// [`token_fingerprint`] of the developer token.
+    pub developer_token: String,
+    /// Customer id of the requests, empty for requests without one.
+    pub customer_id: String,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+type UsageKey = (NaiveDate, String, String);
+
+/// Shared daily usage counters, with an optional [`QuotaBudget`].
+///
+/// Clones share their counters, so one tracker can serve several channels.
+#[derive(Clone)]
+pub struct UsageTracker {
+    usage: Arc<Mutex<BTreeMap<UsageKey, Usage>>>,
+    budget: Arc<QuotaBudget>,
+    clock: Arc<Clock>,
+}
+
+impl fmt::Debug for UsageTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UsageTracker")
+            .field("budget", &self.budget)
+            .field("records", &self.usage.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self::with_budget(QuotaBudget::new())
+    }
+}
+
+impl UsageTracker {
+    /// Creates a tracker counting usage without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_budget(budget: QuotaBudget) -> Self {
+        Self {
+            usage: Arc::new(Mutex::new(BTreeMap::new())),
+            budget: Arc::new(budget),
+            clock: Arc::new(Utc::now),
+        }
+    }
+
+    /// Replaces the clock deciding the current day (default: `Utc::now`).
+    pub fn clock<F>(&mut self, clock: F) -> &mut Self
+    where
+        F: Fn() -> DateTime<Utc> + Send + Sync + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn budget(&self) -> &QuotaBudget {
+        &self.budget
+    }
+
+    /// Returns today's usage of a customer, across developer tokens.
+    pub fn customer_usage(&self, customer_id: &str) -> Usage {
+        self.today_where(|_, customer| customer == customer_id)
+    }
+
+    /// Returns today's usage of a developer token, across customers.
+    pub fn token_usage(&self, developer_token: &str) -> Usage {
+        let fingerprint = token_fingerprint(developer_token);
+        self.today_where(|token, _| token == fingerprint)
+    }
+
+    /// Returns all the usage records, by day, developer token and customer id.
+    pub fn snapshot(&self) -> Vec<UsageRecord> {
+        self.usage
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((date, token, customer), usage)| UsageRecord {
+                date: *date,
+                developer_token: token.clone(),
+                customer_id: customer.clone(),
+                usage: *usage,
+            })
+            .collect()
+    }
+
+    /// Forgets the usage of the days before `date`.
+    pub fn prune_before(&self, date: NaiveDate) {
+        self.usage
+            .lock()
+            .unwrap()
+            .retain(|(day, _, _), _| *day >= date);
+    }
+
+    fn today(&self) -> NaiveDate {
+        (self.clock)().date_naive()
+    }
+
+    fn today_where(&self, matches: impl Fn(&str, &str) -> bool) -> Usage {
+        let today = self.today();
+        let mut total = Usage::default();
+        for ((day, token, customer), usage) in self.usage.lock().unwrap().iter() {
+            if *day == today && matches(token, customer) {
+                total.add(usage);
+            }
+        }
+        total
+    }
+
+    // Counts a call if it fits the budgets, or names the budget it would exceed
+    fn try_reserve(
+        &self,
+        token: &str,
+        customer: &str,
+        operations: u64,
+    ) -> Result<UsageKey, String> {
+        let today = self.today();
+        let mut usage = self.usage.lock().unwrap();
+        let mut token_total = Usage::default();
+        for ((day, t, _), u) in usage.iter() {
+            if *day == today && t == token {
+                token_total.add(u);
+            }
+        }
+        if let Some(budget) = token_total.exceeds(&self.budget.per_developer_token, operations) {
+            return Err(format!(
+                "Daily {} exhausted for the developer token",
+                budget
+            ));
+        }
+        let key = (today, token.to_string(), customer.to_string());
+        // Rejected calls leave no record behind
+        if !customer.is_empty() {
+            let customer_usage = usage.get(&key).copied().unwrap_or_default();
+            if let Some(budget) = customer_usage.exceeds(&self.budget.per_customer, operations) {
+                return Err(format!(
+                    "Daily {} exhausted for customer {}",
+                    budget, customer
+                ));
+            }
+        }
+        let entry = usage.entry(key.clone()).or_default();
+        entry.requests += 1;
+        entry.operations += operations;
+        Ok(key)
+    }
+
+    fn add_consumption(&self, key: &UsageKey, consumption: u64) {
+        if let Some(usage) = self.usage.lock().unwrap().get_mut(key) {
+            usage.query_resource_consumption += consumption;
+        }
+    }
+
+    // Time left until the next UTC midnight
+    fn until_reset(&self) -> Duration {
+        let now = (self.clock)();
+        let tomorrow = now
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .and_then(|day| day.and_hms_opt(0, 0, 0))
+            .map(|midnight| midnight.and_utc());
+        tomorrow
+            .and_then(|midnight| (midnight - now).to_std().ok())
+            .unwrap_or(Duration::from_secs(1))
+    }
+}
+
+// Operations counted for a request: one per mutate operation, or one for the request
+fn request_operations(message: &DynamicMessage) -> u64 {
+    ["operations", "mutate_operations"]
+        .iter()
+        .find_map(|name| match message.get_field_by_name(name)?.as_ref() {
+            Value::List(items) => Some(items.len() as u64),
+            _ => None,
+        })
+        .unwrap_or(1)
+        .max(1)
+}
+
+// ---------------------------------------------------------------------------
+// Layer
+// ---------------------------------------------------------------------------
+
+/// Tower layer counting calls into a [`UsageTracker`] and enforcing its budget.
+#[derive(Debug, Clone, Default)]
+pub struct QuotaLayer {
+    tracker: UsageTracker,
+}
+
+impl QuotaLayer {
+    pub fn new(tracker: UsageTracker) -> Self {
+        Self { tracker }
+    }
+
+    pub fn tracker(&self) -> &UsageTracker {
+        &self.tracker
+    }
+}
+
+impl<S> tower::Layer<S> for QuotaLayer {
+    type Service = Quota<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Quota {
+            inner,
+            tracker: self.tracker.clone(),
+        }
+    }
+}
+
+/// gRPC channel counting calls and enforcing budgets; see the [module documentation](self).
+///
+/// The request body is buffered to read the customer id and count mutate operations.
+#[derive(Debug, Clone)]
+pub struct Quota<S> {
+    inner: S,
+    tracker: UsageTracker,
+}
+
+impl<S, ResBody> tower::Service<http::Request<tonic::body::Body>> for Quota<S>
+where
+    S: tower::Service<http::Request<tonic::body::Body>, Response = http::Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: fmt::Display,
+{
+    type Response = http::Response<ObservedBody<ResBody>>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let mut inner = take_ready(&mut self.inner);
+        let tracker = self.tracker.clone();
+
+        Box::pin(async move {
+            let request = BufferedRequest::new(request).await?;
+            let token = request
+                .headers()
+                .get("developer-token")
+                .and_then(|v| v.to_str().ok())
+                .map(token_fingerprint)
+                .unwrap_or_default();
+            let customer_id = request.customer_id().unwrap_or_default();
+            let operations = request.message().map_or(1, request_operations);
+            let fields = request.method().map(|m| ResponseFields::new(&m.output()));
+
+            let key = loop {
+                match tracker.try_reserve(&token, &customer_id, operations) {
+                    Ok(key) => break key,
+                    Err(exhausted) => match tracker.budget.over_budget {
+                        OverBudget::Reject => {
+                            return Err(Status::resource_exhausted(exhausted).into())
+                        }
+                        OverBudget::Delay => {
+                            log::info!("{}; waiting for the daily reset", exhausted);
+                            tokio::time::sleep(tracker.until_reset()).await;
+                        }
+                    },
+                }
+            };
+
+            let response = inner
+                .call(request.into_request())
+                .await
+                .map_err(Into::into)?;
+            Ok(match fields {
+                Some(fields) => ObservedBody::observe(
+                    response,
+                    CallUsage {
+                        tracker,
+                        key,
+                        fields,
+                        buffer: BytesMut::new(),
+                    },
+                ),
+                None => ObservedBody::unobserved(response),
+            })
+        })
+    }
+}
+
+// Adds the `query_resource_consumption` of each response message to the usage of a call
+struct CallUsage {
+    tracker: UsageTracker,
+    key: UsageKey,
+    fields: ResponseFields,
+    buffer: BytesMut,
+}
+
+impl CallObserver for CallUsage {
+    fn data(&mut self, data: &Bytes) {
+        self.buffer.extend_from_slice(data);
+        let consumption: i64 = split_messages(&mut self.buffer)
+            .into_iter()
+            .filter(|(compressed, _)| !compressed)
+            .filter_map(|(_, message)| self.fields.scan(message))
+            .map(|(_, consumption)| consumption)
+            .sum();
+        if consumption > 0 {
+            self.tracker.add_consumption(&self.key, consumption as u64);
+        }
+    }
+
+    fn finish(self: Box<Self>, _status: Status) {}
+}
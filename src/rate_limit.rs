@@ -0,0 +1,423 @@
+//! Client-side rate limiting per developer token and per customer.
+//!
+//! [`RateLimitLayer`] is a tower layer for the channel under the generated clients. It
+//! makes each call take a token from token buckets before it is sent, waiting for the
+//! buckets to refill when they are empty:
+//!
+//! - one bucket per developer token, shared by all its customers;
+//! - one bucket per customer id, for calls that carry one (`customer_id` or a
+//!   `customers/{id}/...` `resource_name`).
+//!
+//! Reads and mutations have separate limits and buckets, told apart with
+//! [`is_mutation`](crate::retry::is_mutation).
+//!
+//! The limiter adapts to the quota the API actually grants: when a call fails with an
+//! error of [`ErrorCategory::Quota`] (`RESOURCE_EXHAUSTED`, rate and resource consumption
+//! quota errors), the rate of its buckets is multiplied by the decrease factor (never below
+//! a tenth of the configured rate), then recovers by a tenth of the configured rate per
+//! recovery interval without quota errors.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut limiter = RateLimitLayer::new();
+//! limiter
+//!     .per_developer_token(CallKind::Read, RateLimit::per_second(50.0))
+//!     .per_customer(CallKind::Read, RateLimit::per_second(5.0))
+//!     .per_customer(CallKind::Mutate, RateLimit::per_minute(60.0).with_burst(5));
+//!
+//! let channel = ServiceBuilder::new()
+//!     .layer(limiter)
+//!     .service(client.channel().clone());
+//! let service = GoogleAdsServiceClient::with_interceptor(channel, client.interceptor().clone());
+//! ```
+
+use crate::error::{ErrorCategory, GoogleAdsError};
+use crate::middleware::{take_ready, BufferedRequest, CallObserver, ObservedBody};
+use crate::quota::token_fingerprint;
+use crate::retry::is_mutation;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::Status;
+
+// Lowest adapted rate, as a fraction of the configured rate
+const MIN_RATE_FRACTION: f64 = 0.1;
+
+// Rate recovered per recovery interval, as a fraction of the configured rate
+const RECOVERY_STEP: f64 = 0.1;
+
+/// Whether a call reads or mutates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    Read,
+    Mutate,
+}
+
+impl CallKind {
+    /// Classifies a gRPC method path, e.g.
+    /// `/google.ads.googleads.v23.services.GoogleAdsService/SearchStream`.
+    pub fn of_method(path: &str) -> Self {
+        if is_mutation(path) {
+            CallKind::Mutate
+        } else {
+            CallKind::Read
+        }
+    }
+}
+
+/// Sustained rate and burst size of a token bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Allows `rate` calls per second, in bursts of up to one second's worth of calls.
+    pub fn per_second(rate: f64) -> Self {
+        Self {
+            per_second: rate,
+            burst: rate.ceil().max(1.0) as u32,
+        }
+    }
+
+    /// Allows `rate` calls per minute, in bursts of one call.
+    pub fn per_minute(rate: f64) -> Self {
+        Self {
+            per_second: rate / 60.0,
+            burst: 1,
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Buckets
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Scope {
+    DeveloperToken(String),
+    Customer(String),
+}
+
+type BucketKey = (Scope, CallKind);
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+    adapted: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            rate: limit.per_second,
+            tokens: limit.burst as f64,
+            refilled: now,
+            adapted: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant, recovery_interval: Duration) {
+        if self.rate < self.limit.per_second && !recovery_interval.is_zero() {
+            let steps = (now - self.adapted).as_secs_f64() / recovery_interval.as_secs_f64();
+            let steps = steps.floor();
+            if steps >= 1.0 {
+                self.rate = (self.rate + steps * RECOVERY_STEP * self.limit.per_second)
+                    .min(self.limit.per_second);
+                self.adapted += recovery_interval.mul_f64(steps);
+            }
+        }
+        let elapsed = (now - self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.limit.burst as f64);
+        self.refilled = now;
+    }
+
+    // Time until a token is available
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+
+    fn throttle(&mut self, now: Instant, decrease: f64) {
+        self.rate = (self.rate * decrease).max(self.limit.per_second * MIN_RATE_FRACTION);
+        self.tokens = self.tokens.min(0.0);
+        self.adapted = now;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Limits {
+    per_developer_token: HashMap<CallKind, RateLimit>,
+    per_customer: HashMap<CallKind, RateLimit>,
+    decrease: f64,
+    recovery_interval: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            per_developer_token: HashMap::new(),
+            per_customer: HashMap::new(),
+            decrease: 0.5,
+            recovery_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Limits {
+    fn limit(&self, scope: &Scope, kind: CallKind) -> Option<RateLimit> {
+        match scope {
+            Scope::DeveloperToken(_) => self.per_developer_token.get(&kind).copied(),
+            Scope::Customer(_) => self.per_customer.get(&kind).copied(),
+        }
+    }
+}
+
+// The buckets of a call, shared by the clones of a layer
+#[derive(Debug, Default)]
+struct Limiter {
+    limits: Limits,
+    buckets: Mutex<HashMap<BucketKey, Bucket>>,
+}
+
+impl Limiter {
+    fn keys(&self, token: &str, customer_id: Option<&str>, kind: CallKind) -> Vec<BucketKey> {
+        let mut scopes = vec![Scope::DeveloperToken(token.to_string())];
+        if let Some(customer_id) = customer_id.filter(|id| !id.is_empty()) {
+            scopes.push(Scope::Customer(customer_id.to_string()));
+        }
+        scopes
+            .into_iter()
+            .filter(|scope| self.limits.limit(scope, kind).is_some())
+            .map(|scope| (scope, kind))
+            .collect()
+    }
+
+    // Takes a token from every bucket, waiting until all of them have one
+    async fn acquire(&self, keys: &[BucketKey]) {
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().unwrap();
+                let mut wait = Duration::ZERO;
+                for key in keys {
+                    let limit = self.limits.limit(&key.0, key.1).expect("limited scope");
+                    let bucket = buckets
+                        .entry(key.clone())
+                        .or_insert_with(|| Bucket::new(limit, now));
+                    bucket.refill(now, self.limits.recovery_interval);
+                    wait = wait.max(bucket.wait());
+                }
+                if wait.is_zero() {
+                    for key in keys {
+                        if let Some(bucket) = buckets.get_mut(key) {
+                            bucket.tokens -= 1.0;
+                        }
+                    }
+                    return;
+                }
+                wait
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn throttle(&self, keys: &[BucketKey]) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        for key in keys {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.refill(now, self.limits.recovery_interval);
+                bucket.throttle(now, self.limits.decrease);
+                log::info!(
+                    "Quota error: {:?} rate of {:?} lowered to {:.3}/s",
+                    key.1,
+                    key.0,
+                    bucket.rate
+                );
+            }
+        }
+    }
+
+    fn rate(&self, key: &BucketKey) -> Option<f64> {
+        let limit = self.limits.limit(&key.0, key.1)?;
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(key) {
+            Some(bucket) => {
+                bucket.refill(Instant::now(), self.limits.recovery_interval);
+                Some(bucket.rate)
+            }
+            None => Some(limit.per_second),
+        }
+    }
+}
+
+// True for statuses telling that the caller exceeded its quota; other quota errors, such
+// as `quota_error.ACCESS_PROHIBITED`, do not go away by slowing down
+fn is_quota_error(status: &Status) -> bool {
+    GoogleAdsError::from_status(status).category() == ErrorCategory::Quota
+}
+
+// ---------------------------------------------------------------------------
+// Layer
+// ---------------------------------------------------------------------------
+
+/// Tower layer rate limiting the calls made through the channel it wraps.
+///
+/// Clones share their buckets; changing the limits of a clone gives it its own buckets.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitLayer {
+    limiter: Arc<Limiter>,
+}
+
+impl RateLimitLayer {
+    /// Creates a layer without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the `kind` calls of each developer token, across its customers.
+    pub fn per_developer_token(&mut self, kind: CallKind, limit: RateLimit) -> &mut Self {
+        self.limits().per_developer_token.insert(kind, limit);
+        self
+    }
+
+    /// Limits the `kind` calls of each customer id.
+    pub fn per_customer(&mut self, kind: CallKind, limit: RateLimit) -> &mut Self {
+        self.limits().per_customer.insert(kind, limit);
+        self
+    }
+
+    /// Sets the factor applied to the rate on a quota error (default 0.5) and the interval
+    /// after which a tenth of the configured rate is recovered (default 10 seconds).
+    pub fn adaptive(&mut self, decrease: f64, recovery_interval: Duration) -> &mut Self {
+        let limits = self.limits();
+        limits.decrease = decrease.clamp(MIN_RATE_FRACTION, 1.0);
+        limits.recovery_interval = recovery_interval;
+        self
+    }
+
+    /// Returns the current rate, in calls per second, of the `kind` calls of a developer
+    /// token, or of a customer if `customer_id` is given; `None` if they are not limited.
+    pub fn current_rate(
+        &self,
+        developer_token: &str,
+        customer_id: Option<&str>,
+        kind: CallKind,
+    ) -> Option<f64> {
+        let scope = match customer_id {
+            Some(customer_id) => Scope::Customer(customer_id.to_string()),
+            None => Scope::DeveloperToken(token_fingerprint(developer_token)),
+        };
+        self.limiter.rate(&(scope, kind))
+    }
+
+    fn limits(&mut self) -> &mut Limits {
+        if Arc::get_mut(&mut self.limiter).is_none() {
+            self.limiter = Arc::new(Limiter {
+                limits: self.limiter.limits.clone(),
+                buckets: Mutex::new(HashMap::new()),
+            });
+        }
+        &mut Arc::get_mut(&mut self.limiter)
+            .expect("limiter is not shared")
+            .limits
+    }
+}
+
+impl<S> tower::Layer<S> for RateLimitLayer {
+    type Service = RateLimited<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimited {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+/// gRPC channel rate limiting calls; see the [module documentation](self).
+///
+/// The request body is buffered to read the customer id.
+#[derive(Debug, Clone)]
+pub struct RateLimited<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<S, ResBody> tower::Service<http::Request<tonic::body::Body>> for RateLimited<S>
+where
+    S: tower::Service<http::Request<tonic::body::Body>, Response = http::Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: fmt::Display,
+{
+    type Response = http::Response<ObservedBody<ResBody>>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let mut inner = take_ready(&mut self.inner);
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            let request = BufferedRequest::new(request).await?;
+            let token = request
+                .headers()
+                .get("developer-token")
+                .and_then(|v| v.to_str().ok())
+                .map(token_fingerprint)
+                .unwrap_or_default();
+            let kind = CallKind::of_method(request.path());
+            let keys = limiter.keys(&token, request.customer_id().as_deref(), kind);
+            limiter.acquire(&keys).await;
+
+            let response = inner
+                .call(request.into_request())
+                .await
+                .map_err(Into::into)?;
+            Ok(ObservedBody::observe(response, Throttle { limiter, keys }))
+        })
+    }
+}
+
+// Lowers the rate of the buckets of a call that ends with a quota error
+struct Throttle {
+    limiter: Arc<Limiter>,
+    keys: Vec<BucketKey>,
+}
+
+impl CallObserver for Throttle {
+    fn finish(self: Box<Self>, status: Status) {
+        if is_quota_error(&status) {
+            self.limiter.throttle(&self.keys);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Retries of transient Google Ads API failures.
+//!
+//! [`RetryLayer`] is a tower layer for the channel under the generated clients. It retries
+//! calls failing with `UNAVAILABLE`, `DEADLINE_EXCEEDED` or `INTERNAL`, and calls whose
+//! `GoogleAdsFailure` only holds transient errors (e.g. `internal_error.TRANSIENT_ERROR`,
+//! `database_error.CONCURRENT_MODIFICATION` or `quota_error.RESOURCE_TEMPORARILY_EXHAUSTED`),
+//! with jittered exponential backoff. A `retry_delay` sent by the server in the quota error
+//! details is honoured.
+//!
+//! Mutations are not idempotent, since a mutate that timed out may still have been applied,
+//! so they are only retried if [`RetryPolicy::retry_mutations`] is set.
+//!
+//! The layer only sees the response headers, so a `search_stream` that breaks after
+//! returning rows is not retried by it. [`RetryPolicy::search_stream`] re-opens such a
+//! stream from scratch and skips the rows that were already yielded.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut policy = RetryPolicy::default();
+//! policy.max_retries(3).initial_backoff(Duration::from_millis(500));
+//!
+//! let channel = ServiceBuilder::new()
+//!     .layer(RetryLayer::new(policy.clone()))
+//!     .service(client.channel().clone());
+//! let service = GoogleAdsServiceClient::with_interceptor(channel, client.interceptor().clone());
+//!
+//! let mut batches = policy.search_stream(service, request);
+//! while let Some(batch) = batches.next().await {
+//!     for row in batch?.results { /* ... */ }
+//! }
+//! ```
+
+use crate::error::GoogleAdsError;
+use crate::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use futures::future::BoxFuture;
+use futures::stream::{self, Stream};
+use http_body_util::{BodyExt, Full};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::{Code, Status, Streaming};
+
+/// gRPC path of `GoogleAdsService.SearchStream`.
+pub const SEARCH_STREAM_PATH: &str =
+    "/google.ads.googleads.v23.services.GoogleAdsService/SearchStream";
+
+// Methods with these prefixes only read data and are safe to retry
+const READ_ONLY_PREFIXES: &[&str] = &[
+    "Search", "Get", "List", "Generate", "Suggest", "Fetch", "Quote",
+];
+
+/// When and how long to wait before retrying a failed call.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    retry_mutations: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 2.0,
+            retry_mutations: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the number of retries after the first attempt (default 5).
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the backoff before the first retry (default 1s).
+    pub fn initial_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum backoff between retries (default 60s).
+    pub fn max_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the factor the backoff grows by after each retry (default 2).
+    pub fn multiplier(&mut self, multiplier: f64) -> &mut Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Allows retrying mutations (default false). Only enable this if the operations are
+    /// safe to apply twice.
+    pub fn retry_mutations(&mut self, retry: bool) -> &mut Self {
+        self.retry_mutations = retry;
+        self
+    }
+
+    /// Returns true if calls to the gRPC method `path` may be retried.
+    pub fn retries_method(&self, path: &str) -> bool {
+        self.max_retries > 0 && (self.retry_mutations || !is_mutation(path))
+    }
+
+    /// Returns true if a call to `path` that failed with `error` should be retried.
+    pub fn should_retry(&self, path: &str, error: &GoogleAdsError) -> bool {
+        self.retries_method(path) && is_transient(error)
+    }
+
+    /// Returns the jittered backoff before retry number `attempt` (starting at 0).
+    ///
+    /// The delay is drawn uniformly between half and all of the exponential backoff, so
+    /// clients failing together do not retry together.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.initial_backoff.as_secs_f64() * self.multiplier.powi(attempt as i32);
+        let capped = exponential.min(self.max_backoff.as_secs_f64());
+        Duration::from_secs_f64(capped * (0.5 + 0.5 * jitter()))
+    }
+
+    /// Returns the delay before retry number `attempt`: the backoff, or the delay
+    /// requested by the server if that is longer.
+    pub fn retry_delay(&self, attempt: u32, error: &GoogleAdsError) -> Duration {
+        let backoff = self.backoff(attempt);
+        server_retry_delay(error).map_or(backoff, |delay| delay.max(backoff))
+    }
+
+    /// Runs `search_stream`, re-opening the stream from scratch if it fails with a
+    /// transient error, including after some batches were returned.
+    ///
+    /// Rows already yielded are skipped on the re-opened stream, which relies on the query
+    /// returning rows in the same order; add an `ORDER BY` clause to guarantee it. Retries
+    /// are counted from the last batch that made progress.
+    pub fn search_stream<T>(
+        &self,
+        client: GoogleAdsServiceClient<T>,
+        request: SearchGoogleAdsStreamRequest,
+    ) -> impl Stream<Item = Result<SearchGoogleAdsStreamResponse, Status>> + Send
+    where
+        T: tonic::client::GrpcService<tonic::body::Body> + Clone + Send + 'static,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+        T::Future: Send,
+    {
+        let state = SearchStreamState {
+            client,
+            request,
+            policy: self.clone(),
+            stream: None,
+            yielded: 0,
+            skip: 0,
+            attempt: 0,
+            done: false,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if state.done {
+                    return None;
+                }
+                if state.stream.is_none() {
+                    match state.client.search_stream(state.request.clone()).await {
+                        Ok(response) => {
+                            state.stream = Some(response.into_inner());
+                            state.skip = state.yielded;
+                        }
+                        Err(status) => {
+                            if state.backoff(&status).await {
+                                continue;
+                            }
+                            state.done = true;
+                            return Some((Err(status), state));
+                        }
+                    }
+                }
+
+                let stream = state.stream.as_mut().expect("stream was opened");
+                match stream.message().await {
+                    Ok(Some(mut batch)) => {
+                        let skipped = state.skip.min(batch.results.len());
+                        if skipped > 0 {
+                            batch.results.drain(..skipped);
+                            state.skip -= skipped;
+                            if batch.results.is_empty() && batch.summary_row.is_none() {
+                                continue;
+                            }
+                        }
+                        if !batch.results.is_empty() {
+                            state.attempt = 0;
+                        }
+                        state.yielded += batch.results.len();
+                        return Some((Ok(batch), state));
+                    }
+                    Ok(None) => return None,
+                    Err(status) => {
+                        state.stream = None;
+                        if state.backoff(&status).await {
+                            continue;
+                        }
+                        state.done = true;
+                        return Some((Err(status), state));
+                    }
+                }
+            }
+        })
+    }
+}
+
+struct SearchStreamState<T> {
+    client: GoogleAdsServiceClient<T>,
+    request: SearchGoogleAdsStreamRequest,
+    policy: RetryPolicy,
+    stream: Option<Streaming<SearchGoogleAdsStreamResponse>>,
+    yielded: usize,
+    skip: usize,
+    attempt: u32,
+    done: bool,
+}
+
+impl<T> SearchStreamState<T> {
+    // Sleeps and returns true if the failed stream should be re-opened
+    async fn backoff(&mut self, status: &Status) -> bool {
+        let error = GoogleAdsError::from_status(status);
+        if self.attempt >= self.policy.max_retries
+            || !self.policy.should_retry(SEARCH_STREAM_PATH, &error)
+        {
+            return false;
+        }
+        tokio::time::sleep(self.policy.retry_delay(self.attempt, &error)).await;
+        self.attempt += 1;
+        true
+    }
+}
+
+/// Returns true if the gRPC method `path` (e.g. `/google.ads.googleads.v23.services.CampaignService/MutateCampaigns`)
+/// changes data.
+pub fn is_mutation(path: &str) -> bool {
+    let method = path.rsplit('/').next().unwrap_or(path);
+    !READ_ONLY_PREFIXES
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+}
+
+/// Returns true if the error is transient: a bare `UNAVAILABLE`, `DEADLINE_EXCEEDED` or
+/// `INTERNAL` status, or a failure whose errors are all retryable or
+/// `quota_error.RESOURCE_TEMPORARILY_EXHAUSTED`; see [`GoogleAdsError::is_retryable`].
+pub fn is_transient(error: &GoogleAdsError) -> bool {
+    error.is_retryable()
+}
+
+/// Returns the longest `retry_delay` of the quota error details of the failure, if any.
+pub fn server_retry_delay(error: &GoogleAdsError) -> Option<Duration> {
+    error
+        .errors
+        .iter()
+        .filter_map(|detail| detail.error.details.as_ref()?.quota_error_details.as_ref())
+        .filter_map(|quota| quota.retry_delay.as_ref())
+        .map(|delay| {
+            Duration::new(
+                delay.seconds.max(0) as u64,
+                delay.nanos.clamp(0, 999_999_999) as u32,
+            )
+        })
+        .max()
+}
+
+// Uniform in [0, 1), seeded per call from the randomly keyed std hasher
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// ----------------------------------------------------------------------------
+// Tower layer
+// ----------------------------------------------------------------------------
+
+/// Tower layer wrapping a gRPC channel in [`Retry`].
+#[derive(Debug, Clone, Default)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> tower::Layer<S> for RetryLayer {
+    type Service = Retry<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Retry {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+/// gRPC channel retrying transient failures according to a [`RetryPolicy`].
+///
+/// The request body of retried methods is buffered so it can be sent again. Failures are
+/// read from the status of trailers-only responses, which is how the API reports errors
+/// before any response message.
+#[derive(Debug, Clone)]
+pub struct Retry<S> {
+    inner: S,
+    policy: RetryPolicy,
+}
+
+impl<S> Retry<S> {
+    pub fn new(inner: S, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+}
+
+impl<S, ResBody> tower::Service<http::Request<tonic::body::Body>> for Retry<S>
+where
+    S: tower::Service<http::Request<tonic::body::Body>, Response = http::Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    ResBody: Send + 'static,
+{
+    type Response = http::Response<ResBody>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        // Keep the service that was driven to ready, leave a clone for the next call
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let policy = self.policy.clone();
+
+        Box::pin(async move {
+            let path = request.uri().path().to_string();
+            if !policy.retries_method(&path) {
+                return inner.call(request).await.map_err(Into::into);
+            }
+
+            let (parts, body) = request.into_parts();
+            let body = body.collect().await?.to_bytes();
+            let mut attempt = 0;
+            loop {
+                let request = http::Request::from_parts(
+                    parts.clone(),
+                    tonic::body::Body::new(Full::new(body.clone())),
+                );
+                let result = inner.call(request).await.map_err(Into::into);
+                let error = match &result {
+                    Ok(response) => match Status::from_header_map(response.headers()) {
+                        Some(status) if status.code() != Code::Ok => {
+                            GoogleAdsError::from_status(&status)
+                        }
+                        _ => return result,
+                    },
+                    // Transport errors (e.g. a refused connection) are treated as UNAVAILABLE
+                    Err(err) => GoogleAdsError::from(Status::unavailable(err.to_string())),
+                };
+                if attempt >= policy.max_retries || !policy.should_retry(&path, &error) {
+                    return result;
+                }
+                tokio::time::sleep(policy.retry_delay(attempt, &error)).await;
+                attempt += 1;
+                std::future::poll_fn(|cx| inner.poll_ready(cx))
+                    .await
+                    .map_err(Into::into)?;
+            }
+        })
+    }
+}
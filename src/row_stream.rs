@@ -0,0 +1,183 @@
+//! Row-level view of `GoogleAdsService.SearchStream` responses.
+//!
+//! `search_stream` returns batches of rows, each `SearchGoogleAdsStreamResponse` carrying
+//! the field mask and request id alongside its `results`. [`RowStream`] flattens the
+//! batches into a stream of [`StreamRow`]s, each holding the row together with a shared
+//! `Arc` of the field mask and request id, so callers no longer have to unwrap them from
+//! every batch.
+//!
+//! Batch boundaries stay observable: every row records the index of its batch and whether
+//! it is the last row of that batch, so a writer can flush once per batch.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let response = client.google_ads().search_stream(request).await?;
+//! let mut rows = RowStream::new(response.into_inner());
+//! while let Some(row) = rows.next().await {
+//!     let row = row?;
+//!     writer.write(&row.values())?;
+//!     if row.end_of_batch {
+//!         writer.flush()?;
+//!     }
+//! }
+//! println!("request id: {:?}", rows.request_id());
+//! ```
+
+use crate::error::GoogleAdsError;
+use crate::google::ads::googleads::v23::services::{GoogleAdsRow, SearchGoogleAdsStreamResponse};
+use futures::{Stream, StreamExt};
+use prost_types::FieldMask;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tonic::{Status, Streaming};
+
+/// A row of a `search_stream` response with the context of its batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamRow {
+    pub row: GoogleAdsRow,
+    /// Field mask of the response, shared by all rows.
+    pub field_mask: Arc<FieldMask>,
+    /// Request id of the response, shared by all rows.
+    pub request_id: Arc<str>,
+    /// Zero-based index of the batch the row arrived in.
+    pub batch: usize,
+    /// True for the last row of its batch.
+    pub end_of_batch: bool,
+}
+
+impl StreamRow {
+    /// Returns the values of the field mask paths, in mask order.
+    pub fn values(&self) -> Vec<String> {
+        let paths: Vec<&str> = self.field_mask.paths.iter().map(String::as_str).collect();
+        self.row.get_many(&paths)
+    }
+}
+
+/// Stream of the rows of a `search_stream` response.
+pub struct RowStream<S = Streaming<SearchGoogleAdsStreamResponse>> {
+    inner: S,
+    rows: std::vec::IntoIter<GoogleAdsRow>,
+    field_mask: Arc<FieldMask>,
+    request_id: Arc<str>,
+    summary_row: Option<GoogleAdsRow>,
+    query_resource_consumption: i64,
+    batches: usize,
+    done: bool,
+}
+
+impl<S> RowStream<S>
+where
+    S: Stream<Item = Result<SearchGoogleAdsStreamResponse, Status>> + Unpin,
+{
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            rows: Vec::new().into_iter(),
+            field_mask: Arc::new(FieldMask::default()),
+            request_id: Arc::from(""),
+            summary_row: None,
+            query_resource_consumption: 0,
+            batches: 0,
+            done: false,
+        }
+    }
+
+    fn receive(&mut self, batch: SearchGoogleAdsStreamResponse) {
+        // Every batch repeats the mask and request id; keep sharing the first ones
+        if let Some(mask) = batch.field_mask {
+            if mask != *self.field_mask {
+                self.field_mask = Arc::new(mask);
+            }
+        }
+        if !batch.request_id.is_empty() && batch.request_id != *self.request_id {
+            self.request_id = Arc::from(batch.request_id);
+        }
+        if batch.summary_row.is_some() {
+            self.summary_row = batch.summary_row;
+        }
+        self.query_resource_consumption += batch.query_resource_consumption;
+        self.batches += 1;
+        self.rows = batch.results.into_iter();
+    }
+}
+
+impl<S> RowStream<S> {
+    /// Returns the field mask received so far; empty before the first batch.
+    pub fn field_mask(&self) -> &Arc<FieldMask> {
+        &self.field_mask
+    }
+
+    /// Returns the request id received so far, or `None` before the first batch.
+    pub fn request_id(&self) -> Option<&str> {
+        Some(&*self.request_id).filter(|id| !id.is_empty())
+    }
+
+    /// Returns the summary row, which arrives with the last batch when the request set
+    /// `summary_row_setting`.
+    pub fn summary_row(&self) -> Option<&GoogleAdsRow> {
+        self.summary_row.as_ref()
+    }
+
+    /// Returns the `query_resource_consumption` of the batches received so far.
+    pub fn query_resource_consumption(&self) -> i64 {
+        self.query_resource_consumption
+    }
+
+    /// Returns the number of batches received so far.
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    /// Returns the underlying stream of batches.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> From<S> for RowStream<S>
+where
+    S: Stream<Item = Result<SearchGoogleAdsStreamResponse, Status>> + Unpin,
+{
+    fn from(inner: S) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl<S> Stream for RowStream<S>
+where
+    S: Stream<Item = Result<SearchGoogleAdsStreamResponse, Status>> + Unpin,
+{
+    type Item = Result<StreamRow, GoogleAdsError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(row) = this.rows.next() {
+                return Poll::Ready(Some(Ok(StreamRow {
+                    row,
+                    field_mask: this.field_mask.clone(),
+                    request_id: this.request_id.clone(),
+                    batch: this.batches - 1,
+                    end_of_batch: this.rows.len() == 0,
+                })));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match this.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(batch))) => this.receive(batch),
+                Poll::Ready(Some(Err(status))) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(GoogleAdsError::from_status(&status))));
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
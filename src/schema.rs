@@ -0,0 +1,89 @@
+//! Result schema inference for GAQL queries.
+//!
+//! Resolves each selected field path through the `GoogleAdsRow` descriptor so that the
+//! output column types are known before any row arrives, even for empty results.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let columns = infer_schema("SELECT campaign.id, campaign.status, metrics.cost_micros FROM campaign")?;
+//! for column in &columns {
+//!     println!("{}: {:?} repeated={} micros={}", column.name, column.kind, column.repeated, column.is_micros);
+//! }
+//! ```
+
+use crate::gaql::{is_date_field, resolve_field, Query};
+use prost_reflect::{EnumDescriptor, FieldDescriptor, Kind};
+
+/// Metrics reported in micros whose names lack the `_micros` suffix.
+const MICROS_METRICS: &[&str] = &[
+    "active_view_cpm",
+    "average_cost",
+    "average_cpc",
+    "average_cpe",
+    "average_cpm",
+    "average_cpv",
+    "cost_per_all_conversions",
+    "cost_per_conversion",
+    "cost_per_current_model_attributed_conversion",
+];
+
+/// Type information for one selected column of a GAQL query.
+#[derive(Debug, Clone)]
+pub struct ColumnSchema {
+    /// The field path as written in the `SELECT` clause, e.g. `"campaign.id"`.
+    pub name: String,
+    /// Protobuf kind of the field.
+    pub kind: Kind,
+    /// Enum descriptor when the field is an enum, for resolving value names.
+    pub enum_descriptor: Option<EnumDescriptor>,
+    /// True for repeated fields, which [`GoogleAdsRow::get`] renders as lists.
+    ///
+    /// [`GoogleAdsRow::get`]: crate::google::ads::googleads::v23::services::GoogleAdsRow::get
+    pub repeated: bool,
+    /// True for monetary values expressed in micros (millionths of the currency unit).
+    pub is_micros: bool,
+    /// True for dates (or date-times) encoded as strings, e.g. `segments.date`.
+    pub is_date: bool,
+    /// Descriptor of the resolved field.
+    pub field: FieldDescriptor,
+}
+
+impl ColumnSchema {
+    fn from_field(name: &str, field: FieldDescriptor) -> Self {
+        let kind = field.kind();
+        let enum_descriptor = match &kind {
+            Kind::Enum(desc) => Some(desc.clone()),
+            _ => None,
+        };
+        let is_micros = field.name().ends_with("_micros")
+            || (name.starts_with("metrics.") && MICROS_METRICS.contains(&field.name()));
+
+        Self {
+            name: name.to_string(),
+            enum_descriptor,
+            repeated: field.is_list(),
+            is_micros,
+            is_date: is_date_field(&field),
+            kind,
+            field,
+        }
+    }
+}
+
+impl Query {
+    /// Returns the schema of the columns selected by this query, in `SELECT` order.
+    pub fn schema(&self) -> anyhow::Result<Vec<ColumnSchema>> {
+        self.fields
+            .iter()
+            .map(|path| Ok(ColumnSchema::from_field(path, resolve_field(path)?)))
+            .collect()
+    }
+}
+
+/// Parses a GAQL query and returns the schema of its selected columns.
+///
+/// Fails if the query does not parse or a selected path does not exist on `GoogleAdsRow`.
+pub fn infer_schema(gaql: &str) -> anyhow::Result<Vec<ColumnSchema>> {
+    Query::parse(gaql)?.schema()
+}
@@ -0,0 +1,232 @@
+//! `tracing` spans for Google Ads API calls (feature `tracing`).
+//!
+//! [`TracingLayer`] is a tower layer for the channel under the generated clients. It opens
+//! an `info` span for every call and records what is needed to see which account and query
+//! consumed time:
+//!
+//! | Field | Value |
+//! |-------|-------|
+//! | `otel.name` | method, e.g. `GoogleAdsService/SearchStream` |
+//! | `rpc.system`, `rpc.service`, `rpc.method` | `grpc` and the full service and method names |
+//! | `customer_id` | `customer_id` of the request, or the id in its `resource_name` |
+//! | `query_hash` | [`query_hash`] of the GAQL `query` of the request |
+//! | `request_id` | `request-id` response header |
+//! | `query_resource_consumption` | summed over the response messages |
+//! | `rows` | number of `results` in the response messages |
+//! | `rpc.grpc.status_code` | numeric gRPC status |
+//! | `error_codes` | Google Ads error codes of a failure, e.g. `query_error.BAD_FIELD_NAME` |
+//!
+//! `tracing` span names are static, so spans are named `rpc`; `tracing-opentelemetry`
+//! exports them under their `otel.name`, so they appear in Jaeger as
+//! `GoogleAdsService/SearchStream`. The span closes when the response ends, which for
+//! `search_stream` is after the last batch has been read.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let channel = ServiceBuilder::new()
+//!     .layer(TracingLayer::new())
+//!     .service(client.channel().clone());
+//! let service = GoogleAdsServiceClient::with_interceptor(channel, client.interceptor().clone());
+//! ```
+
+use crate::error::{GoogleAdsError, REQUEST_ID_METADATA_KEY};
+use crate::gaql::normalize_gaql;
+use crate::logging::method_name;
+use crate::middleware::{
+    short_digest, split_messages, take_ready, BufferedRequest, CallObserver, ObservedBody,
+    ResponseFields,
+};
+use bytes::BytesMut;
+use futures::future::BoxFuture;
+use prost_reflect::{MessageDescriptor, Value};
+use std::task::{Context, Poll};
+use tonic::codegen::{Body, Bytes, StdError};
+use tonic::{Code, Status};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+/// Returns a short, stable hash of a GAQL query: the first 16 hex digits of the SHA-256
+/// of its normalized form, so that formatting differences do not change it. Queries that
+/// do not parse are hashed as they are.
+pub fn query_hash(query: &str) -> String {
+    let normalized = normalize_gaql(query).unwrap_or_else(|_| query.trim().to_string());
+    short_digest(normalized.as_bytes())
+}
+
+/// Tower layer opening a span for every call made through the channel it wraps.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingLayer;
+
+impl TracingLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> tower::Layer<S> for TracingLayer {
+    type Service = Traced<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Traced { inner }
+    }
+}
+
+/// gRPC channel recording each call in a span; see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Traced<S> {
+    inner: S,
+}
+
+impl<S> Traced<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, ResBody> tower::Service<http::Request<tonic::body::Body>> for Traced<S>
+where
+    S: tower::Service<http::Request<tonic::body::Body>, Response = http::Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: std::fmt::Display,
+{
+    type Response = http::Response<ObservedBody<ResBody>>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        let mut inner = take_ready(&mut self.inner);
+
+        let path = request.uri().path().to_string();
+        let (service, method) = path
+            .trim_start_matches('/')
+            .split_once('/')
+            .unwrap_or(("", ""));
+        let span = tracing::info_span!(
+            "rpc",
+            otel.name = %method_name(&path),
+            otel.kind = "client",
+            rpc.system = "grpc",
+            rpc.service = service,
+            rpc.method = method,
+            customer_id = Empty,
+            query_hash = Empty,
+            request_id = Empty,
+            query_resource_consumption = Empty,
+            rows = Empty,
+            rpc.grpc.status_code = Empty,
+            error_codes = Empty,
+        );
+
+        Box::pin(async move {
+            if span.is_disabled() {
+                let response = inner.call(request).await.map_err(Into::into)?;
+                return Ok(ObservedBody::unobserved(response));
+            }
+
+            let request = BufferedRequest::new(request).await?;
+            if let Some(customer_id) = request.customer_id() {
+                span.record("customer_id", customer_id.as_str());
+            }
+            if let Some(Value::String(query)) = request
+                .message()
+                .and_then(|message| message.get_field_by_name("query"))
+                .as_deref()
+            {
+                span.record("query_hash", query_hash(query).as_str());
+            }
+            let call = CallSpan::new(span.clone(), request.method().map(|m| m.output()));
+
+            match inner.call(request.into_request()).instrument(span).await {
+                Ok(response) => Ok(ObservedBody::observe(response, call)),
+                Err(err) => {
+                    let err = err.into();
+                    call.record(Status::unavailable(format!("transport error: {}", err)));
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+// Counters of a call until its span is completed
+struct CallSpan {
+    span: Span,
+    fields: Option<ResponseFields>,
+    buffer: BytesMut,
+    rows: u64,
+    consumption: i64,
+}
+
+impl CallSpan {
+    fn new(span: Span, output: Option<MessageDescriptor>) -> Self {
+        Self {
+            span,
+            fields: output.as_ref().map(ResponseFields::new),
+            buffer: BytesMut::new(),
+            rows: 0,
+            consumption: 0,
+        }
+    }
+
+    fn record(self, status: Status) {
+        let span = &self.span;
+        if self.fields.is_some_and(|f| f.results.is_some()) {
+            span.record("rows", self.rows);
+        }
+        if self.fields.is_some_and(|f| f.consumption.is_some()) {
+            span.record("query_resource_consumption", self.consumption);
+        }
+        span.record("rpc.grpc.status_code", status.code() as i32);
+        if status.code() != Code::Ok {
+            let error = GoogleAdsError::from_status(&status);
+            let codes: Vec<String> = error.errors.iter().map(|e| e.error_code()).collect();
+            if !codes.is_empty() {
+                span.record("error_codes", codes.join(",").as_str());
+            }
+            if let Some(request_id) = &error.request_id {
+                span.record("request_id", request_id.as_str());
+            }
+        }
+    }
+}
+
+impl CallObserver for CallSpan {
+    fn headers(&mut self, headers: &http::HeaderMap) {
+        if let Some(request_id) = headers
+            .get(REQUEST_ID_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+        {
+            self.span.record("request_id", request_id);
+        }
+    }
+
+    fn data(&mut self, data: &Bytes) {
+        let Some(fields) = self.fields else {
+            return;
+        };
+        self.buffer.extend_from_slice(data);
+        for (compressed, message) in split_messages(&mut self.buffer) {
+            if compressed {
+                continue;
+            }
+            if let Some((rows, consumption)) = fields.scan(message) {
+                self.rows += rows;
+                self.consumption += consumption;
+            }
+        }
+    }
+
+    fn finish(self: Box<Self>, status: Status) {
+        self.record(status)
+    }
+}
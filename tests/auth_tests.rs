@@ -0,0 +1,242 @@
+// Tests for OAuth2 refresh-token authentication
+//
+// These tests run the Authenticator against a local token endpoint and verify
+// token caching, renewal before expiry and after UNAUTHENTICATED, a single
+// backed-off background renewal while the endpoint fails, error reporting, and
+// the authorization header added by GoogleAdsInterceptor.
+
+mod token_server;
+
+use googleads_rs::{Authenticator, GoogleAdsInterceptor, RefreshTokenCredentials};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use token_server::{token_response, TokenServer};
+use tonic::service::Interceptor;
+use tonic::{Code, Request, Status};
+
+/// Starts a token server answering with `token-1`, `token-2`, ... valid for `expires_in`.
+async fn counting_server(expires_in: u64) -> TokenServer {
+    let counter = AtomicUsize::new(0);
+    TokenServer::start(move |_| {
+        let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        token_response(&format!("token-{}", n), expires_in)
+    })
+    .await
+}
+
+fn authenticator(server: &TokenServer) -> Authenticator {
+    let mut credentials = RefreshTokenCredentials::new("client-id", "client-secret", "refresh-1/x");
+    credentials.token_endpoint(&server.url);
+    Authenticator::new(credentials)
+}
+
+// ============================================================================
+// Token Exchange
+// ============================================================================
+
+#[tokio::test]
+async fn test_exchanges_refresh_token() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+
+    assert_eq!(auth.access_token().await.unwrap(), "token-1");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["grant_type"], "refresh_token");
+    assert_eq!(requests[0]["client_id"], "client-id");
+    assert_eq!(requests[0]["client_secret"], "client-secret");
+    assert_eq!(requests[0]["refresh_token"], "refresh-1/x");
+}
+
+#[tokio::test]
+async fn test_token_endpoint_error() {
+    let server = TokenServer::start(|_| {
+        (
+            400,
+            r#"{"error":"invalid_grant","error_description":"Token has been expired or revoked."}"#
+                .to_string(),
+        )
+    })
+    .await;
+    let auth = authenticator(&server);
+
+    let err = auth.access_token().await.unwrap_err().to_string();
+    assert!(err.contains("invalid_grant"), "{}", err);
+    assert!(err.contains("expired or revoked"), "{}", err);
+    assert_eq!(auth.cached_token(), None);
+}
+
+#[tokio::test]
+async fn test_invalid_token_response() {
+    let server = TokenServer::start(|_| (200, "{}".to_string())).await;
+    let auth = authenticator(&server);
+    assert!(auth.access_token().await.is_err());
+}
+
+// ============================================================================
+// Caching and Renewal
+// ============================================================================
+
+#[tokio::test]
+async fn test_token_is_cached() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+
+    assert_eq!(auth.access_token().await.unwrap(), "token-1");
+    assert_eq!(auth.access_token().await.unwrap(), "token-1");
+    assert_eq!(auth.clone().access_token().await.unwrap(), "token-1");
+    assert_eq!(auth.cached_token().as_deref(), Some("token-1"));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_concurrent_callers_share_one_refresh() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+
+    let tokens = futures::future::join_all((0..5).map(|_| auth.access_token())).await;
+    assert!(tokens.iter().all(|t| t.as_deref().unwrap() == "token-1"));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_refreshes_before_expiry() {
+    // Tokens valid for 30s are always inside the default 60s refresh margin
+    let server = counting_server(30).await;
+    let auth = authenticator(&server);
+
+    assert_eq!(auth.access_token().await.unwrap(), "token-1");
+    assert_eq!(auth.access_token().await.unwrap(), "token-2");
+
+    let auth = auth.with_refresh_margin(Duration::from_secs(10));
+    assert_eq!(auth.access_token().await.unwrap(), "token-2");
+}
+
+#[tokio::test]
+async fn test_cached_token_refreshes_in_background() {
+    let server = counting_server(30).await;
+    let auth = authenticator(&server);
+    auth.access_token().await.unwrap();
+
+    // Still valid, so it is returned while a renewal starts
+    assert_eq!(auth.cached_token().as_deref(), Some("token-1"));
+    for _ in 0..100 {
+        if server.requests().len() >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(server.requests().len() >= 2);
+}
+
+#[tokio::test]
+async fn test_background_refresh_backs_off_after_failure() {
+    let server = TokenServer::start(|_| (503, "unavailable".to_string())).await;
+    let auth = authenticator(&server);
+
+    // Many calls without a token start a single refresh
+    for _ in 0..20 {
+        assert_eq!(auth.cached_token(), None);
+    }
+    for _ in 0..100 {
+        if !server.requests().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(server.requests().len(), 1);
+
+    // After the failure, no new attempt before the backoff has passed
+    for _ in 0..20 {
+        assert_eq!(auth.cached_token(), None);
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_invalidate() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+
+    auth.access_token().await.unwrap();
+    auth.invalidate();
+    assert_eq!(auth.access_token().await.unwrap(), "token-2");
+}
+
+#[tokio::test]
+async fn test_call_retries_once_on_unauthenticated() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let result = auth
+        .call(|| {
+            let attempts = attempts.clone();
+            let auth = auth.clone();
+            async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(Status::unauthenticated("token revoked"))
+                } else {
+                    Ok(auth.cached_token().unwrap())
+                }
+            }
+        })
+        .await;
+
+    assert_eq!(result.unwrap(), "token-2");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_call_does_not_retry_other_errors() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+    let attempts = AtomicUsize::new(0);
+
+    let result: Result<(), Status> = auth
+        .call(|| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(Status::permission_denied("no access")) }
+        })
+        .await;
+
+    assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+// ============================================================================
+// Interceptor
+// ============================================================================
+
+#[tokio::test]
+async fn test_interceptor_uses_authenticator() {
+    let server = counting_server(3600).await;
+    let auth = authenticator(&server);
+    let mut interceptor = GoogleAdsInterceptor::new("dev-token")
+        .unwrap()
+        .with_authenticator(auth.clone());
+
+    // No token fetched yet
+    let status = interceptor.call(Request::new(())).unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    auth.access_token().await.unwrap();
+    let request = interceptor.call(Request::new(())).unwrap();
+    assert_eq!(
+        request.metadata().get("authorization").unwrap(),
+        "Bearer token-1"
+    );
+}
+
+#[test]
+fn test_credentials_debug_redacts_secrets() {
+    let credentials = RefreshTokenCredentials::new("client-id", "client-secret", "refresh-1/x");
+    let debug = format!("{:?}", credentials);
+    assert!(debug.contains("client-id"));
+    assert!(!debug.contains("client-secret"));
+    assert!(!debug.contains("refresh-1/x"));
+}
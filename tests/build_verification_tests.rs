@@ -21,16 +21,21 @@ fn test_google_ads_service_client_exists() {
 
 #[test]
 fn test_other_service_clients_exist() {
-    use googleads_rs::google::ads::googleads::v23::services::{
-        ad_group_service_client::AdGroupServiceClient,
-        campaign_service_client::CampaignServiceClient,
-        customer_service_client::CustomerServiceClient,
-    };
-
-    // Verify other commonly used service clients compile
-    let _: Option<CustomerServiceClient<tonic::transport::Channel>> = None;
-    let _: Option<CampaignServiceClient<tonic::transport::Channel>> = None;
-    let _: Option<AdGroupServiceClient<tonic::transport::Channel>> = None;
+    // Verify other commonly used service clients compile, when their family is enabled
+    #[cfg(feature = "reporting")]
+    {
+        use googleads_rs::google::ads::googleads::v23::services::customer_service_client::CustomerServiceClient;
+        let _: Option<CustomerServiceClient<tonic::transport::Channel>> = None;
+    }
+    #[cfg(feature = "campaign-management")]
+    {
+        use googleads_rs::google::ads::googleads::v23::services::{
+            ad_group_service_client::AdGroupServiceClient,
+            campaign_service_client::CampaignServiceClient,
+        };
+        let _: Option<CampaignServiceClient<tonic::transport::Channel>> = None;
+        let _: Option<AdGroupServiceClient<tonic::transport::Channel>> = None;
+    }
 }
 
 // ============================================================================
@@ -100,3 +105,17 @@ fn test_common_enum_types_exist() {
     let _ad_group_status = AdGroupStatus::Paused;
     let _channel_type = AdvertisingChannelType::Search;
 }
+
+// ============================================================================
+// Test 1.5: Descriptor Pool Complete
+// ============================================================================
+
+#[test]
+fn test_descriptor_pool_has_every_service() {
+    // Service features only narrow codegen; reflection always sees every service
+    let services = googleads_rs::descriptor_pool()
+        .services()
+        .filter(|s| s.package_name() == "google.ads.googleads.v23.services")
+        .count();
+    assert_eq!(services, 111);
+}
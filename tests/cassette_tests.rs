@@ -0,0 +1,289 @@
+// Tests for record-and-replay cassettes
+//
+// These tests record calls made through a mock transport to a cassette file,
+// then replay them without any transport: streamed batches, request ids and
+// GoogleAdsFailure statuses come back as recorded, requests match after GAQL
+// normalization, and repeated requests are replayed in recording order.
+
+mod mock_transport;
+
+use googleads_rs::cassette::{Cassette, CassetteLayer, CassetteMode};
+use googleads_rs::error::GOOGLE_ADS_FAILURE_METADATA_KEY;
+use googleads_rs::google::ads::googleads::v23::errors::{
+    error_code, query_error_enum::QueryError, ErrorCode, GoogleAdsError as GoogleAdsErrorProto,
+    GoogleAdsFailure,
+};
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsRequest,
+    SearchGoogleAdsResponse, SearchGoogleAdsStreamRequest, SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::{GoogleAdsError, GoogleAdsInterceptor};
+use mock_transport::{block_on, encode, MockTransport};
+use prost::Message;
+use std::path::PathBuf;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+use tower::Layer;
+
+const QUERY: &str = "SELECT campaign.id, campaign.name FROM campaign";
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "googleads-rs-{}-{}.jsonl",
+        std::process::id(),
+        name
+    ))
+}
+
+fn batch(ids: &[i64]) -> SearchGoogleAdsStreamResponse {
+    SearchGoogleAdsStreamResponse {
+        results: ids
+            .iter()
+            .map(|&id| GoogleAdsRow {
+                campaign: Some(Campaign {
+                    id: Some(id),
+                    name: Some(format!("Campaign {}", id)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect(),
+        query_resource_consumption: 10,
+        ..Default::default()
+    }
+}
+
+fn stream_request(query: &str) -> SearchGoogleAdsStreamRequest {
+    SearchGoogleAdsStreamRequest {
+        customer_id: "1234567890".to_string(),
+        query: query.to_string(),
+        ..Default::default()
+    }
+}
+
+// Replay must not touch the transport it wraps
+fn unreachable_transport() -> MockTransport {
+    MockTransport::new(|path, _body| panic!("replay called the transport for {}", path))
+}
+
+fn search_stream(
+    channel: Cassette<MockTransport>,
+    request: SearchGoogleAdsStreamRequest,
+) -> Result<Vec<SearchGoogleAdsStreamResponse>, Status> {
+    block_on(async move {
+        let interceptor = GoogleAdsInterceptor::new("SECRET-DEV-TOKEN")
+            .unwrap()
+            .with_access_token("SECRET-ACCESS-TOKEN")
+            .unwrap();
+        let mut client = GoogleAdsServiceClient::with_interceptor(channel, interceptor);
+        let mut stream = client.search_stream(request).await?.into_inner();
+        let mut batches = Vec::new();
+        while let Some(batch) = stream.message().await? {
+            batches.push(batch);
+        }
+        Ok(batches)
+    })
+}
+
+// Returns the total_results_count of a Search call
+fn search_count(channel: Cassette<MockTransport>) -> Result<i64, Status> {
+    block_on(async move {
+        let mut client = GoogleAdsServiceClient::new(channel);
+        let response = client
+            .search(SearchGoogleAdsRequest {
+                customer_id: "1234567890".to_string(),
+                query: QUERY.to_string(),
+                ..Default::default()
+            })
+            .await?;
+        Ok(response.into_inner().total_results_count)
+    })
+}
+
+fn bad_field_status() -> Status {
+    let failure = GoogleAdsFailure {
+        errors: vec![GoogleAdsErrorProto {
+            error_code: Some(ErrorCode {
+                error_code: Some(error_code::ErrorCode::QueryError(
+                    QueryError::BadFieldName as i32,
+                )),
+            }),
+            message: "Unrecognized field in the query: 'campaign.nope'.".to_string(),
+            ..Default::default()
+        }],
+        request_id: "req-failed".to_string(),
+    };
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(&failure.encode_to_vec()),
+    );
+    Status::with_metadata(
+        Code::InvalidArgument,
+        "Request contains an invalid argument.",
+        metadata,
+    )
+}
+
+// ============================================================================
+// Record and replay
+// ============================================================================
+
+#[test]
+fn test_record_then_replay_stream() {
+    let path = cassette_path("stream");
+    let transport =
+        MockTransport::new(|_path, _body| Ok(vec![encode(&batch(&[1, 2])), encode(&batch(&[3]))]))
+            .response_header("request-id", "req-stream");
+    let recorder = CassetteLayer::record(&path).unwrap();
+    let recorded = search_stream(recorder.layer(transport), stream_request(QUERY)).unwrap();
+    assert_eq!(recorded.len(), 2);
+
+    let cassette = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(cassette.lines().count(), 1);
+    assert!(
+        cassette.contains("GoogleAdsService/SearchStream"),
+        "{}",
+        cassette
+    );
+    assert!(cassette.contains("Campaign 3"), "{}", cassette);
+    assert!(cassette.contains("req-stream"), "{}", cassette);
+    assert!(!cassette.contains("SECRET"), "{}", cassette);
+
+    let player = CassetteLayer::replay(&path).unwrap();
+    assert_eq!(player.mode(), CassetteMode::Replay);
+    assert_eq!(player.unplayed(), 1);
+    let replayed =
+        search_stream(player.layer(unreachable_transport()), stream_request(QUERY)).unwrap();
+    assert_eq!(replayed, recorded);
+    assert_eq!(player.unplayed(), 0);
+}
+
+#[test]
+fn test_replay_matches_normalized_query() {
+    let path = cassette_path("normalized");
+    let transport = MockTransport::new(|_path, _body| Ok(vec![encode(&batch(&[7]))]));
+    let recorder = CassetteLayer::record(&path).unwrap();
+    search_stream(recorder.layer(transport), stream_request(QUERY)).unwrap();
+
+    let player = CassetteLayer::replay(&path).unwrap();
+    let replayed = search_stream(
+        player.layer(unreachable_transport()),
+        stream_request("select campaign.id,\n       campaign.name\n  from campaign"),
+    )
+    .unwrap();
+    assert_eq!(replayed, vec![batch(&[7])]);
+}
+
+#[test]
+fn test_record_then_replay_failure() {
+    let path = cassette_path("failure");
+    let transport = MockTransport::new(|_path, _body| Err(bad_field_status()));
+    let recorder = CassetteLayer::record(&path).unwrap();
+    let recorded = search_stream(
+        recorder.layer(transport),
+        stream_request("SELECT campaign.nope FROM campaign"),
+    )
+    .unwrap_err();
+
+    let cassette = std::fs::read_to_string(&path).unwrap();
+    assert!(cassette.contains("BAD_FIELD_NAME"), "{}", cassette);
+
+    let player = CassetteLayer::replay(&path).unwrap();
+    let replayed = search_stream(
+        player.layer(unreachable_transport()),
+        stream_request("SELECT campaign.nope FROM campaign"),
+    )
+    .unwrap_err();
+    assert_eq!(replayed.code(), Code::InvalidArgument);
+    assert_eq!(replayed.message(), recorded.message());
+    let error = GoogleAdsError::from_status(&replayed);
+    assert_eq!(error.errors[0].error_code(), "query_error.BAD_FIELD_NAME");
+    assert_eq!(error.request_id.as_deref(), Some("req-failed"));
+}
+
+#[test]
+fn test_stream_broken_mid_way_is_replayed() {
+    let path = cassette_path("broken");
+    let transport = MockTransport::streaming(|_path, _body| {
+        (
+            vec![encode(&batch(&[1]))],
+            Status::resource_exhausted("quota"),
+        )
+    });
+    let recorder = CassetteLayer::record(&path).unwrap();
+    assert!(search_stream(recorder.layer(transport), stream_request(QUERY)).is_err());
+
+    let player = CassetteLayer::replay(&path).unwrap();
+    let replayed = block_on(async move {
+        let mut client = GoogleAdsServiceClient::new(player.layer(unreachable_transport()));
+        let mut stream = client
+            .search_stream(stream_request(QUERY))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.message().await.unwrap().unwrap();
+        (first, stream.message().await.unwrap_err())
+    });
+    assert_eq!(replayed.0, batch(&[1]));
+    assert_eq!(replayed.1.code(), Code::ResourceExhausted);
+}
+
+// ============================================================================
+// Ordering and misses
+// ============================================================================
+
+#[test]
+fn test_repeated_requests_replay_in_order() {
+    let path = cassette_path("ordered");
+    let calls = std::sync::atomic::AtomicI64::new(0);
+    let transport = MockTransport::new(move |_path, _body| {
+        let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        Ok(vec![encode(&SearchGoogleAdsResponse {
+            total_results_count: call,
+            ..Default::default()
+        })])
+    });
+    let recorder = CassetteLayer::record(&path).unwrap();
+    let channel = recorder.layer(transport);
+    assert_eq!(search_count(channel.clone()).unwrap(), 1);
+    assert_eq!(search_count(channel).unwrap(), 2);
+
+    let player = CassetteLayer::replay(&path).unwrap();
+    let channel = player.layer(unreachable_transport());
+    let first = search_count(channel.clone()).unwrap();
+    let second = search_count(channel.clone()).unwrap();
+    let third = search_count(channel).unwrap_err();
+    assert_eq!(first, 1);
+    assert_eq!(second, 2);
+    assert_eq!(third.code(), Code::NotFound);
+    assert!(
+        third.message().contains("GoogleAdsService/Search"),
+        "{}",
+        third.message()
+    );
+}
+
+#[test]
+fn test_unrecorded_request_is_not_found() {
+    let path = cassette_path("miss");
+    std::fs::write(&path, "").unwrap();
+    let player = CassetteLayer::open(&path, CassetteMode::Replay).unwrap();
+    let status =
+        search_stream(player.layer(unreachable_transport()), stream_request(QUERY)).unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[test]
+fn test_invalid_cassette_is_rejected() {
+    let path = cassette_path("invalid");
+    std::fs::write(
+        &path,
+        r#"{"method":"/google.ads.googleads.v23.services.GoogleAdsService/Search","request":"no_such_field: 1"}"#,
+    )
+    .unwrap();
+    let err = CassetteLayer::replay(&path).unwrap_err();
+    assert!(format!("{:#}", err).contains(":1"), "{:#}", err);
+    assert!(CassetteLayer::replay(cassette_path("missing")).is_err());
+}
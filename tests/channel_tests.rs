@@ -0,0 +1,129 @@
+// Tests for channel configuration
+//
+// These tests check the defaults and endpoint handling of ChannelBuilder, then
+// connect clients to the in-process fake GoogleAdsService through an endpoint
+// override: gzip-compressed streams round-trip, and responses over the maximum
+// decoded message size are rejected.
+
+#![cfg(feature = "server")]
+
+mod mock_transport;
+
+use googleads_rs::client::DEFAULT_ENDPOINT;
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    GoogleAdsRow, SearchGoogleAdsStreamRequest, SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::server::FakeGoogleAdsService;
+use googleads_rs::{ChannelBuilder, GoogleAdsInterceptor};
+use mock_transport::block_on;
+use tonic::Code;
+
+const QUERY: &str = "SELECT campaign.id, campaign.name FROM campaign";
+
+fn batch(rows: i64) -> SearchGoogleAdsStreamResponse {
+    SearchGoogleAdsStreamResponse {
+        results: (0..rows)
+            .map(|id| GoogleAdsRow {
+                campaign: Some(Campaign {
+                    id: Some(id),
+                    name: Some(format!("Campaign {}", id)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn stream_request() -> SearchGoogleAdsStreamRequest {
+    SearchGoogleAdsStreamRequest {
+        customer_id: "1234567890".to_string(),
+        query: QUERY.to_string(),
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// Builder
+// ============================================================================
+
+#[test]
+fn test_endpoint_defaults_and_override() {
+    let mut builder = ChannelBuilder::new();
+    assert_eq!(builder.endpoint_url(), DEFAULT_ENDPOINT);
+    let endpoint = builder.build().unwrap();
+    assert_eq!(endpoint.uri().host(), Some("googleads.googleapis.com"));
+
+    builder.endpoint("googleads.example.com:8443");
+    assert_eq!(builder.endpoint_url(), "https://googleads.example.com:8443");
+    builder.endpoint("http://127.0.0.1:50051");
+    assert_eq!(builder.endpoint_url(), "http://127.0.0.1:50051");
+
+    assert!(ChannelBuilder::new()
+        .endpoint("http://bad host")
+        .build()
+        .is_err());
+}
+
+// ============================================================================
+// Connected clients
+// ============================================================================
+
+#[test]
+fn test_gzip_stream_round_trips() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query(QUERY, vec![batch(500), batch(3)]);
+
+    let (encoding, rows) = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = ChannelBuilder::new()
+            .endpoint(&server.endpoint())
+            .gzip(true)
+            .connect_client(GoogleAdsInterceptor::new("fake-dev-token").unwrap())
+            .await
+            .unwrap();
+        let response = client
+            .google_ads()
+            .search_stream(stream_request())
+            .await
+            .unwrap();
+        let encoding = response
+            .metadata()
+            .get("grpc-encoding")
+            .map(|v| v.to_str().unwrap().to_string());
+        let mut stream = response.into_inner();
+        let mut rows = 0;
+        while let Some(batch) = stream.message().await.unwrap() {
+            rows += batch.results.len();
+        }
+        (encoding, rows)
+    });
+    assert_eq!(encoding.as_deref(), Some("gzip"));
+    assert_eq!(rows, 503);
+}
+
+#[test]
+fn test_responses_over_the_size_limit_are_rejected() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query(QUERY, vec![batch(1000)]);
+
+    let status = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = ChannelBuilder::new()
+            .endpoint(&server.endpoint())
+            .max_decoding_message_size(1024)
+            .connect_client(GoogleAdsInterceptor::new("fake-dev-token").unwrap())
+            .await
+            .unwrap();
+        let mut stream = client
+            .google_ads()
+            .search_stream(stream_request())
+            .await
+            .unwrap()
+            .into_inner();
+        stream.message().await.unwrap_err()
+    });
+    assert_eq!(status.code(), Code::OutOfRange);
+}
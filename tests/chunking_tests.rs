@@ -0,0 +1,381 @@
+// Tests for date-range chunking of GAQL queries
+//
+// These tests verify how DateChunker splits BETWEEN and DURING ranges into
+// windows ordered by segments.date, and that chunked search_stream results are
+// merged in date order and yielded batch by batch.
+
+mod mock_transport;
+
+use chrono::NaiveDate;
+use futures::StreamExt;
+use googleads_rs::chunking::resolve_during;
+use googleads_rs::google::ads::googleads::v23::common::Segments;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::{ChunkWindow, DateChunker, Query};
+use mock_transport::{block_on, encode, MockTransport};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn between(from: &str, to: &str) -> Query {
+    Query::parse(&format!(
+        "SELECT segments.date, metrics.clicks FROM campaign \
+         WHERE campaign.status = 'ENABLED' AND segments.date BETWEEN '{}' AND '{}'",
+        from, to
+    ))
+    .unwrap()
+}
+
+// ============================================================================
+// Windows
+// ============================================================================
+
+#[test]
+fn test_day_windows() {
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let windows = chunker.windows(date(2024, 2, 28), date(2024, 3, 1));
+    assert_eq!(
+        windows,
+        vec![
+            (date(2024, 2, 28), date(2024, 2, 28)),
+            (date(2024, 2, 29), date(2024, 2, 29)),
+            (date(2024, 3, 1), date(2024, 3, 1)),
+        ]
+    );
+}
+
+#[test]
+fn test_week_windows_align_to_monday() {
+    let chunker = DateChunker::new(ChunkWindow::Week);
+    // 2024-01-03 is a Wednesday
+    let windows = chunker.windows(date(2024, 1, 3), date(2024, 1, 20));
+    assert_eq!(
+        windows,
+        vec![
+            (date(2024, 1, 3), date(2024, 1, 7)),
+            (date(2024, 1, 8), date(2024, 1, 14)),
+            (date(2024, 1, 15), date(2024, 1, 20)),
+        ]
+    );
+}
+
+#[test]
+fn test_month_windows_align_to_calendar() {
+    let chunker = DateChunker::new(ChunkWindow::Month);
+    let windows = chunker.windows(date(2023, 12, 15), date(2024, 3, 10));
+    assert_eq!(
+        windows,
+        vec![
+            (date(2023, 12, 15), date(2023, 12, 31)),
+            (date(2024, 1, 1), date(2024, 1, 31)),
+            (date(2024, 2, 1), date(2024, 2, 29)),
+            (date(2024, 3, 1), date(2024, 3, 10)),
+        ]
+    );
+}
+
+#[test]
+fn test_two_years_by_month() {
+    let chunker = DateChunker::new(ChunkWindow::Month);
+    let windows = chunker.windows(date(2023, 1, 1), date(2024, 12, 31));
+    assert_eq!(windows.len(), 24);
+}
+
+// ============================================================================
+// Splitting
+// ============================================================================
+
+#[test]
+fn test_split_between() {
+    let chunker = DateChunker::new(ChunkWindow::Month);
+    let chunks = chunker.split(&between("2024-01-20", "2024-02-10")).unwrap();
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(
+        chunks[0].to_string(),
+        "SELECT segments.date, metrics.clicks FROM campaign \
+         WHERE campaign.status = 'ENABLED' AND segments.date BETWEEN '2024-01-20' AND '2024-01-31' \
+         ORDER BY segments.date ASC"
+    );
+    assert!(chunks[1].to_string().ends_with(
+        "segments.date BETWEEN '2024-02-01' AND '2024-02-10' ORDER BY segments.date ASC"
+    ));
+}
+
+#[test]
+fn test_split_keeps_or_rejects_ordering() {
+    let chunker = DateChunker::new(ChunkWindow::Month);
+    let by_date_then_clicks = Query::parse(
+        "SELECT segments.date, metrics.clicks FROM campaign \
+         WHERE segments.date BETWEEN '2024-01-01' AND '2024-01-31' \
+         ORDER BY segments.date, metrics.clicks DESC",
+    )
+    .unwrap();
+    assert!(chunker.split(&by_date_then_clicks).unwrap()[0]
+        .to_string()
+        .ends_with("ORDER BY segments.date ASC, metrics.clicks DESC"));
+
+    let by_clicks = Query::parse(
+        "SELECT segments.date, metrics.clicks FROM campaign \
+         WHERE segments.date BETWEEN '2024-01-01' AND '2024-01-31' ORDER BY metrics.clicks DESC",
+    )
+    .unwrap();
+    let err = chunker.split(&by_clicks).unwrap_err();
+    assert!(err.to_string().contains("segments.date ASC"), "{}", err);
+
+    // Without segments.date in SELECT, rows are per window and need no ordering
+    let per_window = Query::parse(
+        "SELECT campaign.id, metrics.clicks FROM campaign \
+         WHERE segments.date BETWEEN '2024-01-01' AND '2024-01-31'",
+    )
+    .unwrap();
+    assert!(!chunker.split(&per_window).unwrap()[0]
+        .to_string()
+        .contains("ORDER BY"));
+}
+
+#[test]
+fn test_split_during() {
+    let mut chunker = DateChunker::new(ChunkWindow::Week);
+    chunker.today(date(2024, 5, 15));
+    let query = Query::parse(
+        "SELECT segments.date, metrics.clicks FROM campaign WHERE segments.date DURING LAST_14_DAYS",
+    )
+    .unwrap();
+    assert_eq!(
+        chunker.date_range(&query).unwrap(),
+        (date(2024, 5, 1), date(2024, 5, 14))
+    );
+    let chunks = chunker.split(&query).unwrap();
+    assert_eq!(chunks.len(), 3);
+}
+
+#[test]
+fn test_split_rejects_missing_date_condition() {
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let query = Query::parse("SELECT campaign.id FROM campaign").unwrap();
+    assert!(chunker.split(&query).is_err());
+}
+
+#[test]
+fn test_split_rejects_unsupported_operator() {
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let query =
+        Query::parse("SELECT campaign.id FROM campaign WHERE segments.date >= '2024-01-01'")
+            .unwrap();
+    assert!(chunker.split(&query).is_err());
+}
+
+#[test]
+fn test_split_rejects_limit() {
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let query = Query::parse(
+        "SELECT campaign.id FROM campaign \
+         WHERE segments.date BETWEEN '2024-01-01' AND '2024-01-02' LIMIT 10",
+    )
+    .unwrap();
+    assert!(chunker.split(&query).is_err());
+}
+
+#[test]
+fn test_split_rejects_inverted_range() {
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    assert!(chunker.split(&between("2024-02-01", "2024-01-01")).is_err());
+}
+
+#[test]
+fn test_split_rejects_unbound_placeholder() {
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let query =
+        Query::parse("SELECT campaign.id FROM campaign WHERE segments.date BETWEEN :from AND :to")
+            .unwrap();
+    assert!(chunker.split(&query).is_err());
+}
+
+// ============================================================================
+// DURING Resolution
+// ============================================================================
+
+#[test]
+fn test_resolve_during_functions() {
+    // 2024-05-15 is a Wednesday
+    let today = date(2024, 5, 15);
+    assert_eq!(resolve_during("TODAY", today).unwrap(), (today, today));
+    assert_eq!(
+        resolve_during("YESTERDAY", today).unwrap(),
+        (date(2024, 5, 14), date(2024, 5, 14))
+    );
+    assert_eq!(
+        resolve_during("LAST_7_DAYS", today).unwrap(),
+        (date(2024, 5, 8), date(2024, 5, 14))
+    );
+    assert_eq!(
+        resolve_during("LAST_30_DAYS", today).unwrap(),
+        (date(2024, 4, 15), date(2024, 5, 14))
+    );
+    assert_eq!(
+        resolve_during("THIS_MONTH", today).unwrap(),
+        (date(2024, 5, 1), today)
+    );
+    assert_eq!(
+        resolve_during("LAST_MONTH", today).unwrap(),
+        (date(2024, 4, 1), date(2024, 4, 30))
+    );
+    assert_eq!(
+        resolve_during("THIS_WEEK_MON_TODAY", today).unwrap(),
+        (date(2024, 5, 13), today)
+    );
+    assert_eq!(
+        resolve_during("THIS_WEEK_SUN_TODAY", today).unwrap(),
+        (date(2024, 5, 12), today)
+    );
+    assert_eq!(
+        resolve_during("LAST_WEEK_MON_SUN", today).unwrap(),
+        (date(2024, 5, 6), date(2024, 5, 12))
+    );
+    assert_eq!(
+        resolve_during("LAST_WEEK_SUN_SAT", today).unwrap(),
+        (date(2024, 5, 5), date(2024, 5, 11))
+    );
+    assert_eq!(
+        resolve_during("LAST_BUSINESS_WEEK", today).unwrap(),
+        (date(2024, 5, 6), date(2024, 5, 10))
+    );
+    assert!(resolve_during("NEXT_WEEK", today).is_err());
+}
+
+// ============================================================================
+// Chunked search_stream
+// ============================================================================
+
+fn date_row(d: &str) -> GoogleAdsRow {
+    GoogleAdsRow {
+        segments: Some(Segments {
+            date: Some(d.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_search_stream_merges_chunks_in_date_order() {
+    let transport = MockTransport::new(|_path, body| {
+        let request: SearchGoogleAdsStreamRequest = prost::Message::decode(body).unwrap();
+        let query = Query::parse(&request.query).unwrap();
+        let mut chunker = DateChunker::new(ChunkWindow::Day);
+        chunker.concurrency(1);
+        let (from, to) = chunker.date_range(&query).unwrap();
+        // Answer each chunk with one row per day, split over two batches
+        let rows: Vec<GoogleAdsRow> = chunker
+            .windows(from, to)
+            .iter()
+            .map(|(d, _)| date_row(&d.format("%Y-%m-%d").to_string()))
+            .collect();
+        let (first, second) = rows.split_at(rows.len() / 2);
+        Ok(vec![
+            encode(&SearchGoogleAdsStreamResponse {
+                results: first.to_vec(),
+                ..Default::default()
+            }),
+            encode(&SearchGoogleAdsStreamResponse {
+                results: second.to_vec(),
+                ..Default::default()
+            }),
+        ])
+    });
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let mut chunker = DateChunker::new(ChunkWindow::Week);
+    chunker.concurrency(3);
+    let stream = chunker
+        .search_stream(client, "1234567890", &between("2024-01-01", "2024-01-31"))
+        .unwrap();
+    let rows: Vec<GoogleAdsRow> = block_on(stream.map(|r| r.unwrap()).collect());
+
+    let dates: Vec<String> = rows.iter().map(|r| r.get("segments.date")).collect();
+    assert_eq!(dates.len(), 31);
+    assert_eq!(dates.first().unwrap(), "2024-01-01");
+    assert_eq!(dates.last().unwrap(), "2024-01-31");
+    let mut sorted = dates.clone();
+    sorted.sort();
+    assert_eq!(dates, sorted);
+
+    let calls = transport.calls();
+    assert_eq!(calls.len(), 5);
+    let request: SearchGoogleAdsStreamRequest = calls[0].decode();
+    assert_eq!(request.customer_id, "1234567890");
+    assert!(request.query.ends_with("ORDER BY segments.date ASC"));
+}
+
+#[test]
+fn test_search_stream_yields_batches_before_chunk_ends() {
+    // The first chunk breaks after one batch: its rows still come before the error
+    let transport = MockTransport::streaming(|_path, body| {
+        let request: SearchGoogleAdsStreamRequest = prost::Message::decode(body).unwrap();
+        let batch = |date: &str| {
+            encode(&SearchGoogleAdsStreamResponse {
+                results: vec![date_row(date)],
+                ..Default::default()
+            })
+        };
+        if request.query.contains("'2024-01-01'") {
+            (
+                vec![batch("2024-01-01")],
+                tonic::Status::unavailable("stream broken"),
+            )
+        } else {
+            (vec![batch("2024-01-02")], tonic::Status::ok(""))
+        }
+    });
+    let client = GoogleAdsServiceClient::new(transport);
+
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let stream = chunker
+        .search_stream(client, "1", &between("2024-01-01", "2024-01-02"))
+        .unwrap();
+    let results: Vec<_> = block_on(stream.collect::<Vec<_>>());
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        results[0].as_ref().unwrap().get("segments.date"),
+        "2024-01-01"
+    );
+    assert_eq!(
+        results[1].as_ref().unwrap_err().code(),
+        tonic::Code::Unavailable
+    );
+    assert_eq!(
+        results[2].as_ref().unwrap().get("segments.date"),
+        "2024-01-02"
+    );
+}
+
+#[test]
+fn test_search_stream_surfaces_chunk_error() {
+    let transport = MockTransport::new(|_path, body| {
+        let request: SearchGoogleAdsStreamRequest = prost::Message::decode(body).unwrap();
+        if request.query.contains("2024-01-02") {
+            Err(tonic::Status::deadline_exceeded("too slow"))
+        } else {
+            Ok(vec![encode(&SearchGoogleAdsStreamResponse {
+                results: vec![date_row("2024-01-01")],
+                ..Default::default()
+            })])
+        }
+    });
+    let client = GoogleAdsServiceClient::new(transport);
+
+    let chunker = DateChunker::new(ChunkWindow::Day);
+    let stream = chunker
+        .search_stream(client, "1", &between("2024-01-01", "2024-01-03"))
+        .unwrap();
+    let results: Vec<_> = block_on(stream.collect::<Vec<_>>());
+
+    assert!(results[0].is_ok());
+    let err = results[1].as_ref().unwrap_err();
+    assert_eq!(err.code(), tonic::Code::DeadlineExceeded);
+}
@@ -0,0 +1,132 @@
+// Tests for GoogleAdsClient and its header-injecting interceptor
+//
+// These tests verify the metadata added to every request, the per-call
+// login-customer-id override, and the generated service client accessors.
+
+use googleads_rs::client::normalize_customer_id;
+use googleads_rs::{GoogleAdsClient, GoogleAdsInterceptor, LoginCustomerId};
+use tonic::service::Interceptor;
+use tonic::transport::Channel;
+use tonic::Request;
+
+fn header<'a>(request: &'a Request<()>, name: &str) -> Option<&'a str> {
+    request
+        .metadata()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+// ============================================================================
+// Interceptor
+// ============================================================================
+
+#[test]
+fn test_developer_token_only() {
+    let mut interceptor = GoogleAdsInterceptor::new("dev-token").unwrap();
+    let request = interceptor.call(Request::new(())).unwrap();
+    assert_eq!(header(&request, "developer-token"), Some("dev-token"));
+    assert_eq!(header(&request, "login-customer-id"), None);
+    assert_eq!(header(&request, "linked-customer-id"), None);
+    assert_eq!(header(&request, "authorization"), None);
+}
+
+#[test]
+fn test_all_headers() {
+    let mut interceptor = GoogleAdsInterceptor::new("dev-token")
+        .unwrap()
+        .with_login_customer_id("123-456-7890")
+        .unwrap()
+        .with_linked_customer_id("555-666-7777")
+        .unwrap()
+        .with_access_token("ya29.token")
+        .unwrap();
+    let request = interceptor.call(Request::new(())).unwrap();
+    assert_eq!(header(&request, "developer-token"), Some("dev-token"));
+    assert_eq!(header(&request, "login-customer-id"), Some("1234567890"));
+    assert_eq!(header(&request, "linked-customer-id"), Some("5556667777"));
+    assert_eq!(header(&request, "authorization"), Some("Bearer ya29.token"));
+}
+
+#[test]
+fn test_per_call_login_customer_override() {
+    let mut interceptor = GoogleAdsInterceptor::new("dev-token")
+        .unwrap()
+        .with_login_customer_id("1111111111")
+        .unwrap();
+    let mut request = Request::new(());
+    request
+        .extensions_mut()
+        .insert(LoginCustomerId::new("222-222-2222"));
+    let request = interceptor.call(request).unwrap();
+    assert_eq!(header(&request, "login-customer-id"), Some("2222222222"));
+
+    // The default is used again for the next call
+    let request = interceptor.call(Request::new(())).unwrap();
+    assert_eq!(header(&request, "login-customer-id"), Some("1111111111"));
+}
+
+#[test]
+fn test_invalid_override_is_rejected() {
+    let mut interceptor = GoogleAdsInterceptor::new("dev-token").unwrap();
+    let mut request = Request::new(());
+    request
+        .extensions_mut()
+        .insert(LoginCustomerId("12\n34".to_string()));
+    let status = interceptor.call(request).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[test]
+fn test_invalid_developer_token() {
+    assert!(GoogleAdsInterceptor::new("bad\ntoken").is_err());
+}
+
+#[test]
+fn test_debug_redacts_secrets() {
+    let interceptor = GoogleAdsInterceptor::new("dev-token")
+        .unwrap()
+        .with_access_token("ya29.token")
+        .unwrap();
+    let debug = format!("{:?}", interceptor);
+    assert!(!debug.contains("dev-token"));
+    assert!(!debug.contains("ya29.token"));
+}
+
+#[test]
+fn test_normalize_customer_id() {
+    assert_eq!(normalize_customer_id("123-456-7890"), "1234567890");
+    assert_eq!(normalize_customer_id("1234567890"), "1234567890");
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[tokio::test]
+async fn test_with_login_customer_id_keeps_original() {
+    let channel = Channel::from_static("http://127.0.0.1:1").connect_lazy();
+    let client = GoogleAdsClient::new(channel, GoogleAdsInterceptor::new("dev-token").unwrap());
+    let scoped = client.with_login_customer_id("999-000-1111").unwrap();
+    assert_eq!(client.interceptor().login_customer_id(), None);
+    assert_eq!(scoped.interceptor().login_customer_id(), Some("9990001111"));
+}
+
+#[tokio::test]
+async fn test_service_accessors() {
+    let channel = Channel::from_static("http://127.0.0.1:1").connect_lazy();
+    let client = GoogleAdsClient::new(channel, GoogleAdsInterceptor::new("dev-token").unwrap());
+    // Accessors are generated for every service
+    let _ = client.google_ads();
+    #[cfg(feature = "reporting")]
+    {
+        let _ = client.google_ads_field();
+        let _ = client.customer();
+    }
+    #[cfg(feature = "campaign-management")]
+    let _ = client.campaign();
+    #[cfg(feature = "account-management")]
+    let _ = client.customer_client_link();
+    // Outside the service families
+    #[cfg(feature = "all-services")]
+    let _ = client.you_tube_video_upload();
+}
@@ -0,0 +1,206 @@
+// Tests for loading configuration from google-ads.yaml and the environment
+//
+// These tests verify parsing of the shared google-ads.yaml format, GOOGLE_ADS_*
+// overrides, validation, and building interceptors from the configuration.
+
+use googleads_rs::GoogleAdsConfig;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const YAML: &str = r#"
+# Same file as used by the Python client library
+developer_token: INSERT_DEVELOPER_TOKEN_HERE
+client_id: 1234.apps.googleusercontent.com
+client_secret: secret
+refresh_token: 1//refresh
+login_customer_id: 1234567890
+use_proto_plus: True
+"#;
+
+fn write_temp(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("googleads-rs-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+// ============================================================================
+// YAML
+// ============================================================================
+
+#[test]
+fn test_from_yaml() {
+    let config = GoogleAdsConfig::from_yaml(YAML).unwrap();
+    assert_eq!(
+        config.developer_token.as_deref(),
+        Some("INSERT_DEVELOPER_TOKEN_HERE")
+    );
+    assert_eq!(
+        config.client_id.as_deref(),
+        Some("1234.apps.googleusercontent.com")
+    );
+    assert_eq!(config.refresh_token.as_deref(), Some("1//refresh"));
+    // Unquoted customer ids are parsed as numbers by YAML
+    assert_eq!(config.login_customer_id.as_deref(), Some("1234567890"));
+    assert_eq!(config.linked_customer_id, None);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_empty_yaml() {
+    assert_eq!(
+        GoogleAdsConfig::from_yaml("").unwrap(),
+        GoogleAdsConfig::default()
+    );
+}
+
+#[test]
+fn test_invalid_yaml() {
+    assert!(GoogleAdsConfig::from_yaml("developer_token: [a, b]").is_err());
+}
+
+#[test]
+fn test_from_file() {
+    let path = write_temp("from-file.yaml", YAML);
+    let config = GoogleAdsConfig::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(config.client_secret.as_deref(), Some("secret"));
+
+    assert!(GoogleAdsConfig::from_file("/nonexistent/google-ads.yaml").is_err());
+}
+
+// ============================================================================
+// Environment Overrides
+// ============================================================================
+
+#[test]
+fn test_env_overrides_yaml() {
+    let mut config = GoogleAdsConfig::from_yaml(YAML).unwrap();
+    config.override_with(env(&[
+        ("GOOGLE_ADS_DEVELOPER_TOKEN", "env-token"),
+        ("GOOGLE_ADS_LINKED_CUSTOMER_ID", "555-666-7777"),
+        ("GOOGLE_ADS_ENDPOINT", "localhost:50051"),
+        ("GOOGLE_ADS_CLIENT_SECRET", ""),
+    ]));
+    assert_eq!(config.developer_token.as_deref(), Some("env-token"));
+    assert_eq!(config.linked_customer_id.as_deref(), Some("555-666-7777"));
+    assert_eq!(config.endpoint.as_deref(), Some("localhost:50051"));
+    // Empty values do not override
+    assert_eq!(config.client_secret.as_deref(), Some("secret"));
+    assert_eq!(config.login_customer_id.as_deref(), Some("1234567890"));
+}
+
+#[test]
+fn test_load_from_configured_path_and_env() {
+    // The only test touching process environment variables
+    let path = write_temp("load.yaml", YAML);
+    std::env::set_var("GOOGLE_ADS_CONFIGURATION_FILE_PATH", &path);
+    std::env::set_var("GOOGLE_ADS_LOGIN_CUSTOMER_ID", "111-222-3333");
+    let config = GoogleAdsConfig::load();
+
+    std::env::set_var(
+        "GOOGLE_ADS_CONFIGURATION_FILE_PATH",
+        "/nonexistent/google-ads.yaml",
+    );
+    let missing = GoogleAdsConfig::load();
+
+    std::env::remove_var("GOOGLE_ADS_CONFIGURATION_FILE_PATH");
+    std::env::remove_var("GOOGLE_ADS_LOGIN_CUSTOMER_ID");
+    std::fs::remove_file(&path).unwrap();
+
+    let config = config.unwrap();
+    assert_eq!(config.login_customer_id.as_deref(), Some("111-222-3333"));
+    assert_eq!(config.refresh_token.as_deref(), Some("1//refresh"));
+    assert!(missing.is_err());
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+#[test]
+fn test_requires_developer_token() {
+    let config =
+        GoogleAdsConfig::from_yaml("client_id: x\nclient_secret: y\nrefresh_token: z").unwrap();
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("developer_token"), "{}", err);
+}
+
+#[test]
+fn test_requires_complete_oauth_credentials() {
+    let config = GoogleAdsConfig::from_yaml("developer_token: t\nclient_id: x").unwrap();
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("client_secret, refresh_token"), "{}", err);
+}
+
+#[test]
+fn test_service_account_needs_no_refresh_token() {
+    let config = GoogleAdsConfig::from_yaml(
+        "developer_token: t\njson_key_file_path: /etc/key.json\nimpersonated_email: admin@example.com",
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_rejects_malformed_customer_id() {
+    let mut config = GoogleAdsConfig::from_yaml(YAML).unwrap();
+    config.login_customer_id = Some("12345".to_string());
+    assert!(config.validate().is_err());
+    config.login_customer_id = Some("123-456-7890".to_string());
+    assert!(config.validate().is_ok());
+}
+
+// ============================================================================
+// Client Construction
+// ============================================================================
+
+#[test]
+fn test_endpoint_url() {
+    let mut config = GoogleAdsConfig::default();
+    assert_eq!(config.endpoint_url(), "https://googleads.googleapis.com");
+    config.endpoint = Some("googleads.googleapis.com".to_string());
+    assert_eq!(config.endpoint_url(), "https://googleads.googleapis.com");
+    config.endpoint = Some("http://localhost:50051".to_string());
+    assert_eq!(config.endpoint_url(), "http://localhost:50051");
+}
+
+#[test]
+fn test_interceptor_from_config() {
+    let config = GoogleAdsConfig::from_yaml(YAML).unwrap();
+    let interceptor = config.interceptor().unwrap();
+    assert_eq!(interceptor.login_customer_id(), Some("1234567890"));
+    assert!(interceptor.authenticator().is_some());
+}
+
+#[test]
+fn test_service_account_authenticator_from_config() {
+    let mut config = GoogleAdsConfig::from_yaml("developer_token: t").unwrap();
+    config.json_key_file_path = Some(
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/service_account_key.json"
+        )
+        .to_string(),
+    );
+    assert!(config.authenticator().is_ok());
+
+    config.json_key_file_path = Some("/nonexistent/key.json".to_string());
+    assert!(config.authenticator().is_err());
+}
+
+#[test]
+fn test_debug_redacts_secrets() {
+    let config = GoogleAdsConfig::from_yaml(YAML).unwrap();
+    let debug = format!("{:?}", config);
+    assert!(debug.contains("1234.apps.googleusercontent.com"));
+    assert!(!debug.contains("INSERT_DEVELOPER_TOKEN_HERE"));
+    assert!(!debug.contains("1//refresh"));
+}
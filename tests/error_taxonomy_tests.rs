@@ -0,0 +1,286 @@
+// Tests for the error taxonomy generated from the ErrorCode descriptors
+//
+// These tests verify that every enum value of the ErrorCode oneof is classified,
+// spot-check the categories of well-known error codes, and check how a
+// GoogleAdsError combines the categories of its errors.
+
+use googleads_rs::error::{classify, error_taxonomy, GOOGLE_ADS_FAILURE_FQN};
+use googleads_rs::google::ads::googleads::v23::errors::{
+    authentication_error_enum::AuthenticationError, error_code, internal_error_enum::InternalError,
+    quota_error_enum::QuotaError, request_error_enum::RequestError, ErrorCode,
+    GoogleAdsError as GoogleAdsErrorProto, GoogleAdsFailure,
+};
+use googleads_rs::retry::is_transient;
+use googleads_rs::{ErrorCategory, GoogleAdsError};
+use prost::Message;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+
+fn error(code: error_code::ErrorCode) -> GoogleAdsErrorProto {
+    GoogleAdsErrorProto {
+        error_code: Some(ErrorCode {
+            error_code: Some(code),
+        }),
+        message: "error".to_string(),
+        ..Default::default()
+    }
+}
+
+fn status(code: Code, errors: Vec<GoogleAdsErrorProto>) -> Status {
+    let failure = GoogleAdsFailure {
+        errors,
+        request_id: "abc".to_string(),
+    };
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        googleads_rs::error::GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(&failure.encode_to_vec()),
+    );
+    Status::with_metadata(code, "failed", metadata)
+}
+
+// ============================================================================
+// Generated Taxonomy
+// ============================================================================
+
+#[test]
+fn test_taxonomy_covers_every_error_enum() {
+    let pool = googleads_rs::descriptor_pool();
+    let error_code = pool
+        .get_message_by_name("google.ads.googleads.v23.errors.ErrorCode")
+        .unwrap();
+    let taxonomy = error_taxonomy();
+
+    let mut expected = 0;
+    for field in error_code.fields() {
+        if let Some(enum_desc) = field.kind().as_enum() {
+            for value in enum_desc.values() {
+                assert!(
+                    taxonomy.contains_key(&(field.name().to_string(), value.name().to_string())),
+                    "{}.{} is not classified",
+                    field.name(),
+                    value.name()
+                );
+                expected += 1;
+            }
+        }
+    }
+    assert_eq!(taxonomy.len(), expected);
+    assert!(taxonomy.len() > 1000, "{}", taxonomy.len());
+}
+
+#[test]
+fn test_spot_checks() {
+    let cases = [
+        (
+            "internal_error",
+            "TRANSIENT_ERROR",
+            ErrorCategory::Retryable,
+        ),
+        ("internal_error", "INTERNAL_ERROR", ErrorCategory::Retryable),
+        (
+            "internal_error",
+            "ERROR_CODE_NOT_PUBLISHED",
+            ErrorCategory::InvalidRequest,
+        ),
+        (
+            "database_error",
+            "CONCURRENT_MODIFICATION",
+            ErrorCategory::Retryable,
+        ),
+        ("quota_error", "RESOURCE_EXHAUSTED", ErrorCategory::Quota),
+        (
+            "quota_error",
+            "RESOURCE_TEMPORARILY_EXHAUSTED",
+            ErrorCategory::Quota,
+        ),
+        (
+            "quota_error",
+            "EXCESSIVE_SHORT_TERM_QUERY_RESOURCE_CONSUMPTION",
+            ErrorCategory::Quota,
+        ),
+        (
+            "quota_error",
+            "EXCESSIVE_LONG_TERM_QUERY_RESOURCE_CONSUMPTION",
+            ErrorCategory::Quota,
+        ),
+        ("quota_error", "ACCESS_PROHIBITED", ErrorCategory::Auth),
+        (
+            "authentication_error",
+            "OAUTH_TOKEN_EXPIRED",
+            ErrorCategory::Auth,
+        ),
+        (
+            "authorization_error",
+            "USER_PERMISSION_DENIED",
+            ErrorCategory::Auth,
+        ),
+        (
+            "policy_finding_error",
+            "POLICY_FINDING",
+            ErrorCategory::PolicyViolation,
+        ),
+        (
+            "policy_violation_error",
+            "POLICY_ERROR",
+            ErrorCategory::PolicyViolation,
+        ),
+        (
+            "customer_error",
+            "CREATION_DENIED_FOR_POLICY_VIOLATION",
+            ErrorCategory::PolicyViolation,
+        ),
+        (
+            "request_error",
+            "INVALID_CUSTOMER_ID",
+            ErrorCategory::InvalidRequest,
+        ),
+        ("field_error", "REQUIRED", ErrorCategory::InvalidRequest),
+        ("request_error", "UNSPECIFIED", ErrorCategory::Unknown),
+        ("no_such_error", "NOPE", ErrorCategory::Unknown),
+    ];
+    for (kind, code, category) in cases {
+        assert_eq!(classify(kind, code), category, "{}.{}", kind, code);
+    }
+}
+
+#[test]
+fn test_retryable_categories() {
+    assert!(ErrorCategory::Retryable.is_retryable());
+    assert!(!ErrorCategory::Quota.is_retryable());
+    assert!(!ErrorCategory::Auth.is_retryable());
+    assert!(!ErrorCategory::InvalidRequest.is_retryable());
+    assert!(!ErrorCategory::PartialFailure.is_retryable());
+    assert_eq!(
+        ErrorCategory::PolicyViolation.to_string(),
+        "policy_violation"
+    );
+}
+
+// ============================================================================
+// GoogleAdsError
+// ============================================================================
+
+#[test]
+fn test_error_category_from_details() {
+    let transient = GoogleAdsError::from_status(&status(
+        Code::Internal,
+        vec![error(error_code::ErrorCode::InternalError(
+            InternalError::TransientError as i32,
+        ))],
+    ));
+    assert_eq!(transient.errors[0].category(), ErrorCategory::Retryable);
+    assert!(transient.is_retryable());
+
+    let auth = GoogleAdsError::from_status(&status(
+        Code::Unauthenticated,
+        vec![error(error_code::ErrorCode::AuthenticationError(
+            AuthenticationError::OauthTokenExpired as i32,
+        ))],
+    ));
+    assert_eq!(auth.category(), ErrorCategory::Auth);
+    assert!(!auth.is_retryable());
+}
+
+#[test]
+fn test_quota_access_prohibited_is_not_retryable() {
+    // The developer token lacks the access level: no amount of waiting helps
+    let prohibited = GoogleAdsError::from_status(&status(
+        Code::PermissionDenied,
+        vec![error(error_code::ErrorCode::QuotaError(
+            QuotaError::AccessProhibited as i32,
+        ))],
+    ));
+    assert_eq!(
+        prohibited.errors[0].error_code(),
+        "quota_error.ACCESS_PROHIBITED"
+    );
+    assert_eq!(prohibited.category(), ErrorCategory::Auth);
+    assert!(!prohibited.is_retryable());
+
+    let exhausted = GoogleAdsError::from_status(&status(
+        Code::ResourceExhausted,
+        vec![error(error_code::ErrorCode::QuotaError(
+            QuotaError::ResourceExhausted as i32,
+        ))],
+    ));
+    assert_eq!(exhausted.category(), ErrorCategory::Quota);
+    assert!(!exhausted.is_retryable());
+}
+
+#[test]
+fn test_only_temporary_quota_errors_are_retryable() {
+    // Daily quotas only reset with the quota window; temporary exhaustion clears soon
+    let cases = [
+        (QuotaError::ResourceExhausted, false),
+        (QuotaError::ResourceTemporarilyExhausted, true),
+        (QuotaError::AccessProhibited, false),
+    ];
+    for (code, retryable) in cases {
+        let error = GoogleAdsError::from_status(&status(
+            Code::ResourceExhausted,
+            vec![error(error_code::ErrorCode::QuotaError(code as i32))],
+        ));
+        assert_eq!(error.is_retryable(), retryable, "{:?}", code);
+        assert_eq!(is_transient(&error), retryable, "{:?}", code);
+    }
+    assert!(!GoogleAdsError::from_status(&Status::resource_exhausted("quota")).is_retryable());
+}
+
+#[test]
+fn test_most_severe_detail_wins() {
+    let error = GoogleAdsError::from_status(&status(
+        Code::InvalidArgument,
+        vec![
+            error(error_code::ErrorCode::QuotaError(
+                QuotaError::ResourceExhausted as i32,
+            )),
+            error(error_code::ErrorCode::RequestError(
+                RequestError::InvalidCustomerId as i32,
+            )),
+        ],
+    ));
+    assert_eq!(error.category(), ErrorCategory::InvalidRequest);
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_category_from_status_code() {
+    let cases = [
+        (Code::Unavailable, ErrorCategory::Retryable),
+        (Code::DeadlineExceeded, ErrorCategory::Retryable),
+        (Code::ResourceExhausted, ErrorCategory::Quota),
+        (Code::PermissionDenied, ErrorCategory::Auth),
+        (Code::InvalidArgument, ErrorCategory::InvalidRequest),
+        (Code::Cancelled, ErrorCategory::Unknown),
+    ];
+    for (code, category) in cases {
+        let error = GoogleAdsError::from(Status::new(code, "failed"));
+        assert_eq!(error.category(), category, "{:?}", code);
+    }
+}
+
+#[test]
+fn test_partial_failure() {
+    let failure = GoogleAdsFailure {
+        errors: vec![error(error_code::ErrorCode::RequestError(
+            RequestError::InvalidCustomerId as i32,
+        ))],
+        request_id: "partial-request-id".to_string(),
+    };
+    let partial_failure_error = googleads_rs::google::rpc::Status {
+        code: Code::InvalidArgument as i32,
+        message: "Multiple errors in 'details'.".to_string(),
+        details: vec![prost_types::Any {
+            type_url: format!("type.googleapis.com/{}", GOOGLE_ADS_FAILURE_FQN),
+            value: failure.encode_to_vec(),
+        }],
+    };
+    let error = GoogleAdsError::from_partial_failure(&partial_failure_error);
+
+    assert!(error.partial_failure);
+    assert_eq!(error.code, Code::InvalidArgument);
+    assert_eq!(error.request_id.as_deref(), Some("partial-request-id"));
+    assert_eq!(error.errors[0].category(), ErrorCategory::InvalidRequest);
+    assert_eq!(error.category(), ErrorCategory::PartialFailure);
+}
@@ -0,0 +1,221 @@
+// Tests for decoding GoogleAdsFailure from tonic::Status
+//
+// These tests verify that GoogleAdsError extracts the failure from the binary
+// trailer or the status details, the request id, and each error's code,
+// message, trigger and location.
+
+use bytes::Bytes;
+use googleads_rs::error::{error_code_names, GOOGLE_ADS_FAILURE_METADATA_KEY};
+use googleads_rs::google::ads::googleads::v23::common::{value, Value};
+use googleads_rs::google::ads::googleads::v23::errors::{
+    error_code, error_location::FieldPathElement, quota_error_enum::QuotaError,
+    request_error_enum::RequestError, ErrorCode, ErrorLocation,
+    GoogleAdsError as GoogleAdsErrorProto, GoogleAdsFailure,
+};
+use googleads_rs::GoogleAdsError;
+use prost::Message;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+
+fn quota_error() -> GoogleAdsErrorProto {
+    GoogleAdsErrorProto {
+        error_code: Some(ErrorCode {
+            error_code: Some(error_code::ErrorCode::QuotaError(
+                QuotaError::ResourceExhausted as i32,
+            )),
+        }),
+        message: "Too many requests.".to_string(),
+        ..Default::default()
+    }
+}
+
+fn request_error() -> GoogleAdsErrorProto {
+    GoogleAdsErrorProto {
+        error_code: Some(ErrorCode {
+            error_code: Some(error_code::ErrorCode::RequestError(
+                RequestError::InvalidCustomerId as i32,
+            )),
+        }),
+        message: "The customer id is invalid.".to_string(),
+        trigger: Some(Value {
+            value: Some(value::Value::StringValue("123".to_string())),
+        }),
+        location: Some(ErrorLocation {
+            field_path_elements: vec![
+                FieldPathElement {
+                    field_name: "operations".to_string(),
+                    index: Some(1),
+                },
+                FieldPathElement {
+                    field_name: "create".to_string(),
+                    index: None,
+                },
+                FieldPathElement {
+                    field_name: "name".to_string(),
+                    index: None,
+                },
+            ],
+        }),
+        ..Default::default()
+    }
+}
+
+fn failure(errors: Vec<GoogleAdsErrorProto>) -> GoogleAdsFailure {
+    GoogleAdsFailure {
+        errors,
+        request_id: "payload-request-id".to_string(),
+    }
+}
+
+fn status_with_trailer(code: Code, failure: &GoogleAdsFailure, request_id: &str) -> Status {
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(&failure.encode_to_vec()),
+    );
+    metadata.insert("request-id", request_id.parse().unwrap());
+    Status::with_metadata(code, "Request contains an invalid argument.", metadata)
+}
+
+// ============================================================================
+// Extraction
+// ============================================================================
+
+#[test]
+fn test_from_trailer() {
+    let status = status_with_trailer(
+        Code::InvalidArgument,
+        &failure(vec![request_error()]),
+        "header-request-id",
+    );
+    let error = GoogleAdsError::from_status(&status);
+
+    assert_eq!(error.code, Code::InvalidArgument);
+    assert_eq!(error.message, "Request contains an invalid argument.");
+    assert_eq!(error.request_id.as_deref(), Some("header-request-id"));
+    assert!(error.has_failure());
+    assert_eq!(error.errors.len(), 1);
+
+    let detail = &error.errors[0];
+    assert_eq!(detail.kind, "request_error");
+    assert_eq!(detail.code, "INVALID_CUSTOMER_ID");
+    assert_eq!(detail.error_code(), "request_error.INVALID_CUSTOMER_ID");
+    assert_eq!(detail.message, "The customer id is invalid.");
+    assert_eq!(detail.trigger.as_deref(), Some("'123'"));
+    assert_eq!(
+        detail.location.as_deref(),
+        Some("operations[1].create.name")
+    );
+    assert_eq!(detail.error, request_error());
+}
+
+#[test]
+fn test_from_status_details() {
+    let failure = failure(vec![quota_error(), request_error()]);
+    let rpc_status = googleads_rs::google::rpc::Status {
+        code: Code::ResourceExhausted as i32,
+        message: "Resource has been exhausted.".to_string(),
+        details: vec![prost_types::Any {
+            type_url: "type.googleapis.com/google.ads.googleads.v23.errors.GoogleAdsFailure"
+                .to_string(),
+            value: failure.encode_to_vec(),
+        }],
+    };
+    let status = Status::with_details(
+        Code::ResourceExhausted,
+        "Resource has been exhausted.",
+        Bytes::from(rpc_status.encode_to_vec()),
+    );
+    let error = GoogleAdsError::from_status(&status);
+
+    // No header, so the request id comes from the payload
+    assert_eq!(error.request_id.as_deref(), Some("payload-request-id"));
+    assert_eq!(
+        error
+            .errors
+            .iter()
+            .map(|e| e.error_code())
+            .collect::<Vec<_>>(),
+        vec![
+            "quota_error.RESOURCE_EXHAUSTED",
+            "request_error.INVALID_CUSTOMER_ID"
+        ]
+    );
+}
+
+#[test]
+fn test_status_without_failure() {
+    let error = GoogleAdsError::from(Status::unavailable("connection reset"));
+    assert_eq!(error.code, Code::Unavailable);
+    assert!(!error.has_failure());
+    assert!(error.errors.is_empty());
+    assert_eq!(error.request_id, None);
+    assert_eq!(
+        error.to_string(),
+        "Google Ads API error: Unavailable: connection reset"
+    );
+}
+
+#[test]
+fn test_undecodable_trailer_is_ignored() {
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(b"\xff\xff\xff"),
+    );
+    let status = Status::with_metadata(Code::Internal, "oops", metadata);
+    assert!(!GoogleAdsError::from_status(&status).has_failure());
+}
+
+// ============================================================================
+// Error Codes
+// ============================================================================
+
+#[test]
+fn test_error_code_names() {
+    let code = ErrorCode {
+        error_code: Some(error_code::ErrorCode::QuotaError(
+            QuotaError::ResourceTemporarilyExhausted as i32,
+        )),
+    };
+    assert_eq!(
+        error_code_names(&code),
+        Some((
+            "quota_error".to_string(),
+            "RESOURCE_TEMPORARILY_EXHAUSTED".to_string()
+        ))
+    );
+    assert_eq!(error_code_names(&ErrorCode::default()), None);
+}
+
+#[test]
+fn test_unknown_enum_number() {
+    let code = ErrorCode {
+        error_code: Some(error_code::ErrorCode::QuotaError(9999)),
+    };
+    assert_eq!(
+        error_code_names(&code),
+        Some(("quota_error".to_string(), "9999".to_string()))
+    );
+}
+
+// ============================================================================
+// Display
+// ============================================================================
+
+#[test]
+fn test_display() {
+    let status = status_with_trailer(
+        Code::InvalidArgument,
+        &failure(vec![quota_error(), request_error()]),
+        "abc123",
+    );
+    let error = GoogleAdsError::from_status(&status);
+    assert_eq!(
+        error.to_string(),
+        "Google Ads API error: InvalidArgument (request-id: abc123)\n  \
+         - quota_error.RESOURCE_EXHAUSTED: Too many requests.\n  \
+         - request_error.INVALID_CUSTOMER_ID: The customer id is invalid. \
+         (trigger: '123') (location: operations[1].create.name)"
+    );
+}
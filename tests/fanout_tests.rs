@@ -0,0 +1,188 @@
+// Tests for running one query across many accounts
+//
+// These tests verify that FanOut tags rows with their customer id, keeps going
+// when an account fails or times out, bounds concurrency, and reports progress.
+
+mod mock_transport;
+
+use futures::StreamExt;
+use googleads_rs::fanout::{FanOut, Progress};
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use mock_transport::{block_on, encode, MockTransport};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tonic::{Code, Status};
+
+const QUERY: &str = "SELECT campaign.id FROM campaign";
+
+fn campaign_row(id: i64) -> GoogleAdsRow {
+    GoogleAdsRow {
+        campaign: Some(Campaign {
+            id: Some(id),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+// Customer N answers with campaigns N*10+1..=N*10+N, split over one batch per row;
+// customer 9 fails
+fn accounts_transport() -> MockTransport {
+    MockTransport::new(|_path, body| {
+        let request: SearchGoogleAdsStreamRequest = prost::Message::decode(body).unwrap();
+        let n: i64 = request.customer_id.parse().unwrap();
+        if n == 9 {
+            return Err(Status::permission_denied("not linked"));
+        }
+        Ok((1..=n)
+            .map(|i| {
+                encode(&SearchGoogleAdsStreamResponse {
+                    results: vec![campaign_row(n * 10 + i)],
+                    ..Default::default()
+                })
+            })
+            .collect())
+    })
+}
+
+// ============================================================================
+// Merged stream
+// ============================================================================
+
+#[test]
+fn test_rows_are_tagged_with_customer_id() {
+    let transport = accounts_transport();
+    let client = GoogleAdsServiceClient::new(transport.clone());
+    let mut fanout = FanOut::new();
+    fanout.concurrency(2);
+
+    let result = block_on(async move { fanout.run(client, &["1", "2", "3"], QUERY).await });
+
+    assert!(result.errors.is_empty());
+    let mut by_customer: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for tagged in result.rows {
+        by_customer
+            .entry(tagged.customer_id)
+            .or_default()
+            .push(tagged.row.campaign.unwrap().id.unwrap());
+    }
+    assert_eq!(by_customer["1"], vec![11]);
+    // Rows of one account keep their order
+    assert_eq!(by_customer["3"], vec![31, 32, 33]);
+    assert_eq!(transport.calls().len(), 3);
+}
+
+#[test]
+fn test_customer_ids_are_normalized() {
+    let transport = MockTransport::new(|_path, _body| {
+        Ok(vec![encode(&SearchGoogleAdsStreamResponse {
+            results: vec![campaign_row(1)],
+            ..Default::default()
+        })])
+    });
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let result = block_on(async move { FanOut::new().run(client, &["123-456-7890"], QUERY).await });
+
+    assert_eq!(result.rows[0].customer_id, "1234567890");
+    let request: SearchGoogleAdsStreamRequest = transport.calls()[0].decode();
+    assert_eq!(request.customer_id, "1234567890");
+    assert_eq!(request.query, QUERY);
+}
+
+#[test]
+fn test_account_errors_do_not_abort_the_run() {
+    let client = GoogleAdsServiceClient::new(accounts_transport());
+    let fanout = FanOut::new();
+
+    let items: Vec<_> = block_on(async move {
+        fanout
+            .search_stream(client, &["2", "9", "1"], QUERY)
+            .collect::<Vec<_>>()
+            .await
+    });
+
+    let errors: Vec<_> = items.iter().filter_map(|i| i.as_ref().err()).collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].customer_id, "9");
+    assert_eq!(errors[0].error.code, Code::PermissionDenied);
+    assert!(errors[0].to_string().starts_with("Customer 9: "));
+    assert_eq!(items.iter().filter(|i| i.is_ok()).count(), 3);
+}
+
+#[test]
+fn test_per_account_timeout() {
+    let transport = accounts_transport().delayed(|_path, body| {
+        let request: SearchGoogleAdsStreamRequest = prost::Message::decode(body.clone()).unwrap();
+        (request.customer_id == "3").then(|| Duration::from_secs(5))
+    });
+    let client = GoogleAdsServiceClient::new(transport);
+    let mut fanout = FanOut::new();
+    fanout.timeout(Duration::from_millis(200));
+
+    let result = block_on(async move { fanout.run(client, &["1", "3", "2"], QUERY).await });
+
+    assert_eq!(result.rows.len(), 3);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].customer_id, "3");
+    assert_eq!(result.errors[0].error.code, Code::DeadlineExceeded);
+}
+
+#[test]
+fn test_concurrency_limit() {
+    let in_flight = Arc::new(Mutex::new((0usize, 0usize)));
+    let tracker = in_flight.clone();
+    let transport = accounts_transport().delayed(move |_path, _body| {
+        let mut counts = tracker.lock().unwrap();
+        counts.0 += 1;
+        counts.1 = counts.1.max(counts.0);
+        Some(Duration::from_millis(20))
+    });
+    let client = GoogleAdsServiceClient::new(transport.clone());
+    let mut fanout = FanOut::new();
+    fanout.concurrency(2);
+
+    // Count a call as finished when its account's rows have all been read
+    let finished = in_flight.clone();
+    fanout.on_progress(move |_| finished.lock().unwrap().0 -= 1);
+    let result =
+        block_on(async move { fanout.run(client, &["1", "2", "3", "4", "5"], QUERY).await });
+
+    assert_eq!(result.rows.len(), 15);
+    assert_eq!(in_flight.lock().unwrap().1, 2);
+}
+
+// ============================================================================
+// Progress
+// ============================================================================
+
+#[test]
+fn test_progress_callback() {
+    let reports: Arc<Mutex<Vec<Progress>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = reports.clone();
+    let client = GoogleAdsServiceClient::new(accounts_transport());
+    let mut fanout = FanOut::new();
+    fanout
+        .concurrency(1)
+        .on_progress(move |p| sink.lock().unwrap().push(p.clone()));
+
+    block_on(async move { fanout.run(client, &["2", "9", "3"], QUERY).await });
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 3);
+    assert_eq!(
+        reports.iter().map(|p| p.completed).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert!(reports.iter().all(|p| p.total == 3));
+    assert_eq!(reports[0].customer_id, "2");
+    assert_eq!(reports[0].rows, 2);
+    assert!(reports[1].failed);
+    assert_eq!(reports[2].failures, 1);
+    assert_eq!(reports[2].rows, 3);
+}
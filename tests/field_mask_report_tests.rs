@@ -0,0 +1,115 @@
+// Tests for verifying returned field masks against the requested SELECT list
+//
+// These tests verify that FieldMaskReport reports missing, extra and reordered
+// paths, and that values can be emitted in the requested column order.
+
+use googleads_rs::field_mask::FieldMaskReport;
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::GoogleAdsRow;
+use googleads_rs::Query;
+use prost_types::FieldMask;
+
+fn mask(paths: &[&str]) -> FieldMask {
+    FieldMask {
+        paths: paths.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+// ============================================================================
+// Comparison
+// ============================================================================
+
+#[test]
+fn test_exact_match() {
+    let report = FieldMaskReport::compare(
+        &["campaign.id", "campaign.name"],
+        &mask(&["campaign.id", "campaign.name"]),
+    );
+    assert!(report.is_exact());
+    assert!(report.is_complete());
+    assert!(!report.is_reordered());
+    assert!(report.missing.is_empty());
+    assert!(report.extra.is_empty());
+    assert_eq!(report.column_order, vec![Some(0), Some(1)]);
+}
+
+#[test]
+fn test_missing_paths() {
+    let report = FieldMaskReport::compare(
+        &["campaign.id", "campaign.name", "metrics.clicks"],
+        &mask(&["campaign.id", "metrics.clicks"]),
+    );
+    assert!(!report.is_complete());
+    assert_eq!(report.missing, vec!["campaign.name"]);
+    assert_eq!(report.column_order, vec![Some(0), None, Some(1)]);
+}
+
+#[test]
+fn test_extra_paths() {
+    let report = FieldMaskReport::compare(
+        &["campaign.name"],
+        &mask(&["campaign.resource_name", "campaign.name"]),
+    );
+    assert!(report.is_complete());
+    assert!(!report.is_exact());
+    assert_eq!(report.extra, vec!["campaign.resource_name"]);
+    assert_eq!(report.column_order, vec![Some(1)]);
+}
+
+#[test]
+fn test_reordered_paths() {
+    let report = FieldMaskReport::compare(
+        &["metrics.clicks", "campaign.id"],
+        &mask(&["campaign.id", "metrics.clicks"]),
+    );
+    assert!(report.is_complete());
+    assert!(report.is_reordered());
+    assert_eq!(report.column_order, vec![Some(1), Some(0)]);
+}
+
+#[test]
+fn test_empty_mask() {
+    let report = FieldMaskReport::compare(&["campaign.id"], &FieldMask::default());
+    assert_eq!(report.missing, vec!["campaign.id"]);
+    assert!(!report.is_reordered());
+}
+
+// ============================================================================
+// Column Order Mapping
+// ============================================================================
+
+#[test]
+fn test_reorder_values_to_requested_order() {
+    let report = FieldMaskReport::compare(
+        &["metrics.clicks", "campaign.name", "campaign.id"],
+        &mask(&["campaign.resource_name", "campaign.id", "metrics.clicks"]),
+    );
+    let values = vec![
+        "customers/1/campaigns/2".to_string(),
+        "2".to_string(),
+        "10".to_string(),
+    ];
+    assert_eq!(
+        report.reorder(&values),
+        vec!["10".to_string(), String::new(), "2".to_string()]
+    );
+}
+
+#[test]
+fn test_reorder_with_get_many() {
+    let row = GoogleAdsRow {
+        campaign: Some(Campaign {
+            id: Some(42),
+            name: Some("Brand".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let query = Query::parse("SELECT campaign.name, campaign.id FROM campaign").unwrap();
+    let report = query.check_field_mask(&mask(&["campaign.id", "campaign.name"]));
+
+    let in_mask_order = row.get_many(&report.returned_paths());
+    assert_eq!(in_mask_order, vec!["42", "Brand"]);
+    assert_eq!(report.reorder(&in_mask_order), vec!["Brand", "42"]);
+    assert_eq!(row.get_many(&report.requested_paths()), vec!["Brand", "42"]);
+}
@@ -0,0 +1,546 @@
+// Tests for GAQL parsing and parameter binding
+//
+// These tests verify that Query::parse() understands the GAQL grammar and that
+// Query::bind() escapes and type-checks values against the field descriptors.
+
+use chrono::NaiveDate;
+use googleads_rs::gaql::{Literal, Operand, Operator, Ordering};
+use googleads_rs::{Query, QueryValue};
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+#[test]
+fn test_parse_select_from() {
+    let query = Query::parse("SELECT campaign.id, campaign.name FROM campaign").unwrap();
+    assert_eq!(query.fields, vec!["campaign.id", "campaign.name"]);
+    assert_eq!(query.resource, "campaign");
+    assert!(query.conditions.is_empty());
+    assert!(query.order_by.is_empty());
+    assert_eq!(query.limit, None);
+}
+
+#[test]
+fn test_parse_keywords_case_insensitive() {
+    let query =
+        Query::parse("select campaign.id from campaign where campaign.id > 5 limit 10").unwrap();
+    assert_eq!(query.resource, "campaign");
+    assert_eq!(query.conditions[0].operator, Operator::Gt);
+    assert_eq!(query.limit, Some(10));
+}
+
+#[test]
+fn test_parse_full_query() {
+    let query = Query::parse(
+        "SELECT campaign.id, metrics.clicks FROM campaign \
+         WHERE campaign.status IN ('ENABLED', 'PAUSED') \
+         AND segments.date DURING LAST_30_DAYS \
+         AND campaign.name LIKE '%brand%' \
+         ORDER BY metrics.clicks DESC, campaign.id \
+         LIMIT 50 \
+         PARAMETERS include_drafts = true",
+    )
+    .unwrap();
+
+    assert_eq!(query.conditions.len(), 3);
+    assert_eq!(
+        query.conditions[0].operand,
+        Operand::List(vec![
+            Literal::String("ENABLED".to_string()),
+            Literal::String("PAUSED".to_string())
+        ])
+    );
+    assert_eq!(query.conditions[1].operator, Operator::During);
+    assert_eq!(
+        query.conditions[1].operand,
+        Operand::Value(Literal::Keyword("LAST_30_DAYS".to_string()))
+    );
+    assert_eq!(
+        query.order_by,
+        vec![
+            Ordering {
+                field: "metrics.clicks".to_string(),
+                descending: true
+            },
+            Ordering {
+                field: "campaign.id".to_string(),
+                descending: false
+            },
+        ]
+    );
+    assert_eq!(query.limit, Some(50));
+    assert_eq!(
+        query.parameters,
+        vec![(
+            "include_drafts".to_string(),
+            Literal::Keyword("true".to_string())
+        )]
+    );
+}
+
+#[test]
+fn test_parse_multi_word_operators() {
+    let query = Query::parse(
+        "SELECT campaign.id FROM campaign \
+         WHERE campaign.id NOT IN (1, 2) \
+         AND campaign.name NOT LIKE 'x%' \
+         AND campaign.labels CONTAINS ANY ('customers/1/labels/2') \
+         AND campaign.end_date IS NOT NULL \
+         AND campaign.start_date IS NULL \
+         AND campaign.name NOT REGEXP_MATCH '.*test.*'",
+    )
+    .unwrap();
+
+    let ops: Vec<Operator> = query.conditions.iter().map(|c| c.operator).collect();
+    assert_eq!(
+        ops,
+        vec![
+            Operator::NotIn,
+            Operator::NotLike,
+            Operator::ContainsAny,
+            Operator::IsNotNull,
+            Operator::IsNull,
+            Operator::NotRegexpMatch,
+        ]
+    );
+    assert_eq!(query.conditions[3].operand, Operand::None);
+}
+
+#[test]
+fn test_parse_between() {
+    let query = Query::parse(
+        "SELECT segments.date FROM campaign \
+         WHERE segments.date BETWEEN '2024-01-01' AND '2024-01-31' AND campaign.id = 1",
+    )
+    .unwrap();
+    assert_eq!(query.conditions.len(), 2);
+    assert_eq!(
+        query.conditions[0].operand,
+        Operand::Range(
+            Literal::String("2024-01-01".to_string()),
+            Literal::String("2024-01-31".to_string())
+        )
+    );
+}
+
+#[test]
+fn test_parse_escaped_string_literals() {
+    let query =
+        Query::parse(r#"SELECT campaign.id FROM campaign WHERE campaign.name = 'Joe\'s "Sale"'"#)
+            .unwrap();
+    assert_eq!(
+        query.conditions[0].operand,
+        Operand::Value(Literal::String("Joe's \"Sale\"".to_string()))
+    );
+
+    let query =
+        Query::parse(r#"SELECT campaign.id FROM campaign WHERE campaign.name = "Joe's""#).unwrap();
+    assert_eq!(
+        query.conditions[0].operand,
+        Operand::Value(Literal::String("Joe's".to_string()))
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(Query::parse("").is_err());
+    assert!(Query::parse("SELECT FROM campaign").is_err());
+    assert!(Query::parse("SELECT campaign.id").is_err());
+    assert!(Query::parse("SELECT campaign.id FROM campaign WHERE").is_err());
+    assert!(Query::parse("SELECT campaign.id FROM campaign WHERE campaign.name = 'x").is_err());
+    assert!(Query::parse("SELECT campaign.id FROM campaign WHERE campaign.id FOO 1").is_err());
+    assert!(Query::parse("SELECT campaign.id FROM campaign LIMIT x").is_err());
+    assert!(Query::parse("SELECT campaign.id FROM campaign campaign").is_err());
+}
+
+#[test]
+fn test_parse_placeholders() {
+    let query = Query::parse(
+        "SELECT campaign.id FROM campaign \
+         WHERE campaign.name = :name AND segments.date BETWEEN :from AND :to \
+         AND campaign.id IN :ids",
+    )
+    .unwrap();
+    assert_eq!(query.placeholders(), vec!["name", "from", "to", "ids"]);
+}
+
+#[test]
+fn test_display_round_trip() {
+    let gaql = "SELECT campaign.id, campaign.name FROM campaign WHERE campaign.status = 'ENABLED' \
+                AND campaign.id IN (1, 2) ORDER BY campaign.id DESC LIMIT 5";
+    let query = Query::parse(gaql).unwrap();
+    let rendered = query.to_string();
+    assert_eq!(Query::parse(&rendered).unwrap(), query);
+}
+
+// ============================================================================
+// Binding
+// ============================================================================
+
+#[test]
+fn test_bind_string_escapes_quotes() {
+    let gaql = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.name = :name")
+        .unwrap()
+        .bind("name", r"Joe's \ Sale")
+        .unwrap()
+        .to_gaql()
+        .unwrap();
+    assert_eq!(
+        gaql,
+        r"SELECT campaign.id FROM campaign WHERE campaign.name = 'Joe\'s \\ Sale'"
+    );
+
+    // Escaped output parses back to the original value
+    let reparsed = Query::parse(&gaql).unwrap();
+    assert_eq!(
+        reparsed.conditions[0].operand,
+        Operand::Value(Literal::String(r"Joe's \ Sale".to_string()))
+    );
+}
+
+#[test]
+fn test_bind_dates() {
+    let gaql = Query::parse(
+        "SELECT metrics.clicks FROM campaign WHERE segments.date BETWEEN :from AND :to",
+    )
+    .unwrap()
+    .bind("from", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap())
+    .unwrap()
+    .bind("to", "2024-02-29")
+    .unwrap()
+    .to_gaql()
+    .unwrap();
+    assert!(gaql.ends_with("WHERE segments.date BETWEEN '2024-01-05' AND '2024-02-29'"));
+}
+
+#[test]
+fn test_bind_invalid_date_string_rejected() {
+    let result = Query::parse("SELECT metrics.clicks FROM campaign WHERE segments.date = :d")
+        .unwrap()
+        .bind("d", "last tuesday");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_bind_date_string_with_trailing_text_rejected() {
+    let query =
+        Query::parse("SELECT metrics.clicks FROM campaign WHERE segments.date = :d").unwrap();
+    assert!(query.clone().bind("d", "2024-01-01garbage").is_err());
+    assert!(query.clone().bind("d", "2024-01-01; DROP").is_err());
+    assert!(query.clone().bind("d", "2024-01-01 12:30:00 x").is_err());
+
+    let gaql = query
+        .bind("d", "2024-01-01 12:30:00")
+        .unwrap()
+        .to_gaql()
+        .unwrap();
+    assert!(gaql.ends_with("segments.date = '2024-01-01 12:30:00'"));
+}
+
+#[test]
+fn test_bind_date_to_non_date_field_rejected() {
+    let result = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.name = :d")
+        .unwrap()
+        .bind("d", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap());
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_bind_enum_by_name_and_number() {
+    let query =
+        Query::parse("SELECT campaign.id FROM campaign WHERE campaign.status = :status").unwrap();
+
+    let by_name = query
+        .clone()
+        .bind("status", QueryValue::enum_value("PAUSED"))
+        .unwrap();
+    assert!(by_name
+        .to_gaql()
+        .unwrap()
+        .ends_with("campaign.status = PAUSED"));
+
+    // Enum numbers are resolved to names through the enum descriptor
+    let by_number = query.clone().bind("status", 2).unwrap();
+    assert!(by_number
+        .to_gaql()
+        .unwrap()
+        .ends_with("campaign.status = ENABLED"));
+
+    assert!(query.bind("status", "NOT_A_STATUS").is_err());
+}
+
+#[test]
+fn test_bind_list_for_in() {
+    let gaql = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.id IN :ids")
+        .unwrap()
+        .bind("ids", vec![1i64, 2, 3])
+        .unwrap()
+        .to_gaql()
+        .unwrap();
+    assert!(gaql.ends_with("WHERE campaign.id IN (1, 2, 3)"));
+
+    let gaql =
+        Query::parse("SELECT campaign.id FROM campaign WHERE campaign.status NOT IN :statuses")
+            .unwrap()
+            .bind("statuses", vec!["REMOVED", "PAUSED"])
+            .unwrap()
+            .to_gaql()
+            .unwrap();
+    assert!(gaql.ends_with("WHERE campaign.status NOT IN (REMOVED, PAUSED)"));
+}
+
+#[test]
+fn test_bind_placeholder_inside_list() {
+    let gaql = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.name IN (:a, 'b')")
+        .unwrap()
+        .bind("a", "it's")
+        .unwrap()
+        .to_gaql()
+        .unwrap();
+    assert!(gaql.ends_with(r"WHERE campaign.name IN ('it\'s', 'b')"));
+}
+
+#[test]
+fn test_bind_list_rejected_for_scalar_operator() {
+    let result = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.id = :ids")
+        .unwrap()
+        .bind("ids", vec![1i64, 2]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_bind_empty_list_rejected_for_in() {
+    for operator in ["IN", "NOT IN"] {
+        let gaql = format!(
+            "SELECT campaign.id FROM campaign WHERE campaign.id {} :ids",
+            operator
+        );
+        let err = Query::parse(&gaql)
+            .unwrap()
+            .bind("ids", Vec::<i64>::new())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Operator {} on 'campaign.id' requires a non-empty list",
+                operator
+            )
+        );
+    }
+}
+
+#[test]
+fn test_bind_scalar_rejected_for_in() {
+    let result = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.id IN :ids")
+        .unwrap()
+        .bind("ids", 1i64);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_bind_type_mismatch() {
+    let query = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.id = :v").unwrap();
+    assert!(query.clone().bind("v", "123").is_err());
+    assert!(query.clone().bind("v", true).is_err());
+    assert!(query.bind("v", 123i64).is_ok());
+
+    let query = Query::parse("SELECT metrics.ctr FROM campaign WHERE metrics.ctr > :v").unwrap();
+    assert!(query.clone().bind("v", 0.05).is_ok());
+    assert!(query.bind("v", "0.05").is_err());
+}
+
+#[test]
+fn test_bind_bool() {
+    let gaql = Query::parse(
+        "SELECT customer_client.id FROM customer_client WHERE customer_client.manager = :m",
+    )
+    .unwrap()
+    .bind("m", false)
+    .unwrap()
+    .to_gaql()
+    .unwrap();
+    assert!(gaql.ends_with("customer_client.manager = FALSE"));
+}
+
+#[test]
+fn test_bind_during() {
+    let query =
+        Query::parse("SELECT metrics.clicks FROM campaign WHERE segments.date DURING :range")
+            .unwrap();
+    let gaql = query
+        .clone()
+        .bind("range", "last_7_days")
+        .unwrap()
+        .to_gaql()
+        .unwrap();
+    assert!(gaql.ends_with("segments.date DURING LAST_7_DAYS"));
+    assert!(query.bind("range", "LAST_3_YEARS").is_err());
+}
+
+#[test]
+fn test_bind_unknown_placeholder() {
+    let result = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.id = :id")
+        .unwrap()
+        .bind("other", 1i64);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_bind_unknown_field() {
+    let result = Query::parse("SELECT campaign.id FROM campaign WHERE campaign.nope = :v")
+        .unwrap()
+        .bind("v", 1i64);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_to_gaql_fails_with_unbound_placeholders() {
+    let query = Query::parse(
+        "SELECT campaign.id FROM campaign WHERE campaign.id = :id AND campaign.name = :name",
+    )
+    .unwrap()
+    .bind("id", 1i64)
+    .unwrap();
+    let err = query.to_gaql().unwrap_err().to_string();
+    assert!(err.contains(":name"));
+    assert!(!err.contains(":id"));
+}
+
+#[test]
+fn test_bind_same_placeholder_twice_in_query() {
+    let gaql = Query::parse(
+        "SELECT campaign.id FROM campaign WHERE campaign.name = :n AND campaign.name != :n",
+    )
+    .unwrap()
+    .bind("n", "x")
+    .unwrap()
+    .to_gaql()
+    .unwrap();
+    assert!(gaql.ends_with("campaign.name = 'x' AND campaign.name != 'x'"));
+}
+
+// ============================================================================
+// Formatting
+// ============================================================================
+
+#[test]
+fn test_format_pretty_prints_one_field_per_line() {
+    let query = Query::parse(
+        "select campaign.id,campaign.name from campaign where campaign.status='ENABLED' \
+         and segments.date during last_7_days order by campaign.id limit 10",
+    )
+    .unwrap();
+    assert_eq!(
+        query.format(),
+        "SELECT\n  \
+           campaign.id,\n  \
+           campaign.name\n\
+         FROM campaign\n\
+         WHERE campaign.status = 'ENABLED'\n  \
+           AND segments.date DURING last_7_days\n\
+         ORDER BY campaign.id ASC\n\
+         LIMIT 10"
+    );
+}
+
+#[test]
+fn test_format_with_sorted_fields() {
+    let query = Query::parse("SELECT metrics.clicks, campaign.id FROM campaign").unwrap();
+    let options = googleads_rs::gaql::FormatOptions { sort_fields: true };
+    assert_eq!(
+        query.format_with(&options),
+        "SELECT\n  campaign.id,\n  metrics.clicks\nFROM campaign"
+    );
+    assert_eq!(
+        query.format(),
+        "SELECT\n  metrics.clicks,\n  campaign.id\nFROM campaign"
+    );
+}
+
+#[test]
+fn test_format_output_parses_back() {
+    let query = Query::parse(
+        "SELECT campaign.id FROM campaign WHERE campaign.name = \"it's\" \
+         AND campaign.id IN (1,2) PARAMETERS include_drafts=true",
+    )
+    .unwrap();
+    assert_eq!(Query::parse(&query.format()).unwrap(), query);
+}
+
+#[test]
+fn test_format_gaql_string() {
+    let formatted = googleads_rs::gaql::format_gaql("SELECT campaign.id FROM campaign").unwrap();
+    assert_eq!(formatted, "SELECT\n  campaign.id\nFROM campaign");
+    assert!(googleads_rs::gaql::format_gaql("SELECT").is_err());
+}
+
+// ============================================================================
+// Normalization
+// ============================================================================
+
+#[test]
+fn test_normalize_equivalent_queries_share_key() {
+    let a = Query::parse(
+        "SELECT campaign.name, campaign.id FROM campaign \
+         WHERE segments.date DURING last_7_days AND campaign.status = 'ENABLED'",
+    )
+    .unwrap();
+    let b = Query::parse(
+        "select campaign.id,\n  campaign.name\nfrom campaign\n\
+         where campaign.status = ENABLED and segments.date DURING LAST_7_DAYS",
+    )
+    .unwrap();
+    assert_eq!(a.normalize(), b.normalize());
+    assert_eq!(
+        a.normalize(),
+        "SELECT campaign.id, campaign.name FROM campaign \
+         WHERE campaign.status = ENABLED AND segments.date DURING LAST_7_DAYS"
+    );
+}
+
+#[test]
+fn test_normalize_sorts_lists_and_numbers() {
+    let a = Query::parse(
+        "SELECT campaign.id FROM campaign WHERE campaign.id IN (3, 01, 2) AND metrics.ctr > 0.50",
+    )
+    .unwrap();
+    let b = Query::parse(
+        "SELECT campaign.id FROM campaign WHERE metrics.ctr > 0.5 AND campaign.id IN (1, 2, 3)",
+    )
+    .unwrap();
+    assert_eq!(a.normalize(), b.normalize());
+}
+
+#[test]
+fn test_normalize_quote_styles() {
+    let a = normalize(r#"SELECT campaign.id FROM campaign WHERE campaign.name = "Brand""#);
+    let b = normalize("SELECT campaign.id FROM campaign WHERE campaign.name = 'Brand'");
+    assert_eq!(a, b);
+}
+
+#[test]
+fn test_normalize_keeps_order_by_and_string_case() {
+    let a = normalize("SELECT campaign.id FROM campaign ORDER BY campaign.id, campaign.name");
+    let b = normalize("SELECT campaign.id FROM campaign ORDER BY campaign.name, campaign.id");
+    assert_ne!(a, b);
+
+    let a = normalize("SELECT campaign.id FROM campaign WHERE campaign.name = 'brand'");
+    let b = normalize("SELECT campaign.id FROM campaign WHERE campaign.name = 'BRAND'");
+    assert_ne!(a, b);
+}
+
+#[test]
+fn test_normalize_parameters() {
+    let a = normalize(
+        "SELECT campaign.id FROM campaign PARAMETERS omit_unselected_resource_names = true, include_drafts = false",
+    );
+    let b = normalize(
+        "SELECT campaign.id FROM campaign PARAMETERS include_drafts = FALSE, omit_unselected_resource_names = TRUE",
+    );
+    assert_eq!(a, b);
+}
+
+fn normalize(gaql: &str) -> String {
+    googleads_rs::gaql::normalize_gaql(gaql).unwrap()
+}
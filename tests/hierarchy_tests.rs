@@ -0,0 +1,182 @@
+// Tests for discovering the account hierarchy under a manager
+//
+// These tests verify that customer_client is queried recursively through
+// sub-managers, that the tree records parent links and login-customer-id
+// paths, that cancelled and hidden accounts can be filtered, and that the
+// hierarchy round-trips through JSON.
+
+mod mock_transport;
+
+use googleads_rs::google::ads::googleads::v23::enums::customer_status_enum::CustomerStatus;
+use googleads_rs::google::ads::googleads::v23::resources::CustomerClient;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::hierarchy::{
+    discover_hierarchy, AccountHierarchy, HierarchyDiscovery, CUSTOMER_CLIENT_QUERY,
+};
+use mock_transport::{block_on, encode, MockTransport};
+use tonic::Status;
+
+fn client_row(
+    id: i64,
+    level: i64,
+    manager: bool,
+    status: CustomerStatus,
+    hidden: bool,
+) -> GoogleAdsRow {
+    GoogleAdsRow {
+        customer_client: Some(CustomerClient {
+            client_customer: Some(format!("customers/{}", id)),
+            id: Some(id),
+            level: Some(level),
+            manager: Some(manager),
+            hidden: Some(hidden),
+            status: status as i32,
+            descriptive_name: Some(format!("Account {}", id)),
+            currency_code: Some("USD".to_string()),
+            time_zone: Some("America/New_York".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+// 1 (manager)
+// ├── 2 (manager)
+// │   ├── 6
+// │   └── 7
+// ├── 3
+// ├── 4 (cancelled)
+// └── 5 (hidden manager)
+//     └── 8
+fn hierarchy_transport() -> MockTransport {
+    use CustomerStatus::{Canceled, Enabled};
+    MockTransport::new(|_path, body| {
+        let request: SearchGoogleAdsStreamRequest = prost::Message::decode(body).unwrap();
+        let rows = match request.customer_id.as_str() {
+            "1" => vec![
+                client_row(1, 0, true, Enabled, false),
+                client_row(2, 1, true, Enabled, false),
+                client_row(3, 1, false, Enabled, false),
+                client_row(4, 1, false, Canceled, false),
+                client_row(5, 1, true, Enabled, true),
+            ],
+            "2" => vec![
+                client_row(2, 0, true, Enabled, false),
+                client_row(6, 1, false, Enabled, false),
+                client_row(7, 1, false, Enabled, false),
+            ],
+            "5" => vec![
+                client_row(5, 0, true, Enabled, true),
+                client_row(8, 1, false, Enabled, false),
+            ],
+            other => return Err(Status::permission_denied(format!("no access to {}", other))),
+        };
+        Ok(vec![encode(&SearchGoogleAdsStreamResponse {
+            results: rows,
+            ..Default::default()
+        })])
+    })
+}
+
+// ============================================================================
+// Discovery
+// ============================================================================
+
+#[test]
+fn test_discovers_tree_recursively() {
+    let transport = hierarchy_transport();
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let hierarchy = block_on(async move { discover_hierarchy(client, "1").await }).unwrap();
+
+    let root = &hierarchy.root;
+    assert_eq!(root.customer_id, "1");
+    assert_eq!(root.depth, 0);
+    assert!(root.path.is_empty());
+    assert_eq!(root.parent_id(), None);
+    assert_eq!(
+        root.children
+            .iter()
+            .map(|c| c.customer_id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["2", "3", "4", "5"]
+    );
+
+    let leaf = hierarchy.find("7").unwrap();
+    assert_eq!(leaf.depth, 2);
+    assert_eq!(leaf.path, vec!["1", "2"]);
+    assert_eq!(leaf.parent_id(), Some("2"));
+    assert_eq!(leaf.login_customer_id(), "1");
+    assert_eq!(leaf.descriptive_name, "Account 7");
+    assert_eq!(leaf.status, "ENABLED");
+    assert_eq!(hierarchy.find("4").unwrap().status, "CANCELED");
+
+    // Depth-first order
+    assert_eq!(
+        hierarchy
+            .accounts()
+            .map(|n| n.customer_id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["1", "2", "6", "7", "3", "4", "5", "8"]
+    );
+    assert_eq!(
+        hierarchy.client_customer_ids(),
+        vec!["6", "7", "3", "4", "8"]
+    );
+
+    // One query per manager
+    let calls = transport.calls();
+    assert_eq!(calls.len(), 3);
+    let request: SearchGoogleAdsStreamRequest = calls[0].decode();
+    assert_eq!(request.query, CUSTOMER_CLIENT_QUERY);
+}
+
+#[test]
+fn test_filters_cancelled_and_hidden_accounts() {
+    let transport = hierarchy_transport();
+    let client = GoogleAdsServiceClient::new(transport.clone());
+    let mut discovery = HierarchyDiscovery::new();
+    discovery.exclude_cancelled(true).exclude_hidden(true);
+
+    let hierarchy = block_on(async move { discovery.discover(client, "1").await }).unwrap();
+
+    assert_eq!(hierarchy.client_customer_ids(), vec!["6", "7", "3"]);
+    assert!(hierarchy.find("8").is_none());
+    // The hidden manager is not queried
+    assert_eq!(transport.calls().len(), 2);
+}
+
+#[test]
+fn test_query_failure_is_reported() {
+    let client = GoogleAdsServiceClient::new(hierarchy_transport());
+
+    let err = block_on(async move { discover_hierarchy(client, "999").await }).unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.contains("Failed to list clients of 999"),
+        "{}",
+        message
+    );
+    assert!(message.contains("PermissionDenied"), "{}", message);
+}
+
+// ============================================================================
+// Serialization
+// ============================================================================
+
+#[test]
+fn test_json_round_trip() {
+    let client = GoogleAdsServiceClient::new(hierarchy_transport());
+    let hierarchy = block_on(async move { discover_hierarchy(client, "1").await }).unwrap();
+
+    let json = hierarchy.to_json().unwrap();
+    assert!(json.contains("\"customer_id\": \"6\""));
+    assert!(json.contains("\"path\""));
+
+    let cached = AccountHierarchy::from_json(&json).unwrap();
+    assert_eq!(cached, hierarchy);
+    assert!(AccountHierarchy::from_json("{").is_err());
+}
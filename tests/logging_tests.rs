@@ -0,0 +1,295 @@
+// Tests for request/response logging with redaction
+//
+// These tests verify that LoggingLayer logs one line per call with method,
+// customer id, request id, status and latency, renders request and response
+// messages at debug level, and never logs redacted headers or fields.
+
+mod mock_transport;
+
+use googleads_rs::descriptor_pool;
+#[cfg(feature = "audiences")]
+use googleads_rs::google::ads::googleads::v23::common::{
+    user_identifier, UserData, UserIdentifier,
+};
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsRequest,
+    SearchGoogleAdsResponse,
+};
+#[cfg(feature = "audiences")]
+use googleads_rs::google::ads::googleads::v23::services::{
+    offline_user_data_job_operation,
+    offline_user_data_job_service_client::OfflineUserDataJobServiceClient,
+    AddOfflineUserDataJobOperationsRequest, AddOfflineUserDataJobOperationsResponse,
+    OfflineUserDataJobOperation,
+};
+use googleads_rs::logging::{method_name, LoggingLayer, REDACTED};
+use googleads_rs::GoogleAdsInterceptor;
+use log::{Level, Log, Metadata, Record};
+use mock_transport::{block_on, encode, MockTransport};
+use prost::Message;
+use std::sync::{Mutex, Once};
+use tonic::Status;
+use tower::Layer;
+
+// Captures every record; tests share it, so each one filters on its own customer id
+struct CaptureLogger;
+
+static RECORDS: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+static INIT: Once = Once::new();
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        RECORDS
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+fn capture() {
+    INIT.call_once(|| {
+        log::set_logger(&CaptureLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+    });
+}
+
+fn records_with(needle: &str) -> Vec<(Level, String)> {
+    RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, line)| line.contains(needle))
+        .cloned()
+        .collect()
+}
+
+fn interceptor(login_customer_id: &str) -> GoogleAdsInterceptor {
+    GoogleAdsInterceptor::new("SECRET-DEV-TOKEN")
+        .unwrap()
+        .with_login_customer_id(login_customer_id)
+        .unwrap()
+        .with_access_token("SECRET-ACCESS-TOKEN")
+        .unwrap()
+}
+
+#[cfg(feature = "audiences")]
+fn hashed_user(hash: &str) -> OfflineUserDataJobOperation {
+    OfflineUserDataJobOperation {
+        operation: Some(offline_user_data_job_operation::Operation::Create(
+            UserData {
+                user_identifiers: vec![UserIdentifier {
+                    identifier: Some(user_identifier::Identifier::HashedEmail(hash.to_string())),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )),
+    }
+}
+
+// ============================================================================
+// Call logging
+// ============================================================================
+
+#[test]
+fn test_logs_successful_call() {
+    capture();
+    let transport = MockTransport::new(|_path, _body| {
+        Ok(vec![encode(&SearchGoogleAdsResponse {
+            results: vec![GoogleAdsRow {
+                campaign: Some(Campaign {
+                    resource_name: "customers/1010101010/campaigns/77".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })])
+    })
+    .response_header("request-id", "req-ok");
+    let channel = LoggingLayer::new().layer(transport);
+    let mut client = GoogleAdsServiceClient::with_interceptor(channel, interceptor("1010101010"));
+
+    block_on(async move {
+        client
+            .search(SearchGoogleAdsRequest {
+                customer_id: "1010101010".to_string(),
+                query: "SELECT campaign.id FROM campaign".to_string(),
+                ..Default::default()
+            })
+            .await
+    })
+    .unwrap();
+
+    let records = records_with("1010101010");
+    let summary: Vec<_> = records.iter().filter(|(l, _)| *l == Level::Info).collect();
+    assert_eq!(summary.len(), 1);
+    let line = &summary[0].1;
+    assert!(
+        line.starts_with(
+            "GoogleAdsService/Search customer_id=1010101010 request_id=req-ok status=Ok latency_ms="
+        ),
+        "{}",
+        line
+    );
+    assert!(line.ends_with("messages=1"), "{}", line);
+
+    let debug: Vec<&String> = records
+        .iter()
+        .filter(|(l, _)| *l == Level::Debug)
+        .map(|(_, line)| line)
+        .collect();
+    assert!(debug
+        .iter()
+        .any(|l| l.starts_with("GoogleAdsService/Search request:")
+            && l.contains("query:\"SELECT campaign.id FROM campaign\"")));
+    assert!(debug
+        .iter()
+        .any(|l| l.starts_with("GoogleAdsService/Search response:")
+            && l.contains("customers/1010101010/campaigns/77")));
+    assert!(debug
+        .iter()
+        .any(|l| l.contains(&format!("developer-token: {}", REDACTED))
+            && l.contains(&format!("authorization: {}", REDACTED))));
+    assert!(records.iter().all(|(_, l)| !l.contains("SECRET")));
+}
+
+#[test]
+fn test_logs_failed_call_as_warning() {
+    capture();
+    let transport = MockTransport::new(|_path, _body| Err(Status::invalid_argument("bad query")))
+        .response_header("request-id", "req-failed");
+    let mut client = GoogleAdsServiceClient::new(LoggingLayer::new().layer(transport));
+
+    let result = block_on(async move {
+        client
+            .search(SearchGoogleAdsRequest {
+                customer_id: "2020202020".to_string(),
+                ..Default::default()
+            })
+            .await
+    });
+    assert!(result.is_err());
+
+    let warnings: Vec<_> = records_with("customer_id=2020202020");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].0, Level::Warn);
+    assert!(
+        warnings[0]
+            .1
+            .contains("request_id=req-failed status=InvalidArgument"),
+        "{}",
+        warnings[0].1
+    );
+    assert!(warnings[0].1.ends_with("bad query"), "{}", warnings[0].1);
+}
+
+// ============================================================================
+// Redaction
+// ============================================================================
+
+#[test]
+#[cfg(feature = "audiences")]
+fn test_user_identifiers_are_redacted() {
+    capture();
+    let transport = MockTransport::new(|_path, _body| {
+        Ok(vec![encode(
+            &AddOfflineUserDataJobOperationsResponse::default(),
+        )])
+    });
+    let channel = LoggingLayer::new().layer(transport.clone());
+    let mut client = OfflineUserDataJobServiceClient::new(channel);
+
+    block_on(async move {
+        client
+            .add_offline_user_data_job_operations(AddOfflineUserDataJobOperationsRequest {
+                resource_name: "customers/3030303030/offlineUserDataJobs/1".to_string(),
+                operations: vec![hashed_user("a1b2c3d4hash"), hashed_user("e5f6hash")],
+                ..Default::default()
+            })
+            .await
+    })
+    .unwrap();
+
+    let records = records_with("3030303030");
+    assert!(records.iter().any(|(level, line)| *level == Level::Info
+        && line.starts_with("OfflineUserDataJobService/AddOfflineUserDataJobOperations")));
+    let request = records
+        .iter()
+        .find(|(_, line)| line.contains(" request:"))
+        .map(|(_, line)| line)
+        .unwrap();
+    assert!(!request.contains("hash"), "{}", request);
+    // Both identifiers are still there, emptied
+    assert_eq!(
+        request.matches("user_identifiers").count(),
+        2,
+        "{}",
+        request
+    );
+    // The request itself is sent unchanged
+    let sent: AddOfflineUserDataJobOperationsRequest = transport.calls()[0].decode();
+    assert_eq!(sent.operations[0], hashed_user("a1b2c3d4hash"));
+}
+
+#[test]
+fn test_configurable_field_redaction() {
+    let descriptor = descriptor_pool()
+        .get_message_by_name("google.ads.googleads.v23.services.SearchGoogleAdsRequest")
+        .unwrap();
+    let request = SearchGoogleAdsRequest {
+        customer_id: "1234567890".to_string(),
+        query: "SELECT campaign.id FROM campaign".to_string(),
+        ..Default::default()
+    };
+    let bytes = bytes::Bytes::from(request.encode_to_vec());
+
+    let mut logging = LoggingLayer::new();
+    logging.redact_field("google.ads.googleads.v23.services.SearchGoogleAdsRequest.query");
+    let rendered = logging.render_message(&descriptor, bytes.clone());
+    assert!(rendered.contains("query:\"[REDACTED]\""), "{}", rendered);
+    assert!(
+        rendered.contains("customer_id:\"1234567890\""),
+        "{}",
+        rendered
+    );
+
+    let mut logging = LoggingLayer::new();
+    logging.redact_field("customer_id");
+    let rendered = logging.render_message(&descriptor, bytes);
+    assert!(!rendered.contains("1234567890"), "{}", rendered);
+    assert!(rendered.contains("SELECT campaign.id"), "{}", rendered);
+}
+
+#[test]
+#[cfg(feature = "audiences")]
+fn test_clear_redactions() {
+    let descriptor = descriptor_pool()
+        .get_message_by_name("google.ads.googleads.v23.services.OfflineUserDataJobOperation")
+        .unwrap();
+    let bytes = bytes::Bytes::from(hashed_user("cleartexthash").encode_to_vec());
+
+    let redacted = LoggingLayer::new().render_message(&descriptor, bytes.clone());
+    assert!(!redacted.contains("cleartexthash"), "{}", redacted);
+
+    let mut logging = LoggingLayer::new();
+    logging.clear_redactions();
+    let rendered = logging.render_message(&descriptor, bytes);
+    assert!(rendered.contains("cleartexthash"), "{}", rendered);
+}
+
+#[test]
+fn test_method_name() {
+    assert_eq!(
+        method_name("/google.ads.googleads.v23.services.GoogleAdsService/SearchStream"),
+        "GoogleAdsService/SearchStream"
+    );
+    assert_eq!(method_name("/Service/Method"), "Service/Method");
+}
@@ -0,0 +1,214 @@
+// Mock gRPC transport for exercising generated clients without a network
+//
+// MockTransport implements the tower Service that tonic clients are generic over.
+// Each call is answered by a handler that receives the gRPC method path and the
+// decoded request message bytes, and returns either a list of response messages
+// (sent as one framed stream) or a Status (sent as a trailers-only response).
+// MockTransport::streaming() handlers also return the status sent in the trailers
+// after the messages, to simulate a stream that breaks mid-way, delayed()
+// makes chosen calls wait before answering, and response_header() adds a header
+// (e.g. request-id) to every response.
+//
+// GoogleAdsRow is roughly 47KB, and decoding streamed rows in unoptimized builds
+// needs more than the default 2MB thread stack, so async tests run through
+// block_on() on worker threads with a larger stack.
+
+#![allow(dead_code)]
+
+use bytes::{BufMut, Bytes, BytesMut};
+use http_body::Frame;
+use http_body_util::{BodyExt, StreamBody};
+use prost::Message;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tonic::body::Body;
+use tonic::Status;
+
+type Handler = dyn Fn(&str, Bytes) -> Reply + Send + Sync;
+type Delay = dyn Fn(&str, &Bytes) -> Option<Duration> + Send + Sync;
+
+enum Reply {
+    Messages(Vec<Bytes>, Status),
+    TrailersOnly(Status),
+}
+
+#[derive(Clone)]
+pub struct MockTransport {
+    handler: Arc<Handler>,
+    delay: Option<Arc<Delay>>,
+    response_headers: http::HeaderMap,
+    calls: Arc<Mutex<Vec<RecordedCall>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    pub path: String,
+    pub headers: http::HeaderMap,
+    pub message: Bytes,
+}
+
+impl RecordedCall {
+    pub fn decode<M: Message + Default>(&self) -> M {
+        M::decode(self.message.clone()).expect("Failed to decode recorded request")
+    }
+}
+
+impl MockTransport {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&str, Bytes) -> Result<Vec<Bytes>, Status> + Send + Sync + 'static,
+    {
+        Self::with_handler(move |path, message| match handler(path, message) {
+            Ok(messages) => Reply::Messages(messages, Status::ok("")),
+            Err(status) => Reply::TrailersOnly(status),
+        })
+    }
+
+    /// Answers with the messages followed by the status in the trailers.
+    pub fn streaming<F>(handler: F) -> Self
+    where
+        F: Fn(&str, Bytes) -> (Vec<Bytes>, Status) + Send + Sync + 'static,
+    {
+        Self::with_handler(move |path, message| {
+            let (messages, status) = handler(path, message);
+            Reply::Messages(messages, status)
+        })
+    }
+
+    fn with_handler<F>(handler: F) -> Self
+    where
+        F: Fn(&str, Bytes) -> Reply + Send + Sync + 'static,
+    {
+        Self {
+            handler: Arc::new(handler),
+            delay: None,
+            response_headers: http::HeaderMap::new(),
+            calls: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Waits for the returned duration, if any, before answering a call.
+    pub fn delayed<F>(mut self, delay: F) -> Self
+    where
+        F: Fn(&str, &Bytes) -> Option<Duration> + Send + Sync + 'static,
+    {
+        self.delay = Some(Arc::new(delay));
+        self
+    }
+
+    /// Adds a header to every response, including trailers-only ones.
+    pub fn response_header(mut self, name: &'static str, value: &str) -> Self {
+        self.response_headers
+            .insert(name, http::HeaderValue::from_str(value).unwrap());
+        self
+    }
+
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+/// Runs a future to completion on a runtime whose worker threads have an 8MB stack.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_stack_size(8 * 1024 * 1024)
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async { tokio::spawn(future).await.unwrap() })
+}
+
+/// Runs a future on a paused-clock runtime, on a thread with an 8MB stack.
+pub fn block_on_paused<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .start_paused(true)
+                .build()
+                .unwrap()
+                .block_on(future)
+        })
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+/// Encodes a message for use as a handler response.
+pub fn encode<M: Message>(message: &M) -> Bytes {
+    Bytes::from(message.encode_to_vec())
+}
+
+fn frame(message: &Bytes) -> Bytes {
+    let mut buf = BytesMut::with_capacity(message.len() + 5);
+    buf.put_u8(0);
+    buf.put_u32(message.len() as u32);
+    buf.put_slice(message);
+    buf.freeze()
+}
+
+impl tower::Service<http::Request<Body>> for MockTransport {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let handler = self.handler.clone();
+        let delay = self.delay.clone();
+        let calls = self.calls.clone();
+        let response_headers = self.response_headers.clone();
+        Box::pin(async move {
+            let path = req.uri().path().to_string();
+            let headers = req.headers().clone();
+            let body = req.into_body().collect().await.unwrap().to_bytes();
+            let message = if body.len() >= 5 {
+                body.slice(5..)
+            } else {
+                Bytes::new()
+            };
+            calls.lock().unwrap().push(RecordedCall {
+                path: path.clone(),
+                headers,
+                message: message.clone(),
+            });
+            if let Some(duration) = delay.as_ref().and_then(|d| d(&path, &message)) {
+                tokio::time::sleep(duration).await;
+            }
+
+            let mut response = match handler(&path, message) {
+                Reply::Messages(messages, status) => {
+                    let trailers = status.into_http::<Body>().into_parts().0.headers;
+                    let mut frames: Vec<Result<Frame<Bytes>, Status>> =
+                        messages.iter().map(|m| Ok(Frame::data(frame(m)))).collect();
+                    frames.push(Ok(Frame::trailers(trailers)));
+                    let body = Body::new(StreamBody::new(futures::stream::iter(frames)));
+                    http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .body(body)
+                        .unwrap()
+                }
+                Reply::TrailersOnly(status) => status.into_http(),
+            };
+            response.headers_mut().extend(response_headers);
+            Ok(response)
+        })
+    }
+}
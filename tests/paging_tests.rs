@@ -0,0 +1,186 @@
+// Tests for paginated Search as a stream of rows
+//
+// These tests verify that search_paged follows next_page_token across pages,
+// keeps total_results_count and the field mask of the first page, resumes from
+// a saved page token, and surfaces failures as GoogleAdsError.
+
+mod mock_transport;
+
+use futures::StreamExt;
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsRequest,
+    SearchGoogleAdsResponse,
+};
+use googleads_rs::paging::search_paged;
+use mock_transport::{block_on, encode, MockTransport};
+use tonic::{Code, Status};
+
+const QUERY: &str = "SELECT campaign.id FROM campaign";
+
+fn campaign_row(id: i64) -> GoogleAdsRow {
+    GoogleAdsRow {
+        campaign: Some(Campaign {
+            id: Some(id),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+// Five campaigns over three pages: [1, 2] -> "p2" -> [3, 4] -> "p3" -> [5]
+fn paged_transport() -> MockTransport {
+    MockTransport::new(|_path, body| {
+        let request: SearchGoogleAdsRequest = prost::Message::decode(body).unwrap();
+        let (ids, next) = match request.page_token.as_str() {
+            "" => (vec![1, 2], "p2"),
+            "p2" => (vec![3, 4], "p3"),
+            "p3" => (vec![5], ""),
+            other => return Err(Status::invalid_argument(format!("bad token {}", other))),
+        };
+        let with_total = request
+            .search_settings
+            .is_some_and(|s| s.return_total_results_count);
+        Ok(vec![encode(&SearchGoogleAdsResponse {
+            results: ids.into_iter().map(campaign_row).collect(),
+            next_page_token: next.to_string(),
+            total_results_count: if with_total { 5 } else { 0 },
+            field_mask: Some(prost_types::FieldMask {
+                paths: vec!["campaign.id".to_string()],
+            }),
+            ..Default::default()
+        })])
+    })
+}
+
+fn ids(rows: &[GoogleAdsRow]) -> Vec<i64> {
+    rows.iter()
+        .map(|row| row.campaign.as_ref().unwrap().id.unwrap())
+        .collect()
+}
+
+// ============================================================================
+// Pagination
+// ============================================================================
+
+#[test]
+fn test_follows_page_tokens() {
+    let transport = paged_transport();
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let rows = block_on(async move {
+        let mut paged = search_paged(client, "123-456-7890", QUERY);
+        let mut rows = Vec::new();
+        while let Some(row) = paged.next().await {
+            rows.push(row.unwrap());
+        }
+        assert_eq!(paged.pages(), 3);
+        assert_eq!(paged.total_results_count(), Some(5));
+        assert_eq!(paged.field_mask().unwrap().paths, vec!["campaign.id"]);
+        assert_eq!(paged.next_page_token(), None);
+        rows
+    });
+
+    assert_eq!(ids(&rows), vec![1, 2, 3, 4, 5]);
+    let calls = transport.calls();
+    assert_eq!(calls.len(), 3);
+    let first: SearchGoogleAdsRequest = calls[0].decode();
+    assert_eq!(first.customer_id, "1234567890");
+    assert_eq!(first.query, QUERY);
+    assert_eq!(first.page_token, "");
+    assert!(first.search_settings.unwrap().return_total_results_count);
+    // Only the first page asks for the total
+    let second: SearchGoogleAdsRequest = calls[1].decode();
+    assert_eq!(second.page_token, "p2");
+    assert!(second.search_settings.is_none());
+}
+
+#[test]
+fn test_page_tokens_track_the_current_page() {
+    let client = GoogleAdsServiceClient::new(paged_transport());
+
+    let tokens = block_on(async move {
+        let mut paged = search_paged(client, "1", QUERY);
+        let mut tokens = Vec::new();
+        while let Some(row) = paged.next().await {
+            row.unwrap();
+            tokens.push((
+                paged.page_token().map(str::to_string),
+                paged.next_page_token().map(str::to_string),
+            ));
+        }
+        tokens
+    });
+
+    let page = |t: Option<&str>, n: Option<&str>| (t.map(String::from), n.map(String::from));
+    assert_eq!(
+        tokens,
+        vec![
+            page(None, Some("p2")),
+            page(None, Some("p2")),
+            page(Some("p2"), Some("p3")),
+            page(Some("p2"), Some("p3")),
+            page(Some("p3"), None),
+        ]
+    );
+}
+
+#[test]
+fn test_total_results_count_can_be_disabled() {
+    let transport = paged_transport();
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    block_on(async move {
+        let mut paged = search_paged(client, "1", QUERY);
+        paged.return_total_results_count(false);
+        assert_eq!(paged.by_ref().count().await, 5);
+        assert_eq!(paged.total_results_count(), None);
+    });
+
+    let first: SearchGoogleAdsRequest = transport.calls()[0].decode();
+    assert!(!first.search_settings.unwrap().return_total_results_count);
+}
+
+// ============================================================================
+// Resuming
+// ============================================================================
+
+#[test]
+fn test_resume_from_saved_token() {
+    let transport = paged_transport();
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let rows = block_on(async move {
+        let mut paged = search_paged(client, "1", QUERY);
+        paged.resume_from("p2");
+        let rows: Vec<_> = paged.by_ref().map(Result::unwrap).collect().await;
+        // The first page received is the one resumed from
+        assert_eq!(paged.total_results_count(), Some(5));
+        assert_eq!(paged.pages(), 2);
+        rows
+    });
+
+    assert_eq!(ids(&rows), vec![3, 4, 5]);
+    let first: SearchGoogleAdsRequest = transport.calls()[0].decode();
+    assert_eq!(first.page_token, "p2");
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+#[test]
+fn test_error_ends_the_stream() {
+    let client = GoogleAdsServiceClient::new(paged_transport());
+
+    let items = block_on(async move {
+        let mut paged = search_paged(client, "1", QUERY);
+        paged.resume_from("expired");
+        paged.collect::<Vec<_>>().await
+    });
+
+    assert_eq!(items.len(), 1);
+    let err = items[0].as_ref().unwrap_err();
+    assert_eq!(err.code, Code::InvalidArgument);
+    assert!(err.message.contains("bad token expired"), "{}", err.message);
+}
@@ -0,0 +1,282 @@
+// Tests for mapping partial_failure_error back to mutate operations
+//
+// These tests verify that errors are attached to the operation index and field
+// path from their location, that results are paired with the successful
+// operations, and that errors without an operation index are kept apart.
+
+use googleads_rs::google::ads::googleads::v23::errors::{
+    error_code, error_location::FieldPathElement, field_error_enum::FieldError,
+    request_error_enum::RequestError, ErrorCode, ErrorLocation,
+    GoogleAdsError as GoogleAdsErrorProto, GoogleAdsFailure,
+};
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    campaign_operation, mutate_operation, mutate_operation_response, CampaignOperation,
+    MutateCampaignResult, MutateGoogleAdsRequest, MutateGoogleAdsResponse, MutateOperation,
+    MutateOperationResponse,
+};
+use googleads_rs::{DynamicMutationBuilder, ErrorCategory, PartialFailureReport};
+use prost::Message;
+
+fn operation(name: &str) -> MutateOperation {
+    MutateOperation {
+        operation: Some(mutate_operation::Operation::CampaignOperation(
+            CampaignOperation {
+                operation: Some(campaign_operation::Operation::Create(Campaign {
+                    name: Some(name.to_string()),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+        )),
+    }
+}
+
+fn result(resource_name: &str) -> MutateOperationResponse {
+    MutateOperationResponse {
+        response: Some(mutate_operation_response::Response::CampaignResult(
+            MutateCampaignResult {
+                resource_name: resource_name.to_string(),
+                ..Default::default()
+            },
+        )),
+    }
+}
+
+fn element(name: &str, index: Option<i32>) -> FieldPathElement {
+    FieldPathElement {
+        field_name: name.to_string(),
+        index,
+    }
+}
+
+fn error(code: error_code::ErrorCode, elements: Vec<FieldPathElement>) -> GoogleAdsErrorProto {
+    GoogleAdsErrorProto {
+        error_code: Some(ErrorCode {
+            error_code: Some(code),
+        }),
+        message: "error".to_string(),
+        location: Some(ErrorLocation {
+            field_path_elements: elements,
+        }),
+        ..Default::default()
+    }
+}
+
+fn partial_failure_error(errors: Vec<GoogleAdsErrorProto>) -> googleads_rs::google::rpc::Status {
+    let failure = GoogleAdsFailure {
+        errors,
+        request_id: "req-1".to_string(),
+    };
+    googleads_rs::google::rpc::Status {
+        code: 3,
+        message: "Multiple errors in 'details'.".to_string(),
+        details: vec![prost_types::Any {
+            type_url: "type.googleapis.com/google.ads.googleads.v23.errors.GoogleAdsFailure"
+                .to_string(),
+            value: failure.encode_to_vec(),
+        }],
+    }
+}
+
+fn request() -> MutateGoogleAdsRequest {
+    MutateGoogleAdsRequest {
+        customer_id: "1234567890".to_string(),
+        mutate_operations: vec![operation("A"), operation(""), operation("C")],
+        partial_failure: true,
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// GoogleAdsService.Mutate
+// ============================================================================
+
+#[test]
+fn test_errors_are_attached_to_operations() {
+    let response = MutateGoogleAdsResponse {
+        partial_failure_error: Some(partial_failure_error(vec![error(
+            error_code::ErrorCode::FieldError(FieldError::Required as i32),
+            vec![
+                element("mutate_operations", Some(1)),
+                element("campaign_operation", None),
+                element("create", None),
+                element("name", None),
+            ],
+        )])),
+        // The failed operation gets an empty result
+        mutate_operation_responses: vec![
+            result("customers/1234567890/campaigns/1"),
+            MutateOperationResponse::default(),
+            result("customers/1234567890/campaigns/3"),
+        ],
+    };
+    let report = PartialFailureReport::from_mutate(&request(), &response);
+
+    assert!(report.has_failures());
+    assert_eq!(report.operations.len(), 3);
+    assert!(report.unattributed.is_empty());
+
+    let failed: Vec<_> = report.failed().collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].index, 1);
+    assert_eq!(failed[0].operation, operation(""));
+    assert_eq!(failed[0].result, None);
+    assert_eq!(
+        failed[0].errors[0].field_path,
+        "campaign_operation.create.name"
+    );
+    assert_eq!(
+        failed[0].errors[0].detail.error_code(),
+        "field_error.REQUIRED"
+    );
+
+    let succeeded: Vec<_> = report.succeeded().collect();
+    assert_eq!(
+        succeeded.iter().map(|o| o.index).collect::<Vec<_>>(),
+        vec![0, 2]
+    );
+    assert_eq!(succeeded[1].operation, operation("C"));
+    assert_eq!(
+        succeeded[1].result,
+        Some(result("customers/1234567890/campaigns/3"))
+    );
+
+    let error = report.error.as_ref().unwrap();
+    assert_eq!(error.request_id.as_deref(), Some("req-1"));
+    assert_eq!(error.category(), ErrorCategory::PartialFailure);
+}
+
+#[test]
+fn test_multiple_errors_on_one_operation() {
+    let response = MutateGoogleAdsResponse {
+        partial_failure_error: Some(partial_failure_error(vec![
+            error(
+                error_code::ErrorCode::FieldError(FieldError::Required as i32),
+                vec![element("mutate_operations", Some(0))],
+            ),
+            error(
+                error_code::ErrorCode::FieldError(FieldError::Required as i32),
+                vec![
+                    element("mutate_operations", Some(0)),
+                    element("campaign_operation", None),
+                    element("create", None),
+                    element("bidding_strategy", None),
+                ],
+            ),
+        ])),
+        mutate_operation_responses: vec![MutateOperationResponse::default(); 3],
+    };
+    let report = PartialFailureReport::from_mutate(&request(), &response);
+
+    let errors = &report.operations[0].errors;
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].field_path, "");
+    assert_eq!(
+        errors[1].field_path,
+        "campaign_operation.create.bidding_strategy"
+    );
+    assert_eq!(report.succeeded().count(), 2);
+}
+
+#[test]
+fn test_unattributed_errors() {
+    let response = MutateGoogleAdsResponse {
+        partial_failure_error: Some(partial_failure_error(vec![
+            error(
+                error_code::ErrorCode::RequestError(RequestError::InvalidCustomerId as i32),
+                vec![element("customer_id", None)],
+            ),
+            error(
+                error_code::ErrorCode::RequestError(RequestError::InvalidCustomerId as i32),
+                vec![element("mutate_operations", Some(99))],
+            ),
+        ])),
+        mutate_operation_responses: vec![],
+    };
+    let report = PartialFailureReport::from_mutate(&request(), &response);
+
+    assert_eq!(report.unattributed.len(), 2);
+    assert_eq!(report.failed().count(), 0);
+    assert!(report.has_failures());
+}
+
+#[test]
+fn test_all_operations_succeeded() {
+    let response = MutateGoogleAdsResponse {
+        partial_failure_error: None,
+        mutate_operation_responses: vec![
+            result("customers/1/campaigns/1"),
+            result("customers/1/campaigns/2"),
+            result("customers/1/campaigns/3"),
+        ],
+    };
+    let report = PartialFailureReport::from_mutate(&request(), &response);
+
+    assert!(!report.has_failures());
+    assert!(report.error.is_none());
+    assert_eq!(report.succeeded().count(), 3);
+    assert_eq!(
+        report.operations[1].result,
+        Some(result("customers/1/campaigns/2"))
+    );
+}
+
+#[test]
+fn test_dynamic_mutation_builder_request() {
+    let mut builder = DynamicMutationBuilder::new("Campaign", "1234567890");
+    builder.set_field("name", "Renamed");
+    let request = builder.build("customers/1234567890/campaigns/1").unwrap();
+    assert!(request.partial_failure);
+
+    let response = MutateGoogleAdsResponse {
+        partial_failure_error: Some(partial_failure_error(vec![error(
+            error_code::ErrorCode::FieldError(FieldError::Required as i32),
+            vec![
+                element("mutate_operations", Some(0)),
+                element("campaign_operation", None),
+                element("update", None),
+                element("name", None),
+            ],
+        )])),
+        mutate_operation_responses: vec![MutateOperationResponse::default()],
+    };
+    let report = PartialFailureReport::from_mutate(&request, &response);
+    assert_eq!(
+        report.operations[0].errors[0].field_path,
+        "campaign_operation.update.name"
+    );
+}
+
+// ============================================================================
+// Service-specific mutates
+// ============================================================================
+
+#[test]
+fn test_from_parts_with_service_operations() {
+    let operations = vec![CampaignOperation::default(), CampaignOperation::default()];
+    let results = vec![
+        MutateCampaignResult::default(),
+        MutateCampaignResult {
+            resource_name: "customers/1/campaigns/2".to_string(),
+            ..Default::default()
+        },
+    ];
+    let status = partial_failure_error(vec![error(
+        error_code::ErrorCode::FieldError(FieldError::Required as i32),
+        vec![
+            element("operations", Some(0)),
+            element("create", None),
+            element("name", None),
+        ],
+    )]);
+
+    let report =
+        PartialFailureReport::from_parts("operations", &operations, &results, Some(&status));
+
+    assert_eq!(report.operations[0].errors[0].field_path, "create.name");
+    assert_eq!(
+        report.operations[1].result.as_ref().unwrap().resource_name,
+        "customers/1/campaigns/2"
+    );
+}
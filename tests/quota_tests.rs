@@ -0,0 +1,283 @@
+// Tests for daily usage tracking and quota budgets
+//
+// These tests verify that QuotaLayer counts requests, operations and
+// query_resource_consumption per developer token and customer, rejects calls
+// over a per-customer or per-token budget without sending them, delays calls
+// until the daily reset when configured to, and exports usage records without
+// the developer token itself.
+
+mod mock_transport;
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, MutateGoogleAdsRequest,
+    MutateGoogleAdsResponse, MutateOperation, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::quota::{
+    token_fingerprint, DailyLimits, OverBudget, Quota, QuotaBudget, QuotaLayer, Usage,
+    UsageTracker, BASIC_ACCESS_DAILY_OPERATIONS,
+};
+use googleads_rs::GoogleAdsInterceptor;
+use mock_transport::{block_on, encode, MockTransport};
+use std::time::Instant;
+use tonic::codegen::InterceptedService;
+use tonic::{Code, Status};
+use tower::Layer;
+
+const TOKEN: &str = "DEV-TOKEN-1";
+
+fn transport() -> MockTransport {
+    MockTransport::new(|path, body| {
+        if path.ends_with("/Mutate") {
+            let request: MutateGoogleAdsRequest = prost::Message::decode(body).unwrap();
+            return Ok(vec![encode(&MutateGoogleAdsResponse {
+                mutate_operation_responses: vec![
+                    Default::default();
+                    request.mutate_operations.len()
+                ],
+                ..Default::default()
+            })]);
+        }
+        let batch = SearchGoogleAdsStreamResponse {
+            results: vec![GoogleAdsRow::default(); 2],
+            query_resource_consumption: 30,
+            ..Default::default()
+        };
+        Ok(vec![encode(&batch), encode(&batch)])
+    })
+}
+
+type Client =
+    GoogleAdsServiceClient<InterceptedService<Quota<MockTransport>, GoogleAdsInterceptor>>;
+
+fn client(tracker: &UsageTracker, transport: &MockTransport, token: &str) -> Client {
+    GoogleAdsServiceClient::with_interceptor(
+        QuotaLayer::new(tracker.clone()).layer(transport.clone()),
+        GoogleAdsInterceptor::new(token).unwrap(),
+    )
+}
+
+fn search_stream(mut client: Client, customer_id: &str) -> Result<usize, Status> {
+    let request = SearchGoogleAdsStreamRequest {
+        customer_id: customer_id.to_string(),
+        query: "SELECT campaign.id FROM campaign".to_string(),
+        ..Default::default()
+    };
+    block_on(async move {
+        let mut stream = client.search_stream(request).await?.into_inner();
+        let mut rows = 0;
+        while let Some(batch) = stream.message().await? {
+            rows += batch.results.len();
+        }
+        Ok(rows)
+    })
+}
+
+fn mutate(mut client: Client, customer_id: &str, operations: usize) -> Result<(), Status> {
+    let request = MutateGoogleAdsRequest {
+        customer_id: customer_id.to_string(),
+        mutate_operations: vec![MutateOperation::default(); operations],
+        ..Default::default()
+    };
+    block_on(async move { client.mutate(request).await.map(|_| ()) })
+}
+
+// ============================================================================
+// Usage
+// ============================================================================
+
+#[test]
+fn test_usage_is_counted_per_customer_and_token() {
+    let tracker = UsageTracker::new();
+    let transport = transport();
+
+    assert_eq!(
+        search_stream(client(&tracker, &transport, TOKEN), "1111111111").unwrap(),
+        4
+    );
+    mutate(client(&tracker, &transport, TOKEN), "1111111111", 3).unwrap();
+    search_stream(client(&tracker, &transport, TOKEN), "2222222222").unwrap();
+    search_stream(client(&tracker, &transport, "DEV-TOKEN-2"), "2222222222").unwrap();
+
+    assert_eq!(
+        tracker.customer_usage("1111111111"),
+        Usage {
+            requests: 2,
+            operations: 4,
+            query_resource_consumption: 60,
+        }
+    );
+    assert_eq!(
+        tracker.customer_usage("2222222222"),
+        Usage {
+            requests: 2,
+            operations: 2,
+            query_resource_consumption: 120,
+        }
+    );
+    assert_eq!(
+        tracker.token_usage(TOKEN),
+        Usage {
+            requests: 3,
+            operations: 5,
+            query_resource_consumption: 120,
+        }
+    );
+    assert_eq!(tracker.token_usage("DEV-TOKEN-2").requests, 1);
+}
+
+#[test]
+fn test_snapshot_serializes_without_the_token() {
+    let tracker = UsageTracker::new();
+    search_stream(client(&tracker, &transport(), TOKEN), "3333333333").unwrap();
+
+    let snapshot = tracker.snapshot();
+    assert_eq!(snapshot.len(), 1);
+    assert_eq!(snapshot[0].developer_token, token_fingerprint(TOKEN));
+    assert_eq!(snapshot[0].customer_id, "3333333333");
+    assert_eq!(snapshot[0].date, Utc::now().date_naive());
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json[0]["requests"], 1);
+    assert_eq!(json[0]["query_resource_consumption"], 60);
+    assert!(!json.to_string().contains(TOKEN), "{}", json);
+
+    tracker.prune_before(Utc::now().date_naive() + TimeDelta::days(1));
+    assert!(tracker.snapshot().is_empty());
+}
+
+// ============================================================================
+// Budgets
+// ============================================================================
+
+#[test]
+fn test_customer_budget_rejects_without_sending() {
+    let mut budget = QuotaBudget::new();
+    budget.per_customer(DailyLimits {
+        requests: Some(2),
+        ..Default::default()
+    });
+    let tracker = UsageTracker::with_budget(budget);
+    let transport = transport();
+
+    search_stream(client(&tracker, &transport, TOKEN), "4444444444").unwrap();
+    search_stream(client(&tracker, &transport, TOKEN), "4444444444").unwrap();
+    let status = search_stream(client(&tracker, &transport, TOKEN), "4444444444").unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert!(
+        status.message().contains("requests budget of 2")
+            && status.message().contains("4444444444"),
+        "{}",
+        status.message()
+    );
+    assert_eq!(transport.calls().len(), 2);
+    assert_eq!(tracker.customer_usage("4444444444").requests, 2);
+
+    // Other customers have their own budget
+    search_stream(client(&tracker, &transport, TOKEN), "5555555555").unwrap();
+}
+
+#[test]
+fn test_rejected_calls_leave_no_usage_record() {
+    let mut budget = QuotaBudget::new();
+    budget.per_customer(DailyLimits {
+        requests: Some(0),
+        ..Default::default()
+    });
+    let tracker = UsageTracker::with_budget(budget);
+    let transport = transport();
+
+    let status = search_stream(client(&tracker, &transport, TOKEN), "4444444444").unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert!(transport.calls().is_empty());
+    assert!(tracker.snapshot().is_empty());
+}
+
+#[test]
+fn test_basic_access_operations_per_token() {
+    let mut budget = QuotaBudget::new();
+    budget.per_developer_token(DailyLimits {
+        operations: Some(5),
+        ..Default::default()
+    });
+    let tracker = UsageTracker::with_budget(budget);
+    let transport = transport();
+
+    mutate(client(&tracker, &transport, TOKEN), "6666666666", 4).unwrap();
+    let status = mutate(client(&tracker, &transport, TOKEN), "7777777777", 2).unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert!(
+        status.message().contains("operations budget of 5"),
+        "{}",
+        status.message()
+    );
+    mutate(client(&tracker, &transport, TOKEN), "7777777777", 1).unwrap();
+    mutate(client(&tracker, &transport, "DEV-TOKEN-2"), "7777777777", 5).unwrap();
+
+    let mut expected = QuotaBudget::new();
+    expected.per_developer_token(DailyLimits {
+        operations: Some(BASIC_ACCESS_DAILY_OPERATIONS),
+        ..Default::default()
+    });
+    assert_eq!(QuotaBudget::basic_access(), expected);
+}
+
+#[test]
+fn test_consumption_budget_stops_calls_once_reached() {
+    let mut budget = QuotaBudget::new();
+    budget.per_customer(DailyLimits {
+        query_resource_consumption: Some(100),
+        ..Default::default()
+    });
+    let tracker = UsageTracker::with_budget(budget);
+    let transport = transport();
+
+    // 60 per call: the second call starts under budget and ends over it
+    search_stream(client(&tracker, &transport, TOKEN), "8888888888").unwrap();
+    search_stream(client(&tracker, &transport, TOKEN), "8888888888").unwrap();
+    let status = search_stream(client(&tracker, &transport, TOKEN), "8888888888").unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(
+        tracker
+            .customer_usage("8888888888")
+            .query_resource_consumption,
+        120
+    );
+}
+
+#[test]
+fn test_delay_waits_for_the_daily_reset() {
+    let mut budget = QuotaBudget::new();
+    budget
+        .per_customer(DailyLimits {
+            requests: Some(1),
+            ..Default::default()
+        })
+        .on_exceeded(OverBudget::Delay);
+    let mut tracker = UsageTracker::with_budget(budget);
+    // Half a second before midnight, running in real time
+    let midnight: DateTime<Utc> = NaiveDate::from_ymd_opt(2025, 3, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    let start = Instant::now();
+    tracker.clock(move || {
+        midnight - TimeDelta::milliseconds(500) + TimeDelta::from_std(start.elapsed()).unwrap()
+    });
+    let transport = transport();
+
+    search_stream(client(&tracker, &transport, TOKEN), "9999999999").unwrap();
+    search_stream(client(&tracker, &transport, TOKEN), "9999999999").unwrap();
+    assert!(start.elapsed().as_millis() >= 400);
+
+    let days: Vec<_> = tracker.snapshot().iter().map(|r| r.date).collect();
+    assert_eq!(
+        days,
+        vec![
+            NaiveDate::from_ymd_opt(2025, 2, 28).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()
+        ]
+    );
+}
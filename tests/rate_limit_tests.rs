@@ -0,0 +1,316 @@
+// Tests for client-side rate limiting
+//
+// These tests run on paused tokio time, so bucket waits are measured exactly.
+// They verify that RateLimitLayer spaces calls by the bucket rate after the
+// initial burst, keys buckets per customer and per developer token, keeps
+// separate read and mutation limits, and halves the rate of a bucket on quota
+// errors before recovering it step by step. Quota errors that waiting does not
+// fix, such as quota_error.ACCESS_PROHIBITED, leave the rate alone.
+
+mod mock_transport;
+
+use googleads_rs::error::GOOGLE_ADS_FAILURE_METADATA_KEY;
+use googleads_rs::google::ads::googleads::v23::errors::{
+    error_code, quota_error_enum::QuotaError, ErrorCode, GoogleAdsError as GoogleAdsErrorProto,
+    GoogleAdsFailure,
+};
+#[cfg(feature = "campaign-management")]
+use googleads_rs::google::ads::googleads::v23::services::{
+    campaign_service_client::CampaignServiceClient, MutateCampaignsRequest,
+};
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, SearchGoogleAdsRequest,
+    SearchGoogleAdsResponse, SearchGoogleAdsStreamRequest,
+};
+use googleads_rs::rate_limit::{CallKind, RateLimit, RateLimitLayer, RateLimited};
+use googleads_rs::GoogleAdsInterceptor;
+use mock_transport::{block_on_paused, encode, MockTransport};
+use prost::Message;
+use std::time::Duration;
+use tokio::time::Instant;
+use tonic::codegen::InterceptedService;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+use tower::Layer;
+
+const TOKEN: &str = "DEV-TOKEN-1";
+
+type Channel = InterceptedService<RateLimited<MockTransport>, GoogleAdsInterceptor>;
+
+fn transport() -> MockTransport {
+    // An empty message decodes as any response, including MutateCampaignsResponse
+    MockTransport::new(|_path, _body| Ok(vec![encode(&SearchGoogleAdsResponse::default())]))
+}
+
+fn channel(layer: &RateLimitLayer, transport: &MockTransport, token: &str) -> Channel {
+    InterceptedService::new(
+        layer.layer(transport.clone()),
+        GoogleAdsInterceptor::new(token).unwrap(),
+    )
+}
+
+async fn search(channel: Channel, customer_id: &str) -> Result<(), Status> {
+    let mut client = GoogleAdsServiceClient::new(channel);
+    client
+        .search(SearchGoogleAdsRequest {
+            customer_id: customer_id.to_string(),
+            query: "SELECT campaign.id FROM campaign".to_string(),
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+#[cfg(feature = "campaign-management")]
+async fn mutate(channel: Channel, customer_id: &str) -> Result<(), Status> {
+    let mut client = CampaignServiceClient::new(channel);
+    client
+        .mutate_campaigns(MutateCampaignsRequest {
+            customer_id: customer_id.to_string(),
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+fn quota_failure(code: Code, error: QuotaError) -> Status {
+    let failure = GoogleAdsFailure {
+        errors: vec![GoogleAdsErrorProto {
+            error_code: Some(ErrorCode {
+                error_code: Some(error_code::ErrorCode::QuotaError(error as i32)),
+            }),
+            ..Default::default()
+        }],
+        request_id: "abc".to_string(),
+    };
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(&failure.encode_to_vec()),
+    );
+    Status::with_metadata(code, "failed", metadata)
+}
+
+fn assert_elapsed(start: Instant, expected: Duration) {
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed >= expected && elapsed < expected + Duration::from_millis(50),
+        "elapsed {:?}, expected {:?}",
+        elapsed,
+        expected
+    );
+}
+
+// ============================================================================
+// Buckets
+// ============================================================================
+
+#[test]
+fn test_call_kind() {
+    let services = "/google.ads.googleads.v23.services";
+    assert_eq!(
+        CallKind::of_method(&format!("{}.GoogleAdsService/SearchStream", services)),
+        CallKind::Read
+    );
+    assert_eq!(
+        CallKind::of_method(&format!("{}.CampaignService/MutateCampaigns", services)),
+        CallKind::Mutate
+    );
+    assert_eq!(RateLimit::per_second(2.5).burst, 3);
+    assert_eq!(RateLimit::per_minute(30.0).per_second, 0.5);
+}
+
+#[test]
+fn test_calls_are_spaced_after_the_burst() {
+    block_on_paused(async {
+        let mut layer = RateLimitLayer::new();
+        layer.per_customer(CallKind::Read, RateLimit::per_second(2.0));
+        let transport = transport();
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            search(channel(&layer, &transport, TOKEN), "1111111111")
+                .await
+                .unwrap();
+        }
+        // Two calls from the burst, then one every half second
+        assert_elapsed(start, Duration::from_millis(1500));
+
+        // Another customer has its own bucket
+        let start = Instant::now();
+        search(channel(&layer, &transport, TOKEN), "2222222222")
+            .await
+            .unwrap();
+        assert_elapsed(start, Duration::ZERO);
+        assert_eq!(transport.calls().len(), 6);
+    });
+}
+
+#[test]
+fn test_developer_token_bucket_spans_customers() {
+    block_on_paused(async {
+        let mut layer = RateLimitLayer::new();
+        layer.per_developer_token(CallKind::Read, RateLimit::per_second(1.0));
+        let transport = transport();
+
+        let start = Instant::now();
+        search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap();
+        search(channel(&layer, &transport, TOKEN), "2222222222")
+            .await
+            .unwrap();
+        assert_elapsed(start, Duration::from_secs(1));
+
+        // Another developer token has its own bucket
+        let start = Instant::now();
+        search(channel(&layer, &transport, "DEV-TOKEN-2"), "2222222222")
+            .await
+            .unwrap();
+        assert_elapsed(start, Duration::ZERO);
+    });
+}
+
+#[test]
+#[cfg(feature = "campaign-management")]
+fn test_reads_and_mutations_have_separate_limits() {
+    block_on_paused(async {
+        let mut layer = RateLimitLayer::new();
+        layer
+            .per_customer(CallKind::Read, RateLimit::per_minute(1.0))
+            .per_customer(CallKind::Mutate, RateLimit::per_second(10.0));
+        let transport = transport();
+
+        let start = Instant::now();
+        search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            mutate(channel(&layer, &transport, TOKEN), "1111111111")
+                .await
+                .unwrap();
+        }
+        assert_elapsed(start, Duration::ZERO);
+
+        search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap();
+        assert_elapsed(start, Duration::from_secs(60));
+    });
+}
+
+// ============================================================================
+// Adaptation
+// ============================================================================
+
+#[test]
+fn test_quota_errors_lower_the_rate_then_it_recovers() {
+    block_on_paused(async {
+        let mut layer = RateLimitLayer::new();
+        layer
+            .per_customer(CallKind::Read, RateLimit::per_second(10.0))
+            .adaptive(0.5, Duration::from_secs(10));
+        let transport = MockTransport::new(|_path, _body| {
+            Err(Status::resource_exhausted("Too many requests."))
+        });
+
+        let status = search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(
+            layer.current_rate(TOKEN, Some("1111111111"), CallKind::Read),
+            Some(5.0)
+        );
+        search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            layer.current_rate(TOKEN, Some("1111111111"), CallKind::Read),
+            Some(2.5)
+        );
+        // Unaffected buckets and unlimited scopes
+        assert_eq!(
+            layer.current_rate(TOKEN, Some("2222222222"), CallKind::Read),
+            Some(10.0)
+        );
+        assert_eq!(layer.current_rate(TOKEN, None, CallKind::Read), None);
+
+        // A tenth of the configured rate comes back per quiet interval
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let rate = layer
+            .current_rate(TOKEN, Some("1111111111"), CallKind::Read)
+            .unwrap();
+        assert!((rate - 3.5).abs() < 1e-9, "{}", rate);
+        tokio::time::advance(Duration::from_secs(100)).await;
+        assert_eq!(
+            layer.current_rate(TOKEN, Some("1111111111"), CallKind::Read),
+            Some(10.0)
+        );
+    });
+}
+
+#[test]
+fn test_quota_error_in_trailers_lowers_the_rate() {
+    block_on_paused(async {
+        let mut layer = RateLimitLayer::new();
+        layer.per_developer_token(CallKind::Read, RateLimit::per_second(4.0));
+        let transport = MockTransport::streaming(|_path, _body| {
+            (vec![], Status::resource_exhausted("Too many requests."))
+        });
+
+        let mut client = GoogleAdsServiceClient::new(channel(&layer, &transport, TOKEN));
+        let mut stream = client
+            .search_stream(SearchGoogleAdsStreamRequest {
+                customer_id: "1111111111".to_string(),
+                query: "SELECT campaign.id FROM campaign".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            stream.message().await.unwrap_err().code(),
+            Code::ResourceExhausted
+        );
+        assert_eq!(layer.current_rate(TOKEN, None, CallKind::Read), Some(2.0));
+    });
+}
+
+#[test]
+fn test_access_prohibited_does_not_lower_the_rate() {
+    block_on_paused(async {
+        let mut layer = RateLimitLayer::new();
+        layer.per_customer(CallKind::Read, RateLimit::per_second(4.0));
+        let transport = MockTransport::new(|_path, _body| {
+            Err(quota_failure(
+                Code::ResourceExhausted,
+                QuotaError::AccessProhibited,
+            ))
+        });
+
+        // Slowing down does not lift a prohibition, whatever the gRPC code
+        let status = search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(
+            layer.current_rate(TOKEN, Some("1111111111"), CallKind::Read),
+            Some(4.0)
+        );
+
+        let transport = MockTransport::new(|_path, _body| {
+            Err(quota_failure(
+                Code::ResourceExhausted,
+                QuotaError::ResourceExhausted,
+            ))
+        });
+        search(channel(&layer, &transport, TOKEN), "1111111111")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            layer.current_rate(TOKEN, Some("1111111111"), CallKind::Read),
+            Some(2.0)
+        );
+    });
+}
@@ -0,0 +1,371 @@
+// Tests for retrying transient Google Ads API failures
+//
+// These tests verify the backoff schedule, which errors and methods are retried,
+// the RetryLayer over a mock transport, and re-opening a broken search_stream.
+
+mod mock_transport;
+
+use futures::StreamExt;
+use googleads_rs::error::GOOGLE_ADS_FAILURE_METADATA_KEY;
+use googleads_rs::google::ads::googleads::v23::errors::{
+    database_error_enum::DatabaseError, error_code, quota_error_enum::QuotaError,
+    request_error_enum::RequestError, ErrorCode, ErrorDetails,
+    GoogleAdsError as GoogleAdsErrorProto, GoogleAdsFailure, QuotaErrorDetails,
+};
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+#[cfg(feature = "campaign-management")]
+use googleads_rs::google::ads::googleads::v23::services::{
+    campaign_service_client::CampaignServiceClient, MutateCampaignsRequest, MutateCampaignsResponse,
+};
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsRequest,
+    SearchGoogleAdsResponse, SearchGoogleAdsStreamRequest, SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::retry::{is_mutation, is_transient, server_retry_delay, Retry};
+use googleads_rs::{GoogleAdsError, RetryPolicy};
+use mock_transport::{block_on, encode, MockTransport};
+use prost::Message;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+
+fn fast_policy() -> RetryPolicy {
+    let mut policy = RetryPolicy::default();
+    policy
+        .max_retries(3)
+        .initial_backoff(Duration::from_millis(1))
+        .max_backoff(Duration::from_millis(5));
+    policy
+}
+
+fn failure_status(code: Code, error_code: error_code::ErrorCode) -> Status {
+    let failure = GoogleAdsFailure {
+        errors: vec![GoogleAdsErrorProto {
+            error_code: Some(ErrorCode {
+                error_code: Some(error_code),
+            }),
+            ..Default::default()
+        }],
+        request_id: "abc".to_string(),
+    };
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(&failure.encode_to_vec()),
+    );
+    Status::with_metadata(code, "failed", metadata)
+}
+
+fn campaign_row(id: i64) -> GoogleAdsRow {
+    GoogleAdsRow {
+        campaign: Some(Campaign {
+            id: Some(id),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn batch(ids: &[i64]) -> bytes::Bytes {
+    encode(&SearchGoogleAdsStreamResponse {
+        results: ids.iter().map(|id| campaign_row(*id)).collect(),
+        ..Default::default()
+    })
+}
+
+// ============================================================================
+// Policy
+// ============================================================================
+
+#[test]
+fn test_is_mutation() {
+    let services = "/google.ads.googleads.v23.services";
+    assert!(is_mutation(&format!(
+        "{}.CampaignService/MutateCampaigns",
+        services
+    )));
+    assert!(is_mutation(&format!(
+        "{}.ConversionUploadService/UploadClickConversions",
+        services
+    )));
+    assert!(!is_mutation(&format!(
+        "{}.GoogleAdsService/SearchStream",
+        services
+    )));
+    assert!(!is_mutation(&format!(
+        "{}.KeywordPlanIdeaService/GenerateKeywordIdeas",
+        services
+    )));
+    assert!(!is_mutation(&format!(
+        "{}.CustomerService/ListAccessibleCustomers",
+        services
+    )));
+}
+
+#[test]
+fn test_backoff_is_exponential_jittered_and_capped() {
+    let mut policy = RetryPolicy::default();
+    policy
+        .initial_backoff(Duration::from_millis(100))
+        .max_backoff(Duration::from_secs(1));
+
+    for _ in 0..20 {
+        let first = policy.backoff(0);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+        let fourth = policy.backoff(3);
+        assert!(fourth >= Duration::from_millis(400) && fourth <= Duration::from_millis(800));
+        let capped = policy.backoff(10);
+        assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_secs(1));
+    }
+}
+
+#[test]
+fn test_transient_errors() {
+    assert!(is_transient(&Status::unavailable("reset").into()));
+    assert!(is_transient(&Status::deadline_exceeded("slow").into()));
+    assert!(is_transient(&Status::internal("oops").into()));
+    assert!(!is_transient(&Status::invalid_argument("bad").into()));
+
+    let concurrent: GoogleAdsError = failure_status(
+        Code::Aborted,
+        error_code::ErrorCode::DatabaseError(DatabaseError::ConcurrentModification as i32),
+    )
+    .into();
+    assert!(is_transient(&concurrent));
+
+    let temporarily_exhausted: GoogleAdsError = failure_status(
+        Code::ResourceExhausted,
+        error_code::ErrorCode::QuotaError(QuotaError::ResourceTemporarilyExhausted as i32),
+    )
+    .into();
+    assert!(is_transient(&temporarily_exhausted));
+
+    // A daily quota error does not go away by retrying
+    let exhausted: GoogleAdsError = failure_status(
+        Code::ResourceExhausted,
+        error_code::ErrorCode::QuotaError(QuotaError::ResourceExhausted as i32),
+    )
+    .into();
+    assert!(!is_transient(&exhausted));
+
+    // An INTERNAL status caused by the request is not transient
+    let invalid: GoogleAdsError = failure_status(
+        Code::Internal,
+        error_code::ErrorCode::RequestError(RequestError::InvalidCustomerId as i32),
+    )
+    .into();
+    assert!(!is_transient(&invalid));
+}
+
+#[test]
+fn test_server_retry_delay_is_honoured() {
+    let failure = GoogleAdsFailure {
+        errors: vec![GoogleAdsErrorProto {
+            error_code: Some(ErrorCode {
+                error_code: Some(error_code::ErrorCode::QuotaError(
+                    QuotaError::ResourceTemporarilyExhausted as i32,
+                )),
+            }),
+            details: Some(ErrorDetails {
+                quota_error_details: Some(QuotaErrorDetails {
+                    retry_delay: Some(prost_types::Duration {
+                        seconds: 30,
+                        nanos: 0,
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }],
+        request_id: String::new(),
+    };
+    let mut metadata = MetadataMap::new();
+    metadata.insert_bin(
+        GOOGLE_ADS_FAILURE_METADATA_KEY,
+        MetadataValue::from_bytes(&failure.encode_to_vec()),
+    );
+    let error = GoogleAdsError::from(Status::with_metadata(
+        Code::ResourceExhausted,
+        "slow down",
+        metadata,
+    ));
+
+    assert_eq!(server_retry_delay(&error), Some(Duration::from_secs(30)));
+    assert_eq!(
+        fast_policy().retry_delay(0, &error),
+        Duration::from_secs(30)
+    );
+}
+
+// ============================================================================
+// Retry Layer
+// ============================================================================
+
+#[test]
+fn test_layer_retries_unavailable_then_succeeds() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let transport = MockTransport::new(move |_path, _body| {
+        if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+            Err(Status::unavailable("connection reset"))
+        } else {
+            Ok(vec![encode(&SearchGoogleAdsResponse {
+                total_results_count: 7,
+                ..Default::default()
+            })])
+        }
+    });
+    let mut client = GoogleAdsServiceClient::new(Retry::new(transport.clone(), fast_policy()));
+
+    let response = block_on(async move {
+        client
+            .search(SearchGoogleAdsRequest {
+                customer_id: "1234567890".to_string(),
+                query: "SELECT campaign.id FROM campaign".to_string(),
+                ..Default::default()
+            })
+            .await
+    })
+    .unwrap();
+
+    assert_eq!(response.into_inner().total_results_count, 7);
+    let calls = transport.calls();
+    assert_eq!(calls.len(), 3);
+    // Every attempt sends the same request
+    let request: SearchGoogleAdsRequest = calls[2].decode();
+    assert_eq!(request.customer_id, "1234567890");
+    assert_eq!(calls[0].message, calls[2].message);
+}
+
+#[test]
+fn test_layer_retries_transient_failure_in_trailer() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let transport = MockTransport::new(move |_path, _body| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            Err(failure_status(
+                Code::Aborted,
+                error_code::ErrorCode::DatabaseError(DatabaseError::ConcurrentModification as i32),
+            ))
+        } else {
+            Ok(vec![encode(&SearchGoogleAdsResponse::default())])
+        }
+    });
+    let mut client = GoogleAdsServiceClient::new(Retry::new(transport.clone(), fast_policy()));
+
+    let result = block_on(async move { client.search(SearchGoogleAdsRequest::default()).await });
+    assert!(result.is_ok());
+    assert_eq!(transport.calls().len(), 2);
+}
+
+#[test]
+fn test_layer_gives_up_after_max_retries() {
+    let transport = MockTransport::new(|_path, _body| Err(Status::unavailable("down")));
+    let mut client = GoogleAdsServiceClient::new(Retry::new(transport.clone(), fast_policy()));
+
+    let status = block_on(async move { client.search(SearchGoogleAdsRequest::default()).await })
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(transport.calls().len(), 4);
+}
+
+#[test]
+fn test_layer_does_not_retry_invalid_request() {
+    let transport = MockTransport::new(|_path, _body| Err(Status::invalid_argument("bad query")));
+    let mut client = GoogleAdsServiceClient::new(Retry::new(transport.clone(), fast_policy()));
+
+    let status = block_on(async move { client.search(SearchGoogleAdsRequest::default()).await })
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(transport.calls().len(), 1);
+}
+
+#[test]
+#[cfg(feature = "campaign-management")]
+fn test_layer_retries_mutations_only_when_opted_in() {
+    let transport = MockTransport::new(|_path, _body| Err(Status::unavailable("down")));
+
+    let mut client = CampaignServiceClient::new(Retry::new(transport.clone(), fast_policy()));
+    let status = block_on(async move {
+        client
+            .mutate_campaigns(MutateCampaignsRequest::default())
+            .await
+    })
+    .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(transport.calls().len(), 1);
+
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let transport = MockTransport::new(move |_path, _body| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            Err(Status::unavailable("down"))
+        } else {
+            Ok(vec![encode(&MutateCampaignsResponse::default())])
+        }
+    });
+    let mut policy = fast_policy();
+    policy.retry_mutations(true);
+    let mut client = CampaignServiceClient::new(Retry::new(transport.clone(), policy));
+    let result = block_on(async move {
+        client
+            .mutate_campaigns(MutateCampaignsRequest::default())
+            .await
+    });
+    assert!(result.is_ok());
+    assert_eq!(transport.calls().len(), 2);
+}
+
+// ============================================================================
+// search_stream
+// ============================================================================
+
+#[test]
+fn test_search_stream_reopens_broken_stream() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let transport = MockTransport::streaming(move |_path, _body| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            (
+                vec![batch(&[1, 2]), batch(&[3])],
+                Status::internal("stream reset"),
+            )
+        } else {
+            (vec![batch(&[1, 2]), batch(&[3, 4])], Status::ok(""))
+        }
+    });
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let stream = fast_policy().search_stream(client, SearchGoogleAdsStreamRequest::default());
+    let batches: Vec<_> = block_on(stream.collect::<Vec<_>>());
+
+    let ids: Vec<i64> = batches
+        .into_iter()
+        .flat_map(|batch| batch.unwrap().results)
+        .map(|row| row.campaign.unwrap().id.unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 2, 3, 4]);
+    assert_eq!(transport.calls().len(), 2);
+}
+
+#[test]
+fn test_search_stream_surfaces_permanent_error() {
+    let transport = MockTransport::streaming(|_path, _body| {
+        (vec![batch(&[1])], Status::invalid_argument("bad query"))
+    });
+    let client = GoogleAdsServiceClient::new(transport.clone());
+
+    let stream = fast_policy().search_stream(client, SearchGoogleAdsStreamRequest::default());
+    let batches: Vec<_> = block_on(stream.collect::<Vec<_>>());
+
+    assert_eq!(batches.len(), 2);
+    assert!(batches[0].is_ok());
+    assert_eq!(
+        batches[1].as_ref().unwrap_err().code(),
+        Code::InvalidArgument
+    );
+    assert_eq!(transport.calls().len(), 1);
+}
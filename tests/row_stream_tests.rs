@@ -0,0 +1,150 @@
+// Tests for the row-level view of search_stream responses
+//
+// These tests verify that RowStream flattens batches into rows sharing one
+// field mask and request id, marks batch boundaries, collects the summary row
+// and resource consumption, and surfaces a broken stream as GoogleAdsError.
+
+mod mock_transport;
+
+use futures::StreamExt;
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsStreamRequest,
+    SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::row_stream::{RowStream, StreamRow};
+use mock_transport::{block_on, encode, MockTransport};
+use std::sync::Arc;
+use tonic::{Code, Status};
+
+fn campaign_row(id: i64) -> GoogleAdsRow {
+    GoogleAdsRow {
+        campaign: Some(Campaign {
+            id: Some(id),
+            name: Some(format!("Campaign {}", id)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn batch(ids: &[i64]) -> SearchGoogleAdsStreamResponse {
+    SearchGoogleAdsStreamResponse {
+        results: ids.iter().copied().map(campaign_row).collect(),
+        field_mask: Some(prost_types::FieldMask {
+            paths: vec!["campaign.id".to_string(), "campaign.name".to_string()],
+        }),
+        request_id: "req-1".to_string(),
+        query_resource_consumption: 10,
+        ..Default::default()
+    }
+}
+
+fn open_rows(transport: MockTransport) -> Vec<Result<StreamRow, googleads_rs::GoogleAdsError>> {
+    let mut client = GoogleAdsServiceClient::new(transport);
+    block_on(async move {
+        let response = client
+            .search_stream(SearchGoogleAdsStreamRequest::default())
+            .await
+            .unwrap();
+        RowStream::new(response.into_inner()).collect().await
+    })
+}
+
+// ============================================================================
+// Rows
+// ============================================================================
+
+#[test]
+fn test_rows_share_field_mask_and_request_id() {
+    let transport =
+        MockTransport::new(|_path, _body| Ok(vec![encode(&batch(&[1, 2])), encode(&batch(&[3]))]));
+
+    let rows: Vec<StreamRow> = open_rows(transport)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].row.campaign.as_ref().unwrap().id, Some(1));
+    assert_eq!(&*rows[2].request_id, "req-1");
+    assert_eq!(
+        rows[0].field_mask.paths,
+        vec!["campaign.id", "campaign.name"]
+    );
+    // One allocation for the whole stream
+    assert!(Arc::ptr_eq(&rows[0].field_mask, &rows[2].field_mask));
+    assert!(Arc::ptr_eq(&rows[0].request_id, &rows[2].request_id));
+    assert_eq!(rows[1].values(), vec!["2", "Campaign 2"]);
+}
+
+#[test]
+fn test_batch_boundaries_are_observable() {
+    let transport = MockTransport::new(|_path, _body| {
+        Ok(vec![
+            encode(&batch(&[1, 2])),
+            encode(&batch(&[])),
+            encode(&batch(&[3, 4, 5])),
+        ])
+    });
+
+    let rows: Vec<StreamRow> = open_rows(transport)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(
+        rows.iter()
+            .map(|r| (r.batch, r.end_of_batch))
+            .collect::<Vec<_>>(),
+        vec![(0, false), (0, true), (2, false), (2, false), (2, true)]
+    );
+}
+
+#[test]
+fn test_stream_totals() {
+    let transport = MockTransport::new(|_path, _body| {
+        let mut last = batch(&[3]);
+        last.summary_row = Some(campaign_row(0));
+        Ok(vec![encode(&batch(&[1, 2])), encode(&last)])
+    });
+    let mut client = GoogleAdsServiceClient::new(transport);
+
+    block_on(async move {
+        let response = client
+            .search_stream(SearchGoogleAdsStreamRequest::default())
+            .await
+            .unwrap();
+        let mut rows = RowStream::new(response.into_inner());
+        assert_eq!(rows.request_id(), None);
+        assert_eq!(rows.by_ref().count().await, 3);
+        assert_eq!(rows.batches(), 2);
+        assert_eq!(rows.query_resource_consumption(), 20);
+        assert_eq!(rows.request_id(), Some("req-1"));
+        assert_eq!(
+            rows.summary_row().unwrap().campaign.as_ref().unwrap().id,
+            Some(0)
+        );
+    });
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+#[test]
+fn test_broken_stream_yields_error_after_rows() {
+    let transport = MockTransport::streaming(|_path, _body| {
+        (
+            vec![encode(&batch(&[1, 2]))],
+            Status::unavailable("connection reset"),
+        )
+    });
+
+    let items = open_rows(transport);
+
+    assert_eq!(items.len(), 3);
+    assert!(items[1].as_ref().unwrap().end_of_batch);
+    let err = items[2].as_ref().unwrap_err();
+    assert_eq!(err.code, Code::Unavailable);
+}
@@ -0,0 +1,114 @@
+// Tests for GAQL result schema inference
+//
+// These tests verify that infer_schema() resolves selected paths through the
+// GoogleAdsRow descriptor without needing any response rows.
+
+use googleads_rs::{infer_schema, Query};
+use prost_reflect::Kind;
+
+// ============================================================================
+// Scalar Columns
+// ============================================================================
+
+#[test]
+fn test_schema_column_order_and_names() {
+    let schema =
+        infer_schema("SELECT metrics.clicks, campaign.name, campaign.id FROM campaign").unwrap();
+    let names: Vec<&str> = schema.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["metrics.clicks", "campaign.name", "campaign.id"]
+    );
+}
+
+#[test]
+fn test_schema_scalar_kinds() {
+    let schema = infer_schema(
+        "SELECT campaign.id, campaign.name, metrics.ctr, customer_client.manager FROM campaign",
+    )
+    .unwrap();
+    assert!(matches!(schema[0].kind, Kind::Int64));
+    assert!(matches!(schema[1].kind, Kind::String));
+    assert!(matches!(schema[2].kind, Kind::Double));
+    assert!(matches!(schema[3].kind, Kind::Bool));
+    assert!(schema.iter().all(|c| !c.repeated));
+    assert!(schema.iter().all(|c| c.enum_descriptor.is_none()));
+}
+
+// ============================================================================
+// Enums, Repeated, Micros and Dates
+// ============================================================================
+
+#[test]
+fn test_schema_enum_descriptor() {
+    let schema = infer_schema("SELECT campaign.status, segments.device FROM campaign").unwrap();
+    let status_enum = schema[0].enum_descriptor.as_ref().unwrap();
+    assert!(status_enum
+        .full_name()
+        .ends_with("CampaignStatusEnum.CampaignStatus"));
+    assert!(status_enum.get_value_by_name("ENABLED").is_some());
+    assert!(schema[1].enum_descriptor.is_some());
+}
+
+#[test]
+fn test_schema_repeated_fields() {
+    let schema = infer_schema(
+        "SELECT campaign.labels, ad_group_ad.ad.final_urls, campaign.name FROM ad_group_ad",
+    )
+    .unwrap();
+    assert!(schema[0].repeated);
+    assert!(schema[1].repeated);
+    assert!(!schema[2].repeated);
+}
+
+#[test]
+fn test_schema_micros_fields() {
+    let schema = infer_schema(
+        "SELECT metrics.cost_micros, campaign_budget.amount_micros, metrics.average_cpc, metrics.clicks \
+         FROM campaign",
+    )
+    .unwrap();
+    assert!(schema[0].is_micros);
+    assert!(schema[1].is_micros);
+    assert!(schema[2].is_micros);
+    assert!(!schema[3].is_micros);
+}
+
+#[test]
+fn test_schema_date_fields() {
+    let schema = infer_schema(
+        "SELECT segments.date, segments.week, campaign.start_date_time, campaign.name, segments.year \
+         FROM campaign",
+    )
+    .unwrap();
+    assert!(schema[0].is_date);
+    assert!(schema[1].is_date);
+    assert!(schema[2].is_date);
+    assert!(!schema[3].is_date);
+    assert!(!schema[4].is_date);
+}
+
+// ============================================================================
+// Errors and Query API
+// ============================================================================
+
+#[test]
+fn test_schema_unknown_field() {
+    assert!(infer_schema("SELECT campaign.not_a_field FROM campaign").is_err());
+    assert!(infer_schema("SELECT not_a_resource.id FROM campaign").is_err());
+    assert!(infer_schema("SELECT campaign.id.value FROM campaign").is_err());
+}
+
+#[test]
+fn test_schema_invalid_query() {
+    assert!(infer_schema("SELECT FROM campaign").is_err());
+}
+
+#[test]
+fn test_schema_from_parsed_query() {
+    let query =
+        Query::parse("SELECT campaign.id FROM campaign WHERE campaign.name = :name").unwrap();
+    let schema = query.schema().unwrap();
+    assert_eq!(schema.len(), 1);
+    assert_eq!(schema[0].field.name(), "id");
+}
@@ -0,0 +1,356 @@
+// Tests for the in-process fake GoogleAdsService
+//
+// These tests serve FakeGoogleAdsService on a local port and exercise it through
+// a real GoogleAdsClient: canned search_stream batches matched on the normalized
+// query, scripted GoogleAdsFailure errors, broken streams, Search paging, and
+// the recording of queries and mutates.
+
+#![cfg(feature = "server")]
+
+mod mock_transport;
+
+use futures::StreamExt;
+use googleads_rs::google::ads::googleads::v23::errors::{
+    error_code, field_error_enum::FieldError, query_error_enum::QueryError,
+    quota_error_enum::QuotaError,
+};
+use googleads_rs::google::ads::googleads::v23::resources::Campaign;
+use googleads_rs::google::ads::googleads::v23::services::{
+    GoogleAdsRow, MutateGoogleAdsRequest, MutateGoogleAdsResponse, MutateOperation,
+    MutateOperationResponse, SearchGoogleAdsStreamRequest, SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::server::{failure_status, FakeGoogleAdsService};
+use googleads_rs::{GoogleAdsError, GoogleAdsInterceptor, RowStream};
+use mock_transport::block_on;
+use tonic::Code;
+
+const QUERY: &str = "SELECT campaign.id FROM campaign";
+
+fn batch(ids: &[i64]) -> SearchGoogleAdsStreamResponse {
+    SearchGoogleAdsStreamResponse {
+        results: ids
+            .iter()
+            .map(|&id| GoogleAdsRow {
+                campaign: Some(Campaign {
+                    id: Some(id),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect(),
+        field_mask: Some(prost_types::FieldMask {
+            paths: vec!["campaign.id".to_string()],
+        }),
+        ..Default::default()
+    }
+}
+
+fn interceptor() -> GoogleAdsInterceptor {
+    GoogleAdsInterceptor::new("fake-dev-token")
+        .unwrap()
+        .with_login_customer_id("9999999999")
+        .unwrap()
+}
+
+fn stream_request(query: &str) -> SearchGoogleAdsStreamRequest {
+    SearchGoogleAdsStreamRequest {
+        customer_id: "1234567890".to_string(),
+        query: query.to_string(),
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// SearchStream
+// ============================================================================
+
+#[test]
+fn test_canned_batches_end_to_end() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query(QUERY, vec![batch(&[1, 2]), batch(&[3])]);
+    let served = fake.clone();
+
+    let (ids, header_id, request_id, batches) = block_on(async move {
+        let server = served.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        let response = client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .unwrap();
+        let header_id = response
+            .metadata()
+            .get("request-id")
+            .map(|v| v.to_str().unwrap().to_string());
+        let mut rows = RowStream::from(response.into_inner());
+        let mut ids = Vec::new();
+        while let Some(row) = rows.next().await {
+            ids.push(row.unwrap().row.campaign.unwrap().id.unwrap());
+        }
+        let request_id = rows.request_id().map(str::to_string);
+        (ids, header_id, request_id, rows.batches())
+    });
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(batches, 2);
+    // The batches carry the request id of the response headers
+    assert!(header_id.as_deref().unwrap().starts_with("fake-"));
+    assert_eq!(request_id, header_id);
+
+    let queries = fake.queries();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0].method, "SearchStream");
+    assert_eq!(queries[0].customer_id, "1234567890");
+    assert_eq!(
+        queries[0].metadata.get("developer-token").unwrap(),
+        "fake-dev-token"
+    );
+    assert_eq!(
+        queries[0].metadata.get("login-customer-id").unwrap(),
+        "9999999999"
+    );
+}
+
+#[test]
+fn test_queries_match_when_normalized() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query("select campaign.id\n  from campaign", vec![batch(&[7])]);
+
+    let rows = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        let mut stream = client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .unwrap()
+            .into_inner();
+        stream.message().await.unwrap().unwrap().results.len()
+    });
+    assert_eq!(rows, 1);
+}
+
+#[test]
+fn test_unknown_query_is_unimplemented() {
+    let fake = FakeGoogleAdsService::new();
+
+    let status = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .unwrap_err()
+    });
+    assert_eq!(status.code(), Code::Unimplemented);
+    assert!(status.message().contains(QUERY), "{}", status.message());
+}
+
+// ============================================================================
+// Scripted failures
+// ============================================================================
+
+#[test]
+fn test_scripted_failure_decodes_as_google_ads_error() {
+    let fake = FakeGoogleAdsService::new();
+    fake.fail_query(
+        QUERY,
+        failure_status(
+            Code::InvalidArgument,
+            [(
+                error_code::ErrorCode::QueryError(QueryError::BadFieldName as i32),
+                "Unrecognized field",
+            )],
+        ),
+    );
+
+    let status = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .unwrap_err()
+    });
+    let error = GoogleAdsError::from_status(&status);
+    assert_eq!(error.code, Code::InvalidArgument);
+    assert!(error.has_failure());
+    assert_eq!(error.errors.len(), 1);
+    assert_eq!(error.errors[0].error_code(), "query_error.BAD_FIELD_NAME");
+    assert_eq!(error.errors[0].message, "Unrecognized field");
+    assert!(error.request_id.unwrap().starts_with("fake-"));
+}
+
+#[test]
+fn test_stream_breaks_after_batches() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query_then_fail(
+        QUERY,
+        vec![batch(&[1, 2])],
+        failure_status(
+            Code::ResourceExhausted,
+            [(
+                error_code::ErrorCode::QuotaError(QuotaError::ResourceExhausted as i32),
+                "Too many requests",
+            )],
+        ),
+    );
+
+    let (ids, error) = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        let response = client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .unwrap();
+        let mut rows = RowStream::from(response.into_inner());
+        let mut ids = Vec::new();
+        let mut error = None;
+        while let Some(row) = rows.next().await {
+            match row {
+                Ok(row) => ids.push(row.row.campaign.unwrap().id.unwrap()),
+                Err(e) => error = Some(e),
+            }
+        }
+        (ids, error.unwrap())
+    });
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(error.code, Code::ResourceExhausted);
+    assert_eq!(error.errors[0].message, "Too many requests");
+}
+
+#[test]
+fn test_fail_next_is_used_once() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query(QUERY, vec![batch(&[1])])
+        .fail_next("SearchStream", tonic::Status::unavailable("try again"));
+
+    let (first, second) = block_on(async move {
+        let server = fake.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        let first = client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .map(|_| ());
+        let second = client
+            .google_ads()
+            .search_stream(stream_request(QUERY))
+            .await
+            .map(|_| ());
+        (first, second)
+    });
+    assert_eq!(first.unwrap_err().code(), Code::Unavailable);
+    assert!(second.is_ok());
+}
+
+// ============================================================================
+// Search
+// ============================================================================
+
+#[test]
+fn test_search_paged_through_fake() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_query(QUERY, vec![batch(&[1, 2, 3]), batch(&[4, 5])])
+        .page_size(2);
+    let served = fake.clone();
+
+    let (ids, total, pages) = block_on(async move {
+        let server = served.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        let mut paged = client.search_paged("1234567890", QUERY);
+        let mut ids = Vec::new();
+        while let Some(row) = paged.next().await {
+            ids.push(row.unwrap().campaign.unwrap().id.unwrap());
+        }
+        (ids, paged.total_results_count(), paged.pages())
+    });
+    assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+    assert_eq!(total, Some(5));
+    assert_eq!(pages, 3);
+    assert!(fake.queries().iter().all(|q| q.method == "Search"));
+}
+
+// ============================================================================
+// Mutate
+// ============================================================================
+
+#[test]
+fn test_mutates_are_recorded() {
+    let fake = FakeGoogleAdsService::new();
+    let served = fake.clone();
+
+    let response = block_on(async move {
+        let server = served.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        client
+            .google_ads()
+            .mutate(MutateGoogleAdsRequest {
+                customer_id: "1234567890".to_string(),
+                mutate_operations: vec![MutateOperation::default(); 2],
+                validate_only: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+    });
+    assert_eq!(response.mutate_operation_responses.len(), 2);
+
+    let mutates = fake.mutates();
+    assert_eq!(mutates.len(), 1);
+    assert_eq!(mutates[0].request.customer_id, "1234567890");
+    assert!(mutates[0].request.validate_only);
+    assert_eq!(mutates[0].request.mutate_operations.len(), 2);
+}
+
+#[test]
+fn test_mutate_handler_and_scripted_failure() {
+    let fake = FakeGoogleAdsService::new();
+    fake.on_mutate(|request| {
+        Ok(MutateGoogleAdsResponse {
+            mutate_operation_responses: vec![
+                MutateOperationResponse::default();
+                request.mutate_operations.len() + 1
+            ],
+            ..Default::default()
+        })
+    })
+    .fail_next(
+        "Mutate",
+        failure_status(
+            Code::InvalidArgument,
+            [(
+                error_code::ErrorCode::FieldError(FieldError::Required as i32),
+                "name is required",
+            )],
+        ),
+    );
+    let served = fake.clone();
+
+    let (first, second) = block_on(async move {
+        let server = served.serve().await.unwrap();
+        let client = server.connect(interceptor()).await.unwrap();
+        let request = MutateGoogleAdsRequest {
+            customer_id: "1234567890".to_string(),
+            mutate_operations: vec![MutateOperation::default()],
+            ..Default::default()
+        };
+        let first = client.google_ads().mutate(request.clone()).await;
+        let second = client.google_ads().mutate(request).await;
+        (first, second)
+    });
+    let error = GoogleAdsError::from_status(&first.unwrap_err());
+    assert_eq!(error.errors[0].error_code(), "field_error.REQUIRED");
+    assert_eq!(
+        second
+            .unwrap()
+            .into_inner()
+            .mutate_operation_responses
+            .len(),
+        2
+    );
+    assert_eq!(fake.mutates().len(), 2);
+}
@@ -0,0 +1,134 @@
+// Local HTTP stand-in for an OAuth2 token endpoint
+//
+// TokenServer listens on 127.0.0.1 and answers every POST with the status and
+// JSON body returned by a handler, which receives the url-encoded form fields
+// of the request. Received forms are recorded for assertions.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+type Handler = dyn Fn(&HashMap<String, String>) -> (u16, String) + Send + Sync;
+
+#[derive(Clone)]
+pub struct TokenServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<HashMap<String, String>>>>,
+}
+
+impl TokenServer {
+    /// Starts the server on the current tokio runtime.
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&HashMap<String, String>) -> (u16, String) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let handler = handler.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    serve(socket, handler, recorded).await;
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Returns the form fields of every request received so far.
+    pub fn requests(&self) -> Vec<HashMap<String, String>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Builds a successful token response body.
+pub fn token_response(access_token: &str, expires_in: u64) -> (u16, String) {
+    (
+        200,
+        format!(
+            r#"{{"access_token":"{}","expires_in":{},"token_type":"Bearer"}}"#,
+            access_token, expires_in
+        ),
+    )
+}
+
+async fn serve(
+    mut socket: TcpStream,
+    handler: Arc<Handler>,
+    recorded: Arc<Mutex<Vec<HashMap<String, String>>>>,
+) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    // Read headers, then the body announced by Content-Length
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            return;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(i) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+    };
+    let headers = String::from_utf8_lossy(&buffer[..header_end]).to_lowercase();
+    let content_length: usize = headers
+        .lines()
+        .find_map(|l| l.strip_prefix("content-length:"))
+        .map(|v| v.trim().parse().unwrap())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        let n = socket.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+
+    let body = String::from_utf8_lossy(&buffer[header_end..]).to_string();
+    let form: HashMap<String, String> = body
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (decode(k), decode(v)))
+        .collect();
+    recorded.lock().unwrap().push(form.clone());
+
+    let (status, response) = handler(&form);
+    let reply = format!(
+        "HTTP/1.1 {} OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    );
+    socket.write_all(reply.as_bytes()).await.unwrap();
+    let _ = socket.shutdown().await;
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.replace('+', " ").into_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+            out.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
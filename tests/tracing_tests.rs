@@ -0,0 +1,209 @@
+// Tests for the tracing instrumentation of RPCs
+//
+// These tests verify that TracingLayer opens one span per call named after the
+// RPC, and records customer id, query hash, request id, resource consumption,
+// row count and error codes as span fields.
+
+#![cfg(feature = "tracing")]
+
+mod mock_transport;
+
+use googleads_rs::error::GOOGLE_ADS_FAILURE_METADATA_KEY;
+use googleads_rs::google::ads::googleads::v23::errors::{
+    error_code, query_error_enum::QueryError, ErrorCode, GoogleAdsError as GoogleAdsErrorProto,
+    GoogleAdsFailure,
+};
+use googleads_rs::google::ads::googleads::v23::services::{
+    google_ads_service_client::GoogleAdsServiceClient, GoogleAdsRow, SearchGoogleAdsRequest,
+    SearchGoogleAdsStreamRequest, SearchGoogleAdsStreamResponse,
+};
+use googleads_rs::telemetry::{query_hash, TracingLayer};
+use mock_transport::{block_on, encode, MockTransport};
+use prost::Message;
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+use tower::Layer;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// Records the fields of every span; tests share it, so each one looks up its own
+// customer id
+struct CaptureSubscriber;
+
+static SPANS: Mutex<Vec<(String, HashMap<String, String>)>> = Mutex::new(Vec::new());
+static INIT: Once = Once::new();
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl Subscriber for CaptureSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        let mut spans = SPANS.lock().unwrap();
+        spans.push((span.metadata().name().to_string(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = SPANS.lock().unwrap();
+        let fields = &mut spans[span.into_u64() as usize - 1].1;
+        values.record(&mut FieldVisitor(fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+fn capture() {
+    INIT.call_once(|| tracing::subscriber::set_global_default(CaptureSubscriber).unwrap());
+}
+
+fn span_for(customer_id: &str) -> (String, HashMap<String, String>) {
+    let spans = SPANS.lock().unwrap();
+    let matching: Vec<_> = spans
+        .iter()
+        .filter(|(_, fields)| fields.get("customer_id").map(String::as_str) == Some(customer_id))
+        .cloned()
+        .collect();
+    assert_eq!(matching.len(), 1, "{:?}", matching);
+    matching.into_iter().next().unwrap()
+}
+
+fn batch(rows: usize, consumption: i64) -> SearchGoogleAdsStreamResponse {
+    SearchGoogleAdsStreamResponse {
+        results: vec![GoogleAdsRow::default(); rows],
+        query_resource_consumption: consumption,
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// Spans
+// ============================================================================
+
+#[test]
+fn test_search_stream_span() {
+    capture();
+    let transport =
+        MockTransport::new(|_path, _body| Ok(vec![encode(&batch(3, 40)), encode(&batch(2, 25))]))
+            .response_header("request-id", "req-stream");
+    let mut client = GoogleAdsServiceClient::new(TracingLayer::new().layer(transport));
+    let query = "SELECT campaign.id FROM campaign";
+
+    let rows = block_on(async move {
+        let mut stream = client
+            .search_stream(SearchGoogleAdsStreamRequest {
+                customer_id: "4040404040".to_string(),
+                query: query.to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut rows = 0;
+        while let Some(batch) = stream.message().await.unwrap() {
+            rows += batch.results.len();
+        }
+        rows
+    });
+    assert_eq!(rows, 5);
+
+    let (name, fields) = span_for("4040404040");
+    assert_eq!(name, "rpc");
+    assert_eq!(fields["otel.name"], "GoogleAdsService/SearchStream");
+    assert_eq!(
+        fields["rpc.service"],
+        "google.ads.googleads.v23.services.GoogleAdsService"
+    );
+    assert_eq!(fields["rpc.method"], "SearchStream");
+    assert_eq!(fields["query_hash"], query_hash(query));
+    assert_eq!(fields["request_id"], "req-stream");
+    assert_eq!(fields["rows"], "5");
+    assert_eq!(fields["query_resource_consumption"], "65");
+    assert_eq!(fields["rpc.grpc.status_code"], "0");
+    assert!(!fields.contains_key("error_codes"));
+}
+
+#[test]
+fn test_failed_call_records_error_codes() {
+    capture();
+    let transport = MockTransport::new(|_path, _body| {
+        let failure = GoogleAdsFailure {
+            errors: vec![GoogleAdsErrorProto {
+                error_code: Some(ErrorCode {
+                    error_code: Some(error_code::ErrorCode::QueryError(
+                        QueryError::BadFieldName as i32,
+                    )),
+                }),
+                ..Default::default()
+            }],
+            request_id: "req-failed".to_string(),
+        };
+        let mut metadata = MetadataMap::new();
+        metadata.insert_bin(
+            GOOGLE_ADS_FAILURE_METADATA_KEY,
+            tonic::metadata::MetadataValue::from_bytes(&failure.encode_to_vec()),
+        );
+        Err(Status::with_metadata(
+            Code::InvalidArgument,
+            "bad field",
+            metadata,
+        ))
+    });
+    let mut client = GoogleAdsServiceClient::new(TracingLayer::new().layer(transport));
+
+    let result = block_on(async move {
+        client
+            .search(SearchGoogleAdsRequest {
+                customer_id: "5050505050".to_string(),
+                query: "SELECT campaign.nope FROM campaign".to_string(),
+                ..Default::default()
+            })
+            .await
+    });
+    assert!(result.is_err());
+
+    let (_, fields) = span_for("5050505050");
+    assert_eq!(fields["otel.name"], "GoogleAdsService/Search");
+    assert_eq!(fields["rpc.grpc.status_code"], "3");
+    assert_eq!(fields["error_codes"], "query_error.BAD_FIELD_NAME");
+    assert_eq!(fields["request_id"], "req-failed");
+}
+
+// ============================================================================
+// Query hash
+// ============================================================================
+
+#[test]
+fn test_query_hash_ignores_formatting() {
+    let hash = query_hash("SELECT campaign.id, campaign.name FROM campaign");
+    assert_eq!(hash.len(), 16);
+    assert_eq!(
+        hash,
+        query_hash("select   campaign.id,\n  campaign.name\nfrom campaign")
+    );
+    assert_ne!(hash, query_hash("SELECT campaign.id FROM campaign"));
+}
@@ -72,8 +72,9 @@ safe_run rm -rf googleapis-master master.zip
 # Update build.rs
 safe_run sed_inplace "s/googleads{}$current_version/googleads{}$GOOGLEADS_API_VERSION/g" build.rs
 
-# Update src/lib.rs
-safe_run sed_inplace "s/googleads::$current_version/googleads::$GOOGLEADS_API_VERSION/g" src/lib.rs
+# Update src/*.rs
+safe_run sed_inplace "s/googleads::$current_version/googleads::$GOOGLEADS_API_VERSION/g" src/*.rs
+safe_run sed_inplace "s/googleads\.$current_version/googleads.$GOOGLEADS_API_VERSION/g" src/*.rs
 
 # Update tests/*.rs
 safe_run sed_inplace "s/googleads::$current_version/googleads::$GOOGLEADS_API_VERSION/g" tests/*.rs